@@ -0,0 +1,19 @@
+// Regenerates the C header for the `ffi` feature, nothing to do otherwise
+
+fn main() {
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+
+        // Only the C API, the rest of the crate isn't meant for C
+        let crate_dir = std::path::PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+        let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(crate_dir.join("src/ffi.rs"))
+            .generate()
+            .expect("failed to generate the C header")
+            .write_to_file(crate_dir.join("include/chip8.h"));
+    }
+}
@@ -0,0 +1,47 @@
+// Runs a ROM for a second without a window and prints the screen, using the C API.
+//
+//   cargo build --release --features ffi
+//   cc examples/c/ascii.c -Iinclude -Ltarget/release -lchip8_emu -o ascii
+//   LD_LIBRARY_PATH=target/release ./ascii roms/IBM.ch8
+
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "chip8.h"
+
+int main(int argc, char **argv) {
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s ROM\n", argv[0]);
+        return 1;
+    }
+
+    FILE *file = fopen(argv[1], "rb");
+    if (!file) {
+        perror(argv[1]);
+        return 1;
+    }
+    unsigned char rom[4096];
+    size_t len = fread(rom, 1, sizeof rom, file);
+    fclose(file);
+
+    Chip8 *emu = chip8_new();
+    if (!chip8_load_rom(emu, rom, len)) {
+        fprintf(stderr, "%s\n", chip8_last_error());
+        chip8_free(emu);
+        return 1;
+    }
+    for (int frame = 0; frame < 60 && chip8_tick(emu, 0); frame++) {
+    }
+
+    uint32_t width, height;
+    const bool *pixels = chip8_framebuffer(emu, &width, &height);
+    for (uint32_t y = 0; y < height; y++) {
+        for (uint32_t x = 0; x < width; x++) {
+            putchar(pixels[y * width + x] ? '#' : ' ');
+        }
+        putchar('\n');
+    }
+
+    chip8_free(emu);
+    return 0;
+}
@@ -0,0 +1,77 @@
+// Serve the crate root (e.g. `python3 -m http.server`) after
+// `wasm-pack build --target web -- --no-default-features --features wasm`
+// and open http://localhost:8000/examples/web/
+import init, { Emulator } from "../../pkg/chip8_emu.js";
+
+// Same layout as the SDL frontend, indexed by keypad key
+const KEYS = ["x", "1", "2", "3", "q", "w", "e", "a", "s", "d", "z", "c", "4", "r", "f", "v"];
+
+await init();
+const emu = new Emulator();
+const canvas = document.getElementById("screen");
+const context = canvas.getContext("2d");
+const status = document.getElementById("status");
+let running = false;
+
+document.getElementById("rom").addEventListener("change", async (event) => {
+  const file = event.target.files[0];
+  try {
+    emu.load_rom(new Uint8Array(await file.arrayBuffer()));
+    status.textContent = file.name;
+    running = true;
+  } catch (e) {
+    status.textContent = `Failed to load ${file.name}: ${e.message}`;
+  }
+});
+
+for (const [type, pressed] of [["keydown", true], ["keyup", false]]) {
+  document.addEventListener(type, (event) => {
+    const key = KEYS.indexOf(event.key.toLowerCase());
+    if (key >= 0) {
+      emu.key_event(key, pressed);
+    }
+  });
+}
+
+function draw() {
+  const width = emu.width();
+  const height = emu.height();
+  if (canvas.width !== width || canvas.height !== height) {
+    canvas.width = width;
+    canvas.height = height;
+  }
+
+  const pixels = emu.framebuffer();
+  const image = context.createImageData(width, height);
+  for (let i = 0; i < pixels.length; i++) {
+    const value = pixels[i] ? 255 : 0;
+    image.data.set([value, value, value, 255], i * 4);
+  }
+  context.putImageData(image, 0, 0);
+}
+
+// requestAnimationFrame runs at the display's refresh rate, step at 60 Hz regardless
+const FRAME_MS = 1000 / 60;
+let last = performance.now();
+let lag = 0;
+
+function frame(now) {
+  // Cap the catch-up after the tab was in the background
+  lag = Math.min(lag + now - last, 250);
+  last = now;
+  if (running) {
+    try {
+      for (; lag >= FRAME_MS; lag -= FRAME_MS) {
+        emu.tick();
+      }
+      draw();
+    } catch (e) {
+      status.textContent = e.message;
+      running = false;
+    }
+  } else {
+    lag = 0;
+  }
+  requestAnimationFrame(frame);
+}
+requestAnimationFrame(frame);
@@ -0,0 +1,52 @@
+/*
+ * C API of chip8_emu. Build the library with `cargo build --release --features ffi` and link
+ * against target/release/libchip8_emu.so (chip8_emu.dll, libchip8_emu.dylib).
+ *
+ *   Chip8 *emu = chip8_new();
+ *   chip8_load_rom(emu, rom, rom_len);
+ *   while (chip8_tick(emu, 0)) {            // 60 times a second
+ *       uint32_t width, height;
+ *       const bool *pixels = chip8_framebuffer(emu, &width, &height);
+ *       ...draw, then chip8_keypress(emu, key, pressed) for input...
+ *   }
+ *   chip8_free(emu);
+ */
+
+#ifndef CHIP8_H
+#define CHIP8_H
+
+/* Generated from src/ffi.rs by cbindgen, don't edit by hand */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct Chip8 Chip8;
+
+#define CHIP8_DEFAULT_TICKS_PER_FRAME 10
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+Chip8 *chip8_new(void);
+
+void chip8_free(Chip8 *emu);
+
+bool chip8_load_rom(Chip8 *emu, const uint8_t *data, size_t len);
+
+bool chip8_tick(Chip8 *emu, uint32_t ticks_per_frame);
+
+const bool *chip8_framebuffer(const Chip8 *emu, uint32_t *width, uint32_t *height);
+
+bool chip8_keypress(Chip8 *emu, uint8_t key, bool pressed);
+
+bool chip8_sound_active(const Chip8 *emu);
+
+const char *chip8_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CHIP8_H */
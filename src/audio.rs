@@ -0,0 +1,196 @@
+use std::collections::VecDeque;
+
+pub const DEFAULT_FREQUENCY: f32 = 440.0;
+pub const DEFAULT_VOLUME: f32 = 0.25;
+// XO-CHIP's sound pattern, 128 one-bit samples, and the FX3A pitch they play at 4000 Hz with
+pub const PATTERN_SIZE: usize = 16;
+pub const DEFAULT_PITCH: u8 = 64;
+const PATTERN_BITS: f32 = (PATTERN_SIZE * 8) as f32;
+const TIMER_HZ: u32 = 60;
+// Frames rendered by nobody, e.g. headless runs or a stalled audio device, are dropped past
+// this so the sound can't lag further and further behind the sound timer
+const MAX_QUEUED_FRAMES: usize = 4;
+
+// What the sound hardware was doing during one 60 Hz timer frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSound {
+    pub active: bool,
+    pub pattern: Option<[u8; PATTERN_SIZE]>,
+    pub pitch: u8,
+}
+
+// The playback rate of an XO-CHIP pattern, in bits per second
+pub fn pattern_rate(pitch: u8) -> f32 {
+    4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0)
+}
+
+#[derive(Debug, Clone)]
+pub struct Buzzer {
+    pub frequency: f32,
+    pub volume: f32,
+    // The M hotkey, the sound timer keeps running and only the output goes quiet
+    pub muted: bool,
+    // Position in the current cycle, 0.0-1.0, a square wave period or the whole pattern
+    phase: f32,
+    frames: VecDeque<FrameSound>,
+    current: Option<FrameSound>,
+    // Samples the current frame still has to produce, fractional so 44.1 kHz averages out
+    owed: f32,
+}
+
+impl Default for Buzzer {
+    fn default() -> Self {
+        Self {
+            frequency: DEFAULT_FREQUENCY,
+            volume: DEFAULT_VOLUME,
+            muted: false,
+            phase: 0.0,
+            frames: VecDeque::new(),
+            current: None,
+            owed: 0.0,
+        }
+    }
+}
+
+impl Buzzer {
+    // Queues a finished timer frame, each one is played for 1/60 s by `render`
+    pub fn push_frame(&mut self, frame: FrameSound) {
+        if self.frames.len() == MAX_QUEUED_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    // Drops queued frames, e.g. after a reset or a loaded state
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.current = None;
+        self.owed = 0.0;
+        self.phase = 0.0;
+    }
+
+    // Samples the queued frames still owe at `sample_rate`
+    pub fn owed_samples(&self, sample_rate: u32) -> usize {
+        let queued = self.frames.len() as f32 * sample_rate as f32 / TIMER_HZ as f32;
+        (self.owed.max(0.0) + queued) as usize
+    }
+
+    // Fills `out` with the queued frames, each lasting 1/60 s, and silence once they run out.
+    // The phase is kept between calls so consecutive buffers join up without clicks.
+    pub fn render(&mut self, out: &mut [f32], sample_rate: u32) {
+        if sample_rate == 0 {
+            out.fill(0.0);
+            return;
+        }
+
+        let frame_samples = sample_rate as f32 / TIMER_HZ as f32;
+        for sample in out.iter_mut() {
+            if self.owed < 1.0 {
+                self.current = self.frames.pop_front();
+                self.owed = if self.current.is_some() {
+                    self.owed + frame_samples
+                } else {
+                    0.0
+                };
+            }
+            *sample = match self.current {
+                Some(frame) => {
+                    self.owed -= 1.0;
+                    self.sample(frame, sample_rate)
+                }
+                None => {
+                    self.phase = 0.0;
+                    0.0
+                }
+            };
+        }
+    }
+
+    fn sample(&mut self, frame: FrameSound, sample_rate: u32) -> f32 {
+        if !frame.active || self.muted {
+            self.phase = 0.0;
+            return 0.0;
+        }
+
+        let (high, step) = match frame.pattern {
+            Some(pattern) => {
+                let bit = (self.phase * PATTERN_BITS) as usize;
+                let high = pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+                (high, pattern_rate(frame.pitch) / PATTERN_BITS)
+            }
+            None => (self.phase < 0.5, self.frequency),
+        };
+        self.phase = (self.phase + step / sample_rate as f32) % 1.0;
+        if high { self.volume } else { -self.volume }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BEEP: FrameSound = FrameSound {
+        active: true,
+        pattern: None,
+        pitch: DEFAULT_PITCH,
+    };
+    const QUIET: FrameSound = FrameSound {
+        active: false,
+        ..BEEP
+    };
+
+    #[test]
+    fn each_frame_plays_for_a_sixtieth_of_a_second() {
+        let mut buzzer = Buzzer::default();
+        buzzer.push_frame(BEEP);
+        buzzer.push_frame(QUIET);
+        assert_eq!(buzzer.owed_samples(600), 20);
+
+        let mut out = [1.0; 30];
+        buzzer.render(&mut out, 600);
+        assert!(out[..10].iter().all(|&s| s != 0.0));
+        // The quiet frame, then nothing left to play
+        assert!(out[10..].iter().all(|&s| s == 0.0));
+        assert_eq!(buzzer.owed_samples(600), 0);
+    }
+
+    #[test]
+    fn a_frame_split_across_buffers_keeps_its_length() {
+        let mut buzzer = Buzzer::default();
+        buzzer.push_frame(BEEP);
+        let mut out = [0.0; 4];
+        buzzer.render(&mut out, 600);
+        assert_eq!(buzzer.owed_samples(600), 6);
+        let mut out = [0.0; 8];
+        buzzer.render(&mut out, 600);
+        assert!(out[..6].iter().all(|&s| s != 0.0));
+        assert!(out[6..].iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn patterns_play_their_bits_at_the_pitch() {
+        let mut pattern = [0; PATTERN_SIZE];
+        pattern[0] = 0b1010_0000;
+        let mut buzzer = Buzzer::default();
+        buzzer.push_frame(FrameSound {
+            pattern: Some(pattern),
+            ..BEEP
+        });
+
+        // At the default pitch one sample per bit
+        let mut out = [0.0; 4];
+        buzzer.render(&mut out, 4000);
+        let volume = buzzer.volume;
+        assert_eq!(out, [volume, -volume, volume, -volume]);
+        assert_eq!(pattern_rate(DEFAULT_PITCH + 48), 8000.0);
+    }
+
+    #[test]
+    fn unplayed_frames_are_dropped() {
+        let mut buzzer = Buzzer::default();
+        for _ in 0..10 {
+            buzzer.push_frame(BEEP);
+        }
+        assert_eq!(buzzer.owed_samples(60), MAX_QUEUED_FRAMES);
+    }
+}
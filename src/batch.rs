@@ -0,0 +1,208 @@
+// Runs a whole ROM directory headlessly across worker threads and collects a compatibility
+// report that can be diffed between releases.
+
+use std::fmt;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use serde_json::{Value, json};
+
+use crate::Chip8;
+use crate::crash_dump;
+use crate::playlist::Playlist;
+
+pub const DEFAULT_FRAMES: u64 = 600;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchOutcome {
+    Ok,
+    // The ROM ended up in a jump to itself, the usual way CHIP-8 programs stop
+    Halted { pc: u16 },
+    Error(String),
+    UnknownOpcode { pc: u16, opcode: u16 },
+}
+
+impl BatchOutcome {
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            BatchOutcome::Error(_) | BatchOutcome::UnknownOpcode { .. }
+        )
+    }
+
+    pub fn status(&self) -> &'static str {
+        match self {
+            BatchOutcome::Ok => "ok",
+            BatchOutcome::Halted { .. } => "halted",
+            BatchOutcome::Error(_) => "error",
+            BatchOutcome::UnknownOpcode { .. } => "unknown-opcode",
+        }
+    }
+
+    pub fn details(&self) -> String {
+        match self {
+            BatchOutcome::Ok => String::new(),
+            BatchOutcome::Halted { pc } => format!("halted at {:#05x}", pc),
+            BatchOutcome::Error(e) => e.clone(),
+            BatchOutcome::UnknownOpcode { pc, opcode } => {
+                format!("{:#06x} at {:#05x}", opcode, pc)
+            }
+        }
+    }
+}
+
+impl fmt::Display for BatchOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchOutcome::Ok => write!(f, "ok"),
+            _ => write!(f, "{}: {}", self.status(), self.details()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchResult {
+    pub rom: String,
+    pub rom_hash: Option<String>,
+    pub outcome: BatchOutcome,
+    pub frames: u64,
+    pub instructions: u64,
+}
+
+fn is_self_jump(pc: u16, opcode: u16) -> bool {
+    opcode & 0xF000 == 0x1000 && opcode & 0x0FFF == pc
+}
+
+pub fn run_rom(rom: &str, frames: u64, ticks_per_frame: usize) -> BatchResult {
+    let mut emu = Chip8::new();
+    let mut result = BatchResult {
+        rom: rom.to_string(),
+        rom_hash: None,
+        outcome: BatchOutcome::Ok,
+        frames: 0,
+        instructions: 0,
+    };
+
+    if let Err(e) = emu.load_rom(rom) {
+        result.outcome = BatchOutcome::Error(format!("load error: {}", e));
+        return result;
+    }
+    result.rom_hash = emu.rom_hash.clone();
+
+    let run = panic::catch_unwind(AssertUnwindSafe(|| {
+        while result.frames < frames {
+            result.instructions += emu.run_cycles(ticks_per_frame);
+            if let Some(error) = emu.halted.take() {
+                return BatchOutcome::Error(error.to_string());
+            }
+            emu.tick_timers();
+            result.frames += 1;
+
+            if let Some(&(pc, opcode)) = emu.history.back()
+                && is_self_jump(pc, opcode)
+            {
+                return BatchOutcome::Halted { pc };
+            }
+        }
+        BatchOutcome::Ok
+    }));
+
+    result.outcome = match run {
+        Ok(outcome) => outcome,
+        Err(payload) => {
+            let message = crash_dump::panic_message(payload.as_ref());
+            match emu.history.back() {
+                Some(&(pc, opcode)) if message.contains("Unimplemented opcode") => {
+                    BatchOutcome::UnknownOpcode { pc, opcode }
+                }
+                _ => BatchOutcome::Error(message.to_string()),
+            }
+        }
+    };
+
+    result
+}
+
+// Results come back in directory order regardless of which worker finished first
+pub fn run_dir(
+    dir: &str,
+    frames: u64,
+    ticks_per_frame: usize,
+    workers: usize,
+) -> io::Result<Vec<BatchResult>> {
+    let playlist = Playlist::from_dir(dir)?;
+    let roms = playlist.roms();
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; roms.len()]);
+
+    // Panics are expected and reported per ROM, keep the default hook from spamming stderr
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(rom) = roms.get(i) else {
+                        break;
+                    };
+                    let result = run_rom(rom, frames, ticks_per_frame);
+                    results.lock().unwrap()[i] = Some(result);
+                }
+            });
+        }
+    });
+
+    panic::set_hook(hook);
+
+    Ok(results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn to_csv(results: &[BatchResult]) -> String {
+    let mut csv = String::from("rom,sha1,status,details,frames,instructions\n");
+    for result in results {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            csv_field(&result.rom),
+            result.rom_hash.as_deref().unwrap_or(""),
+            result.outcome.status(),
+            csv_field(&result.outcome.details()),
+            result.frames,
+            result.instructions,
+        ));
+    }
+    csv
+}
+
+pub fn to_json(results: &[BatchResult]) -> Value {
+    results
+        .iter()
+        .map(|result| {
+            json!({
+                "rom": result.rom,
+                "sha1": result.rom_hash,
+                "status": result.outcome.status(),
+                "details": result.outcome.details(),
+                "frames": result.frames,
+                "instructions": result.instructions,
+            })
+        })
+        .collect()
+}
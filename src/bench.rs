@@ -0,0 +1,80 @@
+// Runs a ROM headlessly as fast as it goes and times every frame, for measuring what a change to
+// the interpreter costs.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::Chip8;
+use crate::error::Chip8Error;
+
+pub const DEFAULT_FRAMES: u64 = 10_000;
+
+pub struct BenchReport {
+    pub frames: u64,
+    pub instructions: u64,
+    pub elapsed: Duration,
+    // Sorted, shortest first
+    pub frame_times: Vec<Duration>,
+}
+
+impl BenchReport {
+    pub fn instructions_per_second(&self) -> f64 {
+        self.instructions as f64 / self.elapsed.as_secs_f64()
+    }
+
+    // The frame time `percent` of frames stayed under, nearest rank
+    pub fn percentile(&self, percent: f64) -> Duration {
+        if self.frame_times.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = (percent / 100.0 * self.frame_times.len() as f64).ceil() as usize;
+        self.frame_times[rank.clamp(1, self.frame_times.len()) - 1]
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} frames, {} instructions in {:.3}s",
+            self.frames,
+            self.instructions,
+            self.elapsed.as_secs_f64()
+        )?;
+        writeln!(f, "{:.0} instructions/s", self.instructions_per_second())?;
+        write!(f, "frame time")?;
+        for (name, percent) in [("p50", 50.0), ("p90", 90.0), ("p99", 99.0), ("max", 100.0)] {
+            let micros = self.percentile(percent).as_secs_f64() * 1e6;
+            write!(f, "  {} {:.1}us", name, micros)?;
+        }
+        Ok(())
+    }
+}
+
+// A fixed seed and no input, so two runs execute the same instructions
+pub fn run(rom: &str, frames: u64, ticks_per_frame: usize) -> Result<BenchReport, Chip8Error> {
+    let mut emu = Chip8::with_seed(0);
+    emu.load_rom(rom)?;
+
+    let mut instructions = 0;
+    let mut frame_times = Vec::with_capacity(frames as usize);
+    let start = Instant::now();
+    for _ in 0..frames {
+        let frame_start = Instant::now();
+        instructions += emu.run_cycles(ticks_per_frame);
+        if let Some(error) = emu.halted.take() {
+            return Err(error);
+        }
+        emu.tick_timers();
+        frame_times.push(frame_start.elapsed());
+    }
+    let elapsed = start.elapsed();
+
+    frame_times.sort();
+    Ok(BenchReport {
+        frames,
+        instructions,
+        elapsed,
+        frame_times,
+    })
+}
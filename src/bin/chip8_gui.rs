@@ -0,0 +1,547 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use chip8_emu::Chip8;
+use chip8_emu::debugger;
+use chip8_emu::quirks::Quirks;
+use chip8_emu::state::{self, SaveState};
+use eframe::egui;
+
+const FOREGROUND: [u8; 4] = [255, 255, 255, 255];
+const BACKGROUND: [u8; 4] = [0, 0, 0, 255];
+// The GUI keeps a single save state per ROM
+const SAVE_SLOT: usize = 0;
+const DEBUGGER_HISTORY: usize = 16;
+
+const KEYMAP: [(egui::Key, usize); 16] = [
+    (egui::Key::Num1, 0x1),
+    (egui::Key::Num2, 0x2),
+    (egui::Key::Num3, 0x3),
+    (egui::Key::Num4, 0xC),
+    (egui::Key::Q, 0x4),
+    (egui::Key::W, 0x5),
+    (egui::Key::E, 0x6),
+    (egui::Key::R, 0xD),
+    (egui::Key::A, 0x7),
+    (egui::Key::S, 0x8),
+    (egui::Key::D, 0x9),
+    (egui::Key::F, 0xE),
+    (egui::Key::Z, 0xA),
+    (egui::Key::X, 0x0),
+    (egui::Key::C, 0xB),
+    (egui::Key::V, 0xF),
+];
+
+#[derive(Debug, Clone, Copy)]
+enum Command {
+    OpenRom,
+    Reset,
+    TogglePause,
+    Settings,
+    SetSpeed(usize),
+    // Flips one `Quirks` flag, named for the status line
+    ToggleQuirk(&'static str, fn(&mut Quirks) -> &mut bool),
+    SaveState,
+    LoadState,
+    Debugger,
+    Quit,
+}
+
+const COMMANDS: [(&str, Command); 19] = [
+    ("Open ROM...", Command::OpenRom),
+    ("Reset", Command::Reset),
+    ("Pause / Resume", Command::TogglePause),
+    ("Save state", Command::SaveState),
+    ("Load state", Command::LoadState),
+    ("Debugger...", Command::Debugger),
+    ("Settings...", Command::Settings),
+    (
+        "Quirk: shifts use VY (8XY6/8XYE)",
+        Command::ToggleQuirk("Shifts use VY", |quirks| &mut quirks.shift_uses_vy),
+    ),
+    (
+        "Quirk: FX55/FX65 move I",
+        Command::ToggleQuirk("FX55/FX65 move I", |quirks| {
+            &mut quirks.load_store_increments_index
+        }),
+    ),
+    (
+        "Quirk: BNNN jumps to XNN + VX",
+        Command::ToggleQuirk("BNNN jumps to XNN + VX", |quirks| &mut quirks.jump_uses_vx),
+    ),
+    (
+        "Quirk: 8XY1/8XY2/8XY3 clear VF",
+        Command::ToggleQuirk("8XY1/8XY2/8XY3 clear VF", |quirks| {
+            &mut quirks.logic_resets_vf
+        }),
+    ),
+    (
+        "Quirk: clip sprites at the edge",
+        Command::ToggleQuirk("Clip sprites", |quirks| &mut quirks.clip_sprites),
+    ),
+    (
+        "Quirk: DRW waits for the frame",
+        Command::ToggleQuirk("DRW waits for the frame", |quirks| &mut quirks.display_wait),
+    ),
+    (
+        "Quirk: FX0A waits for the key release",
+        Command::ToggleQuirk("FX0A waits for the release", |quirks| {
+            &mut quirks.wait_key_release
+        }),
+    ),
+    ("Speed: Slow (5 ticks per frame)", Command::SetSpeed(5)),
+    ("Speed: Normal (10 ticks per frame)", Command::SetSpeed(10)),
+    ("Speed: Fast (20 ticks per frame)", Command::SetSpeed(20)),
+    ("Speed: Turbo (50 ticks per frame)", Command::SetSpeed(50)),
+    ("Quit", Command::Quit),
+];
+
+// Subsequence match, rewarding consecutive characters and matches at the start of words
+fn fuzzy_score(query: &str, label: &str) -> Option<i32> {
+    let mut score = 0;
+    let mut previous_match = None;
+    let mut chars = label.char_indices().peekable();
+
+    for wanted in query.chars().filter(|c| !c.is_whitespace()) {
+        let wanted = wanted.to_ascii_lowercase();
+        let (i, _) = chars
+            .by_ref()
+            .find(|(_, c)| c.to_ascii_lowercase() == wanted)?;
+
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == i) {
+            score += 5;
+        }
+        if i == 0 || label[..i].ends_with([' ', '(', '/']) {
+            score += 3;
+        }
+        previous_match = Some(i);
+    }
+
+    Some(score)
+}
+
+struct GuiApp {
+    emu: Chip8,
+    rom: Option<PathBuf>,
+    paused: bool,
+    ticks_per_frame: usize,
+    show_settings: bool,
+    show_debugger: bool,
+    show_palette: bool,
+    palette_query: String,
+    palette_selected: usize,
+    error: Option<String>,
+    // The last thing worth telling the player, like what the ROM loader found
+    status: Option<String>,
+    // Quirks picked in the palette, kept over resets in place of the ROM database's
+    quirks: Option<Quirks>,
+    screen: Option<egui::TextureHandle>,
+    last_frame: Instant,
+    lag: Duration,
+}
+
+impl GuiApp {
+    fn new() -> Self {
+        Self {
+            emu: Chip8::new(),
+            rom: None,
+            paused: false,
+            ticks_per_frame: 10,
+            show_settings: false,
+            show_debugger: false,
+            show_palette: false,
+            palette_query: String::new(),
+            palette_selected: 0,
+            error: None,
+            status: None,
+            quirks: None,
+            screen: None,
+            last_frame: Instant::now(),
+            lag: Duration::ZERO,
+        }
+    }
+
+    fn open_rom(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("CHIP-8 ROM", &["ch8", "c8", "hex", "txt", "gif"])
+            .add_filter("All files", &["*"])
+            .pick_file()
+        {
+            self.rom = Some(path);
+            self.reset();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.emu = Chip8::new();
+        if let Some(quirks) = self.quirks {
+            self.emu.quirks = quirks;
+            self.emu.rom_database.quirks = false;
+        }
+        // A halted ROM was paused, starting it over runs it again
+        if self.error.take().is_some() {
+            self.paused = false;
+        }
+        self.status = None;
+
+        if let Some(rom) = &self.rom {
+            match self.emu.load_rom(&rom.to_string_lossy()) {
+                Ok(report) => self.status = Some(format!("{}: {}", rom.display(), report)),
+                Err(e) => {
+                    self.error = Some(format!("Failed to load {}: {}", rom.display(), e));
+                    self.rom = None;
+                }
+            }
+        }
+    }
+
+    fn run_command(&mut self, ctx: &egui::Context, command: Command) {
+        match command {
+            Command::OpenRom => self.open_rom(),
+            Command::Reset => self.reset(),
+            Command::TogglePause => self.paused = !self.paused,
+            Command::Settings => self.show_settings = true,
+            Command::SetSpeed(ticks) => self.ticks_per_frame = ticks,
+            Command::ToggleQuirk(name, flag) => {
+                let mut quirks = self.emu.quirks;
+                let on = flag(&mut quirks);
+                *on = !*on;
+                self.status = Some(format!("{}: {}", name, if *on { "on" } else { "off" }));
+                self.emu.quirks = quirks;
+                self.quirks = Some(quirks);
+            }
+            Command::SaveState => self.save_state(),
+            Command::LoadState => self.load_state(),
+            Command::Debugger => self.show_debugger = true,
+            Command::Quit => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+        }
+    }
+
+    fn save_state(&mut self) {
+        let Some(hash) = self.emu.rom_hash.clone() else {
+            self.status = Some("No ROM running, nothing to save".to_string());
+            return;
+        };
+        if !self.emu.supports_save_states() {
+            self.status = Some("Save states don't work with MegaChip ROMs".to_string());
+            return;
+        }
+
+        let path = state::slot_path(&hash, SAVE_SLOT);
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| self.emu.save_state().write(&path));
+        self.status = Some(match result {
+            Ok(()) => format!("State saved to {}", path.display()),
+            Err(e) => format!("Failed to save state {}: {}", path.display(), e),
+        });
+    }
+
+    fn load_state(&mut self) {
+        let Some(hash) = self.emu.rom_hash.clone() else {
+            self.status = Some("No ROM running, nothing to load".to_string());
+            return;
+        };
+
+        let path = state::slot_path(&hash, SAVE_SLOT);
+        match SaveState::read(&path) {
+            Ok(state) => {
+                self.emu.load_state(&state);
+                self.status = Some(format!("State loaded from {}", path.display()));
+            }
+            Err(e) => self.status = Some(format!("Failed to load state {}: {}", path.display(), e)),
+        }
+    }
+
+    fn command_palette(&mut self, ctx: &egui::Context) {
+        if ctx.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::P)) {
+            self.show_palette = !self.show_palette;
+            self.palette_query.clear();
+            self.palette_selected = 0;
+        }
+        if !self.show_palette {
+            return;
+        }
+
+        let mut matches: Vec<_> = COMMANDS
+            .iter()
+            .filter_map(|&(label, command)| {
+                fuzzy_score(&self.palette_query, label).map(|score| (score, label, command))
+            })
+            .collect();
+        matches.sort_by_key(|&(score, _, _)| std::cmp::Reverse(score));
+
+        let (up, down, enter, escape) = ctx.input_mut(|input| {
+            (
+                input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                input.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                input.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        if up {
+            self.palette_selected = self.palette_selected.saturating_sub(1);
+        }
+        if down {
+            self.palette_selected += 1;
+        }
+        self.palette_selected = self.palette_selected.min(matches.len().saturating_sub(1));
+
+        let mut chosen = None;
+        egui::Window::new("Command palette")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                let query = ui.add(
+                    egui::TextEdit::singleline(&mut self.palette_query)
+                        .hint_text("Type a command...")
+                        .desired_width(320.0),
+                );
+                query.request_focus();
+                if query.changed() {
+                    self.palette_selected = 0;
+                }
+
+                for (i, &(_, label, command)) in matches.iter().enumerate() {
+                    if ui
+                        .selectable_label(i == self.palette_selected, label)
+                        .clicked()
+                    {
+                        chosen = Some(command);
+                    }
+                }
+                if matches.is_empty() {
+                    ui.weak("No matching commands");
+                }
+            });
+
+        if enter {
+            chosen = chosen.or(matches
+                .get(self.palette_selected)
+                .map(|&(_, _, command)| command));
+        }
+        if escape || chosen.is_some() {
+            self.show_palette = false;
+        }
+        if let Some(command) = chosen {
+            self.run_command(ctx, command);
+        }
+    }
+
+    fn menu_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            egui::MenuBar::new().ui(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button("Open ROM...").clicked() {
+                        ui.close();
+                        self.open_rom();
+                    }
+                    if ui.button("Quit").clicked() {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                });
+                ui.menu_button("Emulation", |ui| {
+                    if ui.button("Reset").clicked() {
+                        ui.close();
+                        self.reset();
+                    }
+                    let label = if self.paused { "Resume" } else { "Pause" };
+                    if ui.button(label).clicked() {
+                        ui.close();
+                        self.paused = !self.paused;
+                    }
+                });
+                ui.menu_button("Options", |ui| {
+                    if ui.button("Settings...").clicked() {
+                        ui.close();
+                        self.show_settings = true;
+                    }
+                    if ui
+                        .add(egui::Button::new("Command palette...").shortcut_text("Ctrl+P"))
+                        .clicked()
+                    {
+                        ui.close();
+                        self.show_palette = true;
+                        self.palette_query.clear();
+                    }
+                });
+            });
+        });
+    }
+
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Settings")
+            .open(&mut self.show_settings)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.add(
+                    egui::Slider::new(&mut self.ticks_per_frame, 1..=100).text("Ticks per frame"),
+                );
+            });
+    }
+
+    fn debugger_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Debugger")
+            .open(&mut self.show_debugger)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let cpu = debugger::cpu_state(&self.emu);
+                ui.monospace(format!(
+                    "PC {:#05x}  {:04x}  {}",
+                    cpu.pc,
+                    cpu.opcode,
+                    self.emu.decode_opcode(cpu.opcode)
+                ));
+                ui.monospace(format!(
+                    "I {:#05x}  SP {}  DT {:02x}  ST {:02x}",
+                    cpu.index, cpu.sp, cpu.delay_timer, cpu.sound_timer
+                ));
+                for (row, values) in cpu.registers.chunks(4).enumerate() {
+                    let line: Vec<String> = values
+                        .iter()
+                        .enumerate()
+                        .map(|(i, value)| format!("V{:X} {:02x}", row * 4 + i, value))
+                        .collect();
+                    ui.monospace(line.join("  "));
+                }
+                if !cpu.stack.is_empty() {
+                    let stack: Vec<String> = cpu
+                        .stack
+                        .iter()
+                        .map(|addr| format!("{:#05x}", addr))
+                        .collect();
+                    ui.monospace(format!("Stack {}", stack.join(" ")));
+                }
+
+                ui.separator();
+                for (pc, opcode) in debugger::recent_instructions(&self.emu, DEBUGGER_HISTORY) {
+                    ui.monospace(format!(
+                        "{:#05x}  {:04x}  {}",
+                        pc,
+                        opcode,
+                        self.emu.decode_opcode(opcode)
+                    ));
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let label = if self.paused { "Resume" } else { "Pause" };
+                    if ui.button(label).clicked() {
+                        self.paused = !self.paused;
+                    }
+                    if ui
+                        .add_enabled(self.paused && self.rom.is_some(), egui::Button::new("Step"))
+                        .clicked()
+                    {
+                        self.emu.step();
+                        if let Some(error) = self.emu.halted.take() {
+                            self.error = Some(format!("Emulation halted: {}", error));
+                        }
+                    }
+                });
+            });
+    }
+
+    fn update_keypad(&mut self, ctx: &egui::Context) {
+        // Typing into the palette shouldn't press CHIP-8 keys
+        if self.show_palette {
+            self.emu.clear_keys();
+            return;
+        }
+
+        ctx.input(|input| {
+            for (key, idx) in KEYMAP {
+                self.emu.keypress(idx, input.key_down(key));
+            }
+        });
+    }
+
+    fn run_frames(&mut self) {
+        let target_frame_duration = Duration::from_secs_f64(1.0 / 60.0);
+
+        let now = Instant::now();
+        self.lag += now - self.last_frame;
+        self.last_frame = now;
+
+        if self.paused || self.rom.is_none() {
+            self.lag = Duration::ZERO;
+            return;
+        }
+
+        while self.lag >= target_frame_duration {
+            self.emu.process_injected_keys();
+            self.emu.run_cycles(self.ticks_per_frame);
+            if let Some(error) = self.emu.halted.take() {
+                self.error = Some(format!("Emulation halted: {}", error));
+                self.paused = true;
+                self.lag = Duration::ZERO;
+                return;
+            }
+            self.emu.tick_timers();
+            self.lag -= target_frame_duration;
+        }
+    }
+}
+
+impl eframe::App for GuiApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.menu_bar(ctx);
+        self.settings_window(ctx);
+        self.debugger_window(ctx);
+        self.command_palette(ctx);
+        self.update_keypad(ctx);
+        self.run_frames();
+
+        let image = egui::ColorImage::from_rgba_unmultiplied(
+            [self.emu.display_width(), self.emu.display_height()],
+            &self.emu.get_display_rgba(FOREGROUND, BACKGROUND),
+        );
+        let screen = self.screen.get_or_insert_with(|| {
+            ctx.load_texture("screen", image.clone(), egui::TextureOptions::NEAREST)
+        });
+        screen.set(image, egui::TextureOptions::NEAREST);
+
+        if let Some(status) = &self.status {
+            egui::TopBottomPanel::bottom("status").show(ctx, |ui| ui.label(status));
+        }
+        egui::CentralPanel::default()
+            .frame(egui::Frame::NONE.fill(egui::Color32::BLACK))
+            .show(ctx, |ui| {
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                } else if self.rom.is_none() {
+                    ui.centered_and_justified(|ui| ui.label("File > Open ROM to start"));
+                    return;
+                }
+                ui.centered_and_justified(|ui| {
+                    let size = ui.available_size();
+                    let (width, height) = (
+                        self.emu.display_width() as f32,
+                        self.emu.display_height() as f32,
+                    );
+                    let scale = (size.x / width).min(size.y / height);
+                    ui.image((screen.id(), egui::vec2(width, height) * scale));
+                });
+            });
+
+        ctx.request_repaint();
+    }
+}
+
+fn main() -> eframe::Result {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("Chip8 Emulator")
+            .with_inner_size([960.0, 500.0]),
+        ..Default::default()
+    };
+
+    eframe::run_native(
+        "Chip8 Emulator",
+        options,
+        Box::new(|_cc| Ok(Box::new(GuiApp::new()))),
+    )
+}
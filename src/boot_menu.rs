@@ -0,0 +1,213 @@
+// A small CHIP-8 program that lists the playlist and lets the player pick a ROM with the keypad.
+// The host renders the ROM names into a reserved memory region before starting it, and the
+// program reports the choice back through a mailbox byte.
+//
+// Keys: 2 moves up, 8 moves down, 5 starts the selected ROM.
+
+use crate::{Chip8, START_ADDRESS};
+
+// Number of entries written by the host
+pub const COUNT_ADDRESS: usize = 0x800;
+// Selected entry plus one, written by the program, 0 while the menu is open
+pub const MAILBOX_ADDRESS: usize = 0x801;
+pub const ENTRIES_ADDRESS: usize = 0x810;
+// Seven 8x5 sprite columns per name, stored column after column
+pub const ENTRY_SIZE: usize = 35;
+pub const MAX_ENTRIES: usize = 48;
+pub const NAME_LENGTH: usize = 14;
+
+const COLUMNS: usize = ENTRY_SIZE / 5;
+
+#[rustfmt::skip]
+pub const PROGRAM: [u8; 167] = [
+    // start:
+    0xA8, 0x00, // 200: LD I, 0x800
+    0xF0, 0x65, // 202: LD V0, [I]
+    0x84, 0x00, // 204: LD V4, V0
+    0x61, 0x00, // 206: LD V1, 0
+    0x62, 0x00, // 208: LD V2, 0
+    0x63, 0x00, // 20A: LD V3, 0
+    // redraw:
+    0x00, 0xE0, // 20C: CLS
+    0x85, 0x30, // 20E: LD V5, V3
+    0x66, 0x00, // 210: LD V6, 0
+    // draw_entry:
+    0x95, 0x40, // 212: SNE V5, V4
+    0x12, 0x42, // 214: JP 0x242
+    0x46, 0x1E, // 216: SNE V6, 30
+    0x12, 0x42, // 218: JP 0x242
+    0xA8, 0x10, // 21A: LD I, 0x810
+    0x8C, 0x50, // 21C: LD VC, V5
+    // entry_offset:
+    0x4C, 0x00, // 21E: SNE VC, 0
+    0x12, 0x2A, // 220: JP 0x22a
+    0x6B, 0x23, // 222: LD VB, 35
+    0xFB, 0x1E, // 224: ADD I, VB
+    0x7C, 0xFF, // 226: ADD VC, 0xFF
+    0x12, 0x1E, // 228: JP 0x21e
+    // draw_name:
+    0x67, 0x06, // 22A: LD V7, 6
+    0x68, 0x07, // 22C: LD V8, 7
+    // draw_column:
+    0xD7, 0x65, // 22E: DRW V7, V6, 5
+    0x6B, 0x05, // 230: LD VB, 5
+    0xFB, 0x1E, // 232: ADD I, VB
+    0x77, 0x08, // 234: ADD V7, 8
+    0x78, 0xFF, // 236: ADD V8, 0xFF
+    0x38, 0x00, // 238: SE V8, 0
+    0x12, 0x2E, // 23A: JP 0x22e
+    0x75, 0x01, // 23C: ADD V5, 1
+    0x76, 0x06, // 23E: ADD V6, 6
+    0x12, 0x12, // 240: JP 0x212
+    // draw_cursor:
+    0x66, 0x00, // 242: LD V6, 0
+    0x8C, 0x20, // 244: LD VC, V2
+    // cursor_offset:
+    0x4C, 0x00, // 246: SNE VC, 0
+    0x12, 0x50, // 248: JP 0x250
+    0x76, 0x06, // 24A: ADD V6, 6
+    0x7C, 0xFF, // 24C: ADD VC, 0xFF
+    0x12, 0x46, // 24E: JP 0x246
+    // cursor_done:
+    0xA2, 0xA2, // 250: LD I, 0x2a2
+    0x67, 0x00, // 252: LD V7, 0
+    0xD7, 0x65, // 254: DRW V7, V6, 5
+    // wait_key:
+    0xF9, 0x0A, // 256: LD V9, K
+    // wait_release:
+    0xE9, 0xA1, // 258: SKNP V9
+    0x12, 0x58, // 25A: JP 0x258
+    0x49, 0x02, // 25C: SNE V9, 2
+    0x12, 0x6A, // 25E: JP 0x26a
+    0x49, 0x08, // 260: SNE V9, 8
+    0x12, 0x7E, // 262: JP 0x27e
+    0x49, 0x05, // 264: SNE V9, 5
+    0x12, 0x94, // 266: JP 0x294
+    0x12, 0x56, // 268: JP 0x256
+    // up:
+    0x41, 0x00, // 26A: SNE V1, 0
+    0x12, 0x56, // 26C: JP 0x256
+    0x71, 0xFF, // 26E: ADD V1, 0xFF
+    0x42, 0x00, // 270: SNE V2, 0
+    0x12, 0x78, // 272: JP 0x278
+    0x72, 0xFF, // 274: ADD V2, 0xFF
+    0x12, 0x0C, // 276: JP 0x20c
+    // previous_page:
+    0x62, 0x04, // 278: LD V2, 4
+    0x73, 0xFB, // 27A: ADD V3, 0xFB
+    0x12, 0x0C, // 27C: JP 0x20c
+    // down:
+    0x8A, 0x10, // 27E: LD VA, V1
+    0x7A, 0x01, // 280: ADD VA, 1
+    0x9A, 0x40, // 282: SNE VA, V4
+    0x12, 0x56, // 284: JP 0x256
+    0x81, 0xA0, // 286: LD V1, VA
+    0x72, 0x01, // 288: ADD V2, 1
+    0x32, 0x05, // 28A: SE V2, 5
+    0x12, 0x0C, // 28C: JP 0x20c
+    0x62, 0x00, // 28E: LD V2, 0
+    0x73, 0x05, // 290: ADD V3, 5
+    0x12, 0x0C, // 292: JP 0x20c
+    // select:
+    0x44, 0x00, // 294: SNE V4, 0
+    0x12, 0x56, // 296: JP 0x256
+    0x80, 0x10, // 298: LD V0, V1
+    0x70, 0x01, // 29A: ADD V0, 1
+    0xA8, 0x01, // 29C: LD I, 0x801
+    0xF0, 0x55, // 29E: LD [I], V0
+    // halt:
+    0x12, 0xA0, // 2A0: JP 0x2a0
+    // cursor:
+    0x80, 0xC0, 0xE0, 0xC0, 0x80, // 2A2: cursor sprite
+
+];
+
+// 3x5 glyphs for names, unknown characters are drawn as blanks
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        '-' | '_' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        _ => [0; 5],
+    }
+}
+
+// Renders up to NAME_LENGTH characters, four pixels per character, as sprite columns
+pub fn render_name(name: &str) -> [u8; ENTRY_SIZE] {
+    let mut rows = [0u64; 5];
+    for (i, c) in name.chars().take(NAME_LENGTH).enumerate() {
+        let shift = COLUMNS * 8 - 3 - i * 4;
+        for (row, bits) in rows.iter_mut().zip(glyph(c)) {
+            *row |= (bits as u64) << shift;
+        }
+    }
+
+    let mut entry = [0; ENTRY_SIZE];
+    for column in 0..COLUMNS {
+        for (row, bits) in rows.iter().enumerate() {
+            entry[column * 5 + row] = (bits >> ((COLUMNS - 1 - column) * 8)) as u8;
+        }
+    }
+    entry
+}
+
+// Resets the emulator into the menu, names beyond MAX_ENTRIES are left out
+pub fn load(emu: &mut Chip8, names: &[String]) {
+    emu.reset();
+    // The program is built for 0x200 whatever `start_address` the ROMs use
+    emu.pc = START_ADDRESS as u16;
+    emu.memory[START_ADDRESS..START_ADDRESS + PROGRAM.len()].copy_from_slice(&PROGRAM);
+
+    let count = names.len().min(MAX_ENTRIES);
+    emu.memory[COUNT_ADDRESS] = count as u8;
+    for (i, name) in names.iter().take(count).enumerate() {
+        let start = ENTRIES_ADDRESS + i * ENTRY_SIZE;
+        emu.memory[start..start + ENTRY_SIZE].copy_from_slice(&render_name(name));
+    }
+}
+
+// The entry the player picked, once the program has written it to the mailbox
+pub fn selection(emu: &Chip8) -> Option<usize> {
+    match emu.memory[MAILBOX_ADDRESS] {
+        0 => None,
+        choice => Some(choice as usize - 1),
+    }
+}
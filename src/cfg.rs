@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::Chip8;
+use crate::crash_dump;
+use crate::disassembler::{self, RefKind};
+use crate::megachip;
+use crate::rom::RomFormat;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: u16,
+    pub instructions: Vec<(u16, u16)>,
+    pub executed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeKind {
+    Fallthrough,
+    Jump,
+    Skip,
+    Call,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ControlFlowGraph {
+    pub blocks: BTreeMap<u16, BasicBlock>,
+    pub edges: BTreeSet<(u16, u16, EdgeKind)>,
+    // Built from a MegaChip ROM, its blocks are listed with MegaChip's opcodes
+    pub megachip: bool,
+}
+
+// Builds basic blocks from everything statically reachable from `start`. Addresses in
+// `executed` (e.g. PCs recorded while running the ROM) are treated as extra entry points,
+// which recovers code only reachable through `JP V0, addr`, and mark blocks as executed.
+pub fn build(rom: &[u8], start: u16, executed: &BTreeSet<u16>) -> ControlFlowGraph {
+    let megachip = megachip::is_megachip_rom(rom);
+    let mut entries: Vec<u16> = disassembler::call_graph(rom, start).into_keys().collect();
+    let mut code: BTreeSet<u16> = entries
+        .iter()
+        .flat_map(|&entry| disassembler::reachable(rom, start, entry))
+        .collect();
+
+    // Only executed code static analysis missed needs to become a new entry point
+    for &addr in executed {
+        if !code.contains(&addr) {
+            entries.push(addr);
+            code.extend(disassembler::reachable(rom, start, addr));
+        }
+    }
+
+    // Block leaders: entry points, branch targets and whatever follows a branch
+    let mut leaders: BTreeSet<u16> = entries.iter().copied().collect();
+    for &addr in &code {
+        let opcode = disassembler::opcode_at(rom, start, addr).unwrap_or(0);
+        let (next, reference) = disassembler::successors(addr, opcode, megachip);
+
+        if let Some((target, kind)) = reference
+            && kind != RefKind::Index
+        {
+            leaders.insert(target);
+        }
+        if next != [addr + 2] {
+            leaders.extend(next);
+            leaders.insert(addr + 2);
+        }
+    }
+    leaders.retain(|addr| code.contains(addr));
+
+    let mut cfg = ControlFlowGraph {
+        megachip,
+        ..Default::default()
+    };
+
+    for &leader in &leaders {
+        let mut block = BasicBlock {
+            start: leader,
+            instructions: Vec::new(),
+            executed: false,
+        };
+
+        let mut addr = leader;
+        loop {
+            let opcode = disassembler::opcode_at(rom, start, addr).unwrap_or(0);
+            block.instructions.push((addr, opcode));
+            block.executed |= executed.contains(&addr);
+
+            let (next, reference) = disassembler::successors(addr, opcode, megachip);
+            if let Some((target, RefKind::Call)) = reference {
+                cfg.edges.insert((leader, target, EdgeKind::Call));
+            }
+
+            let falls_through = next == [addr + 2];
+            if falls_through && code.contains(&(addr + 2)) && !leaders.contains(&(addr + 2)) {
+                addr += 2;
+                continue;
+            }
+
+            for target in next {
+                let kind = match reference {
+                    _ if target == addr + 2 => EdgeKind::Fallthrough,
+                    Some((_, RefKind::Jump)) => EdgeKind::Jump,
+                    _ => EdgeKind::Skip,
+                };
+                if code.contains(&target) {
+                    cfg.edges.insert((leader, target, kind));
+                }
+            }
+            break;
+        }
+
+        cfg.blocks.insert(leader, block);
+    }
+
+    cfg
+}
+
+// Every PC a recording executed, and what stopped it early if the ROM crashed
+#[derive(Debug, Default)]
+pub struct Execution {
+    pub executed: BTreeSet<u16>,
+    pub crash: Option<String>,
+}
+
+// Runs the ROM headlessly without input and collects every PC it executes
+pub fn record_execution(
+    rom: &str,
+    format: Option<RomFormat>,
+    start: u16,
+    frames: u64,
+    ticks_per_frame: usize,
+) -> io::Result<Execution> {
+    let mut emu = Chip8::with_start_address(start as usize);
+    emu.rom_format = format;
+    emu.load_rom(rom)?;
+
+    let mut executed = BTreeSet::new();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        for _ in 0..frames {
+            for _ in 0..ticks_per_frame {
+                executed.insert(emu.pc);
+                emu.step();
+            }
+            emu.tick_timers();
+        }
+    }));
+
+    // A crash just ends the recording, whatever ran until then is still useful
+    let crash = result
+        .err()
+        .map(|payload| crash_dump::panic_message(payload.as_ref()).to_string());
+    Ok(Execution { executed, crash })
+}
+
+pub fn to_dot(cfg: &ControlFlowGraph) -> String {
+    let mut dot = String::new();
+
+    let _ = writeln!(dot, "digraph chip8 {{");
+    let _ = writeln!(dot, "    node [shape=box fontname=\"monospace\"];");
+
+    for block in cfg.blocks.values() {
+        let mut label = String::new();
+        for &(addr, opcode) in &block.instructions {
+            let _ = write!(
+                label,
+                "{:03x}: {}\\l",
+                addr,
+                disassembler::mnemonic_in(opcode, cfg.megachip)
+            );
+        }
+
+        let style = if block.executed {
+            " style=filled fillcolor=\"#c8e6c9\""
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            dot,
+            "    b{:03x} [label=\"{}\"{}];",
+            block.start, label, style
+        );
+    }
+
+    for &(from, to, kind) in &cfg.edges {
+        let attributes = match kind {
+            EdgeKind::Fallthrough => "",
+            EdgeKind::Jump => " [color=blue]",
+            EdgeKind::Skip => " [color=orange label=\"skip\"]",
+            EdgeKind::Call => " [style=dashed label=\"call\"]",
+        };
+        let _ = writeln!(dot, "    b{:03x} -> b{:03x}{};", from, to, attributes);
+    }
+
+    let _ = writeln!(dot, "}}");
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recording_starts_at_the_start_address() {
+        let path = std::env::temp_dir().join(format!("chip8-cfg-{}.ch8", std::process::id()));
+        // JP 0x602, then loop on JP 0x602
+        std::fs::write(&path, [0x16, 0x02, 0x16, 0x02]).unwrap();
+        let rom = path.to_string_lossy().into_owned();
+
+        let execution = record_execution(&rom, Some(RomFormat::Binary), 0x600, 1, 4).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(execution.executed, BTreeSet::from([0x600, 0x602]));
+        assert!(execution.crash.is_none());
+    }
+}
@@ -0,0 +1,89 @@
+// Finding and freezing the byte a game keeps its lives or score in. Search memory for the value
+// on screen, let it change in the game and search for the new value, and every search keeps
+// only the addresses that matched each time. Frozen addresses are written back to their value
+// once a frame, before the game runs.
+
+#[derive(Debug, Clone, Default)]
+pub struct Cheats {
+    // Addresses that matched every search so far, None before the first one
+    candidates: Option<Vec<usize>>,
+    frozen: Vec<(usize, u8)>,
+}
+
+impl Cheats {
+    // Narrows the candidates down to the addresses that hold `value` now, returns how many are
+    // left
+    pub fn search(&mut self, memory: &[u8], value: u8) -> usize {
+        let candidates = match self.candidates.take() {
+            Some(candidates) => candidates
+                .into_iter()
+                .filter(|&addr| memory.get(addr) == Some(&value))
+                .collect(),
+            None => (0..memory.len())
+                .filter(|&addr| memory[addr] == value)
+                .collect::<Vec<_>>(),
+        };
+        let count = candidates.len();
+        self.candidates = Some(candidates);
+        count
+    }
+
+    // Starts over, the next search looks at all of memory again
+    pub fn new_search(&mut self) {
+        self.candidates = None;
+    }
+
+    pub fn candidates(&self) -> &[usize] {
+        self.candidates.as_deref().unwrap_or_default()
+    }
+
+    pub fn freeze(&mut self, addr: usize, value: u8) {
+        self.unfreeze(addr);
+        self.frozen.push((addr, value));
+    }
+
+    pub fn unfreeze(&mut self, addr: usize) {
+        self.frozen.retain(|&(frozen, _)| frozen != addr);
+    }
+
+    pub fn is_frozen(&self, addr: usize) -> bool {
+        self.frozen.iter().any(|&(frozen, _)| frozen == addr)
+    }
+
+    pub fn frozen(&self) -> &[(usize, u8)] {
+        &self.frozen
+    }
+
+    // Call once a frame
+    pub fn apply(&self, memory: &mut [u8]) {
+        for &(addr, value) in &self.frozen {
+            if let Some(byte) = memory.get_mut(addr) {
+                *byte = value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cheat_search_narrows_and_freezes() {
+        let mut cheats = Cheats::default();
+        let mut memory = [0u8; 0x1000];
+        memory[0x300] = 3;
+        memory[0x301] = 3;
+        assert_eq!(cheats.search(&memory, 3), 2);
+
+        // Only the lives counter goes down with the next death
+        memory[0x300] = 2;
+        assert_eq!(cheats.search(&memory, 2), 1);
+        assert_eq!(cheats.candidates(), [0x300]);
+
+        cheats.freeze(0x300, 9);
+        memory[0x300] = 1;
+        cheats.apply(&mut memory);
+        assert_eq!(memory[0x300], 9);
+    }
+}
@@ -0,0 +1,101 @@
+// CHIP-8X, the interpreter for the COSMAC VIP with the VP-590 colour board and a second hex
+// keypad. The screen stays two-coloured underneath, the board paints lit pixels in the colour of
+// the zone they fall in and dark ones in one background colour for the whole screen. Zones are 8
+// pixels wide and a row high. 02A0 steps the background along, BXY0 colours blocks of 8x4
+// pixel zones and BXYN a column of N zones.
+
+use crate::palette::Rgb;
+
+pub const ZONE_COLUMNS: usize = 8;
+pub const ZONE_ROWS: usize = 32;
+// In low resolution pixels
+pub const ZONE_WIDTH: usize = 8;
+// BXY0's blocks are this many zone rows high
+pub const BLOCK_HEIGHT: usize = 4;
+
+// The order 02A0 goes through them, the board starts on blue
+const BACKGROUNDS: [Rgb; 4] = [
+    [0x00, 0x00, 0x80],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x80, 0x00],
+    [0x80, 0x00, 0x00],
+];
+
+// Indexed by the low three bits of a colour register: red, blue and green
+const FOREGROUNDS: [Rgb; 8] = [
+    [0x00, 0x00, 0x00],
+    [0xFF, 0x00, 0x00],
+    [0x00, 0x00, 0xFF],
+    [0xFF, 0x00, 0xFF],
+    [0x00, 0xFF, 0x00],
+    [0xFF, 0xFF, 0x00],
+    [0x00, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xFF],
+];
+// What the colour memory holds after a reset
+const DEFAULT_FOREGROUND: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorGrid {
+    background: usize,
+    zones: [[u8; ZONE_COLUMNS]; ZONE_ROWS],
+}
+
+impl Default for ColorGrid {
+    fn default() -> Self {
+        Self {
+            background: 0,
+            zones: [[DEFAULT_FOREGROUND; ZONE_COLUMNS]; ZONE_ROWS],
+        }
+    }
+}
+
+impl ColorGrid {
+    // 02A0
+    pub fn cycle_background(&mut self) {
+        self.background = (self.background + 1) % BACKGROUNDS.len();
+    }
+
+    // BXY0, `horizontal` has the first block column in its low nibble and how many more follow
+    // in the high one, `vertical` the same for block rows
+    pub fn fill_blocks(&mut self, horizontal: u8, vertical: u8, color: u8) {
+        let span = |n: u8| (n & 0xF) as usize..=((n & 0xF) + (n >> 4)) as usize;
+        for block_row in span(vertical) {
+            for row in block_row * BLOCK_HEIGHT..(block_row + 1) * BLOCK_HEIGHT {
+                for column in span(horizontal) {
+                    self.set(column, row, color);
+                }
+            }
+        }
+    }
+
+    // BXYN, the zones under a sprite of `rows` rows at pixel `x`, `y`
+    pub fn fill_column(&mut self, x: u8, y: u8, rows: u8, color: u8) {
+        let column = x as usize / ZONE_WIDTH;
+        for row in y as usize..y as usize + rows as usize {
+            self.set(column, row, color);
+        }
+    }
+
+    // Zones past the edge wrap around like sprites
+    fn set(&mut self, column: usize, row: usize, color: u8) {
+        self.zones[row % ZONE_ROWS][column % ZONE_COLUMNS] = color & 7;
+    }
+
+    // The screen in colour, `pixels` is `width` per row in either resolution
+    pub fn colorize(&self, pixels: &[bool], width: usize) -> Vec<Rgb> {
+        let height = pixels.len() / width;
+        pixels
+            .iter()
+            .enumerate()
+            .map(|(i, &lit)| {
+                if !lit {
+                    return BACKGROUNDS[self.background];
+                }
+                let column = (i % width) * ZONE_COLUMNS / width;
+                let row = (i / width) * ZONE_ROWS / height;
+                FOREGROUNDS[self.zones[row][column] as usize]
+            })
+            .collect()
+    }
+}
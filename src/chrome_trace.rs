@@ -0,0 +1,110 @@
+// Records emulator activity in the Chrome trace-event format, viewable in Perfetto or
+// chrome://tracing. Frames, instruction batches, draws and subroutine calls each get their
+// own track so emulated work can be lined up against host frame times.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use serde_json::{Value, json};
+
+const TRACK_FRAMES: u32 = 1;
+const TRACK_CPU: u32 = 2;
+const TRACK_DRAW: u32 = 3;
+const TRACK_SUBROUTINES: u32 = 4;
+
+pub struct TraceRecorder {
+    path: PathBuf,
+    origin: Instant,
+    events: Vec<Value>,
+    open_calls: usize,
+}
+
+impl TraceRecorder {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let mut recorder = Self {
+            path: path.into(),
+            origin: Instant::now(),
+            events: Vec::new(),
+            open_calls: 0,
+        };
+
+        for (tid, name) in [
+            (TRACK_FRAMES, "Frames"),
+            (TRACK_CPU, "CPU"),
+            (TRACK_DRAW, "Draws"),
+            (TRACK_SUBROUTINES, "Subroutines"),
+        ] {
+            recorder.events.push(json!({
+                "ph": "M", "name": "thread_name", "pid": 1, "tid": tid,
+                "args": { "name": name },
+            }));
+        }
+
+        recorder
+    }
+
+    pub fn now(&self) -> f64 {
+        self.origin.elapsed().as_secs_f64() * 1_000_000.0
+    }
+
+    fn complete(&mut self, tid: u32, name: &str, start: f64, args: Value) {
+        let duration = self.now() - start;
+        self.events.push(json!({
+            "ph": "X", "name": name, "pid": 1, "tid": tid,
+            "ts": start, "dur": duration, "args": args,
+        }));
+    }
+
+    pub fn frame(&mut self, frame: u64, start: f64) {
+        self.complete(TRACK_FRAMES, "frame", start, json!({ "frame": frame }));
+    }
+
+    pub fn instruction_batch(&mut self, instructions: u64, start: f64) {
+        self.complete(
+            TRACK_CPU,
+            "instructions",
+            start,
+            json!({ "count": instructions }),
+        );
+    }
+
+    pub fn draw(&mut self, x: u8, y: u8, rows: u8, collision: bool) {
+        let ts = self.now();
+        self.events.push(json!({
+            "ph": "i", "s": "t", "name": "DRW", "pid": 1, "tid": TRACK_DRAW, "ts": ts,
+            "args": { "x": x, "y": y, "rows": rows, "collision": collision },
+        }));
+    }
+
+    pub fn call(&mut self, address: u16) {
+        let ts = self.now();
+        self.open_calls += 1;
+        self.events.push(json!({
+            "ph": "B", "name": format!("sub_{:03x}", address), "pid": 1,
+            "tid": TRACK_SUBROUTINES, "ts": ts,
+        }));
+    }
+
+    pub fn ret(&mut self) {
+        // A RET without a matching CALL would confuse the viewer
+        if self.open_calls == 0 {
+            return;
+        }
+        let ts = self.now();
+        self.open_calls -= 1;
+        self.events.push(json!({
+            "ph": "E", "pid": 1, "tid": TRACK_SUBROUTINES, "ts": ts,
+        }));
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&self) -> io::Result<()> {
+        let trace = json!({ "traceEvents": self.events, "displayTimeUnit": "ms" });
+        fs::write(&self.path, trace.to_string())
+    }
+}
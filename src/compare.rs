@@ -0,0 +1,162 @@
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::Chip8;
+use crate::crash_dump;
+use crate::keypad::KEY_COUNT;
+use crate::quirks::Quirks;
+use crate::timing::CycleTable;
+
+const INPUT_SEED: u64 = 0xC8;
+const KEY_HOLD_FRAMES: u64 = 6;
+
+// Everything that can differ between two runs of the same ROM
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub cycle_table: CycleTable,
+    pub quirks: Quirks,
+}
+
+impl Profile {
+    // A cycle table or quirk preset name, `vip` is both. Whichever half the name doesn't
+    // cover stays at its default.
+    pub fn named(name: &str) -> Option<Self> {
+        let cycle_table = CycleTable::preset(name);
+        let quirks = Quirks::preset(name);
+        if cycle_table.is_none() && quirks.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            name: name.to_string(),
+            cycle_table: cycle_table.unwrap_or_default(),
+            quirks: quirks.unwrap_or_default(),
+        })
+    }
+
+    pub fn apply(&self, emu: &mut Chip8) {
+        emu.cycle_table = self.cycle_table.clone();
+        emu.quirks = self.quirks;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub frame: u64,
+    pub differences: Vec<String>,
+}
+
+pub fn diff_state(a: &Chip8, b: &Chip8) -> Vec<String> {
+    let mut differences = Vec::new();
+
+    if a.pc != b.pc {
+        differences.push(format!("PC {:#05x} vs {:#05x}", a.pc, b.pc));
+    }
+    if a.index != b.index {
+        differences.push(format!("I {:#05x} vs {:#05x}", a.index, b.index));
+    }
+    if a.sp != b.sp {
+        differences.push(format!("SP {} vs {}", a.sp, b.sp));
+    }
+    for (i, (ra, rb)) in a.registers.iter().zip(&b.registers).enumerate() {
+        if ra != rb {
+            differences.push(format!("V{:X} {:#04x} vs {:#04x}", i, ra, rb));
+        }
+    }
+    if a.hires != b.hires {
+        differences.push(format!("hires {} vs {}", a.hires, b.hires));
+    }
+    if a.dtimer != b.dtimer {
+        differences.push(format!("DT {} vs {}", a.dtimer, b.dtimer));
+    }
+    if a.stimer != b.stimer {
+        differences.push(format!("ST {} vs {}", a.stimer, b.stimer));
+    }
+
+    let changed: Vec<usize> = (0..a.memory.len())
+        .filter(|&addr| a.memory[addr] != b.memory[addr])
+        .collect();
+    if let Some(&first) = changed.first() {
+        differences.push(format!(
+            "{} memory bytes differ, first at {:#05x}",
+            changed.len(),
+            first
+        ));
+    }
+
+    let pixels = a
+        .video
+        .pixels()
+        .iter()
+        .zip(b.video.pixels())
+        .filter(|(pa, pb)| pa != pb)
+        .count();
+    if pixels > 0 {
+        differences.push(format!("{} pixels differ", pixels));
+    }
+
+    differences
+}
+
+// Runs the ROM under both profiles with identical input and returns the first frame where
+// their state or video no longer match
+pub fn lockstep(
+    rom: &str,
+    a: &Profile,
+    b: &Profile,
+    frames: u64,
+    ticks_per_frame: usize,
+) -> io::Result<Option<Divergence>> {
+    // Both get the same RND sequence so only the profile can make them diverge
+    let mut emu_a = Chip8::with_seed(INPUT_SEED);
+    let mut emu_b = Chip8::with_seed(INPUT_SEED);
+    a.apply(&mut emu_a);
+    b.apply(&mut emu_b);
+    emu_a.load_rom(rom)?;
+    emu_b.load_rom(rom)?;
+
+    let mut rng = StdRng::seed_from_u64(INPUT_SEED);
+
+    for frame in 0..frames {
+        if frame % KEY_HOLD_FRAMES == 0 {
+            let key = rng.random_bool(0.5).then(|| rng.random_range(0..KEY_COUNT));
+            for emu in [&mut emu_a, &mut emu_b] {
+                emu.clear_keys();
+                if let Some(key) = key {
+                    emu.keypress(key, true);
+                }
+            }
+        }
+
+        let mut crashes = Vec::new();
+        for (profile, emu) in [(a, &mut emu_a), (b, &mut emu_b)] {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                emu.run_cycles(ticks_per_frame);
+                emu.tick_timers();
+            }));
+            if let Err(payload) = result {
+                crashes.push(format!(
+                    "{} crashed: {}",
+                    profile.name,
+                    crash_dump::panic_message(payload.as_ref())
+                ));
+            }
+            if let Some(error) = emu.halted.take() {
+                crashes.push(format!("{} halted: {}", profile.name, error));
+            }
+        }
+
+        let mut differences = crashes;
+        differences.extend(diff_state(&emu_a, &emu_b));
+
+        if !differences.is_empty() {
+            return Ok(Some(Divergence { frame, differences }));
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,73 @@
+use std::any::Any;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Chip8;
+
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| payload.downcast_ref::<&str>().copied())
+        .unwrap_or("unknown error")
+}
+
+pub fn format_crash_dump(emu: &Chip8, reason: &str) -> String {
+    let mut dump = String::new();
+
+    // Writing into a String can't fail
+    let _ = writeln!(dump, "Chip8 crash dump");
+    let _ = writeln!(dump, "Reason: {}", reason);
+    let _ = writeln!(
+        dump,
+        "ROM SHA-1: {}",
+        emu.rom_hash.as_deref().unwrap_or("<no rom loaded>")
+    );
+
+    let _ = writeln!(dump, "\n[State]");
+    let _ = writeln!(
+        dump,
+        "PC: {:#05x}  I: {:#05x}  SP: {}  DT: {}  ST: {}",
+        emu.pc, emu.index, emu.sp, emu.dtimer, emu.stimer
+    );
+    for (i, value) in emu.registers.iter().enumerate() {
+        let _ = write!(dump, "V{:X}: {:#04x}  ", i, value);
+        if i % 8 == 7 {
+            let _ = writeln!(dump);
+        }
+    }
+    let _ = writeln!(dump, "Stack: {:03x?}", &emu.stack[..]);
+    let pressed: Vec<usize> = (0..emu.keypad.len()).filter(|&k| emu.keypad[k]).collect();
+    let _ = writeln!(dump, "Keys down: {:x?}", pressed);
+
+    let _ = writeln!(dump, "\n[Recent instructions, oldest first]");
+    for (pc, opcode) in &emu.history {
+        let _ = writeln!(dump, "{:#05x}: {:04x}", pc, opcode);
+    }
+
+    let _ = writeln!(dump, "\n[Memory]");
+    for (row, bytes) in emu.memory.chunks(16).enumerate() {
+        let _ = write!(dump, "{:03x}:", row * 16);
+        for byte in bytes {
+            let _ = write!(dump, " {:02x}", byte);
+        }
+        let _ = writeln!(dump);
+    }
+
+    dump
+}
+
+pub fn write_crash_dump(emu: &Chip8, reason: &str) -> io::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = PathBuf::from(format!("chip8-crash-{}.txt", timestamp));
+
+    fs::write(&path, format_crash_dump(emu, reason))?;
+
+    Ok(path)
+}
@@ -0,0 +1,309 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write as _};
+
+use crate::Chip8;
+use crate::disassembler;
+
+// How many instructions a multi-step (F11 in the SDL frontend) runs
+pub const DEFAULT_STEP_COUNT: usize = 10;
+
+// Which data accesses to an address pause execution. Instruction fetches never count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Access,
+}
+
+impl WatchKind {
+    fn matches(self, write: bool) -> bool {
+        match self {
+            WatchKind::Read => !write,
+            WatchKind::Write => write,
+            WatchKind::Access => true,
+        }
+    }
+}
+
+// The access that tripped a watchpoint, `pc` is the instruction that made it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub address: u16,
+    pub pc: u16,
+    pub value: u8,
+    pub write: bool,
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.write {
+            write!(
+                f,
+                "Watchpoint: {:#05x} wrote {:#04x} to {:#05x}",
+                self.pc, self.value, self.address
+            )
+        } else {
+            write!(
+                f,
+                "Watchpoint: {:#05x} read {:#04x} from {:#05x}",
+                self.pc, self.value, self.address
+            )
+        }
+    }
+}
+
+// Breakpoints, watchpoints, pause state and queued single steps. Any frontend drives the
+// debugger through `Chip8::debug`, the core honours it in `run_cycles` and `run_pending_steps`.
+#[derive(Debug, Clone, Default)]
+pub struct DebugControl {
+    breakpoints: BTreeSet<u16>,
+    watchpoints: BTreeMap<u16, WatchKind>,
+    watch_hit: Option<WatchHit>,
+    paused: bool,
+    pending_steps: usize,
+}
+
+impl DebugControl {
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.remove(&addr);
+    }
+
+    // Returns whether the breakpoint is now set
+    pub fn toggle_breakpoint(&mut self, addr: u16) -> bool {
+        if !self.breakpoints.remove(&addr) {
+            self.breakpoints.insert(addr);
+            return true;
+        }
+        false
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn add_watchpoint(&mut self, addr: u16, kind: WatchKind) {
+        self.watchpoints.insert(addr, kind);
+    }
+
+    pub fn remove_watchpoint(&mut self, addr: u16) {
+        self.watchpoints.remove(&addr);
+    }
+
+    pub fn watchpoints(&self) -> impl Iterator<Item = (u16, WatchKind)> + '_ {
+        self.watchpoints.iter().map(|(&addr, &kind)| (addr, kind))
+    }
+
+    // The access that paused execution, if a watchpoint did
+    pub fn take_watch_hit(&mut self) -> Option<WatchHit> {
+        self.watch_hit.take()
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.pending_steps = 0;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.paused {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Queues `count` instructions to run while paused, pausing first if needed
+    pub fn step(&mut self, count: usize) {
+        self.paused = true;
+        self.pending_steps += count;
+    }
+
+    pub(crate) fn take_steps(&mut self) -> usize {
+        std::mem::take(&mut self.pending_steps)
+    }
+
+    // Checked after every instruction, so resuming from a breakpoint doesn't stop right away
+    pub(crate) fn hit_breakpoint(&mut self, pc: u16) -> bool {
+        if self.breakpoints.contains(&pc) {
+            self.paused = true;
+            return true;
+        }
+        false
+    }
+
+    // Called for every data access the core makes on behalf of an instruction
+    pub(crate) fn check_access(&mut self, hit: WatchHit) {
+        if self
+            .watchpoints
+            .get(&hit.address)
+            .is_some_and(|kind| kind.matches(hit.write))
+        {
+            self.paused = true;
+            self.watch_hit = Some(hit);
+        }
+    }
+}
+
+// What a debugger view shows of the CPU, read between instructions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuState {
+    pub pc: u16,
+    pub index: u32,
+    pub sp: u8,
+    pub registers: [u8; 16],
+    // Return addresses, oldest call first
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    // The instruction at PC, about to run
+    pub opcode: u16,
+}
+
+impl CpuState {
+    pub fn instruction(&self) -> String {
+        disassembler::mnemonic(self.opcode)
+    }
+}
+
+pub fn cpu_state(emu: &Chip8) -> CpuState {
+    let pc = emu.pc as usize;
+    let opcode = match (emu.memory.get(pc), emu.memory.get(pc + 1)) {
+        (Some(&hi), Some(&lo)) => u16::from_be_bytes([hi, lo]),
+        _ => 0,
+    };
+
+    CpuState {
+        pc: emu.pc,
+        index: emu.index,
+        sp: emu.sp,
+        registers: emu.registers,
+        stack: emu.stack[..emu.sp as usize].to_vec(),
+        delay_timer: emu.dtimer,
+        sound_timer: emu.stimer,
+        opcode,
+    }
+}
+
+// Up to `len` bytes of RAM from `addr`, cut short at the end of memory
+pub fn memory(emu: &Chip8, addr: usize, len: usize) -> &[u8] {
+    let start = addr.min(emu.memory.len());
+    let end = addr.saturating_add(len).min(emu.memory.len());
+    &emu.memory[start..end]
+}
+
+// What a debugger can change between instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Memory(usize),
+    Register(usize),
+    Index,
+    Pc,
+    DelayTimer,
+    SoundTimer,
+}
+
+impl Target {
+    // "v0" to "vf", "i", "pc", "dt", "st" or a memory address in hex
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        match name.as_str() {
+            "i" => return Some(Target::Index),
+            "pc" => return Some(Target::Pc),
+            "dt" => return Some(Target::DelayTimer),
+            "st" => return Some(Target::SoundTimer),
+            _ => {}
+        }
+        if let Some(register) = name.strip_prefix('v') {
+            return u8::from_str_radix(register, 16)
+                .ok()
+                .filter(|&register| register < 16)
+                .map(|register| Target::Register(register as usize));
+        }
+        usize::from_str_radix(name.trim_start_matches("0x"), 16)
+            .ok()
+            .map(Target::Memory)
+    }
+}
+
+// Changes a byte of RAM or a register, returns false when the address is outside memory or the
+// value doesn't fit. Only call it between instructions, the next one sees the change.
+pub fn poke(emu: &mut Chip8, target: Target, value: u32) -> bool {
+    let slot = match target {
+        Target::Index => {
+            emu.index = value;
+            return true;
+        }
+        Target::Pc => return u16::try_from(value).map(|pc| emu.pc = pc).is_ok(),
+        Target::Memory(addr) => emu.memory.get_mut(addr),
+        Target::Register(register) => emu.registers.get_mut(register),
+        Target::DelayTimer => Some(&mut emu.dtimer),
+        Target::SoundTimer => Some(&mut emu.stimer),
+    };
+    match (slot, u8::try_from(value)) {
+        (Some(slot), Ok(byte)) => {
+            *slot = byte;
+            true
+        }
+        _ => false,
+    }
+}
+
+// The last `count` executed instructions as (address, opcode), oldest first
+pub fn recent_instructions(emu: &Chip8, count: usize) -> impl Iterator<Item = (u16, u16)> + '_ {
+    emu.history
+        .iter()
+        .skip(emu.history.len().saturating_sub(count))
+        .copied()
+}
+
+// One line summary of where execution stopped
+pub fn describe(emu: &Chip8) -> String {
+    let cpu = cpu_state(emu);
+    let mut line = format!(
+        "{:#05x}: {:04x} {:<16} I={:#05x}",
+        cpu.pc,
+        cpu.opcode,
+        cpu.instruction(),
+        cpu.index
+    );
+    for (i, value) in cpu.registers.iter().enumerate() {
+        let _ = write!(line, " V{:X}={:02x}", i, value);
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debugger_pokes_memory_and_registers() {
+        let mut emu = Chip8::new();
+        assert_eq!(Target::parse("VA"), Some(Target::Register(0xA)));
+        assert_eq!(Target::parse("0x2f0"), Some(Target::Memory(0x2F0)));
+        assert_eq!(Target::parse("vg"), None);
+
+        assert!(poke(&mut emu, Target::Memory(0x2F0), 0x09));
+        assert!(poke(&mut emu, Target::Register(0xA), 0x03));
+        assert!(poke(&mut emu, Target::Pc, 0x300));
+        assert_eq!(
+            (emu.memory[0x2F0], emu.registers[0xA], emu.pc),
+            (0x09, 0x03, 0x300)
+        );
+
+        // A byte doesn't hold 0x100 and there is no memory that far up
+        assert!(!poke(&mut emu, Target::DelayTimer, 0x100));
+        assert!(!poke(&mut emu, Target::Memory(0x100000), 1));
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::instruction::{decode, decode_megachip};
+use crate::megachip;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RefKind {
+    Jump,
+    Call,
+    Skip,
+    Index,
+}
+
+impl fmt::Display for RefKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RefKind::Jump => write!(f, "JP"),
+            RefKind::Call => write!(f, "CALL"),
+            RefKind::Skip => write!(f, "skip"),
+            RefKind::Index => write!(f, "LD I"),
+        }
+    }
+}
+
+pub fn mnemonic(opcode: u16) -> String {
+    decode(opcode).to_string()
+}
+
+// Like `mnemonic`, with MegaChip's opcodes when `megachip` is set for a ROM starting with MEGAON
+pub(crate) fn mnemonic_in(opcode: u16, megachip: bool) -> String {
+    if megachip {
+        decode_megachip(opcode).to_string()
+    } else {
+        mnemonic(opcode)
+    }
+}
+
+pub(crate) fn opcode_at(rom: &[u8], start: u16, addr: u16) -> Option<u16> {
+    let offset = addr.checked_sub(start)? as usize;
+    Some(u16::from_be_bytes([
+        *rom.get(offset)?,
+        *rom.get(offset + 1)?,
+    ]))
+}
+
+fn is_skip(opcode: u16) -> bool {
+    matches!(opcode >> 12, 3 | 4 | 5 | 9) || matches!(opcode & 0xF0FF, 0xE09E | 0xE0A1)
+}
+
+// Addresses an instruction may continue at, and the references it makes. `megachip` for ROMs
+// that start with MEGAON, where 01NN is followed by a word of data.
+pub(crate) fn successors(
+    addr: u16,
+    opcode: u16,
+    megachip: bool,
+) -> (Vec<u16>, Option<(u16, RefKind)>) {
+    let nnn = opcode & 0x0FFF;
+
+    match opcode >> 12 {
+        _ if opcode == 0x00EE || opcode == 0x00FD => (vec![], None),
+        1 => (vec![nnn], Some((nnn, RefKind::Jump))),
+        // The jump target depends on V0, so static analysis stops here
+        0xB => (vec![], None),
+        2 => (vec![addr + 2], Some((nnn, RefKind::Call))),
+        // MegaChip's LDHI is followed by the low word of its address
+        0 if megachip && opcode & 0xFF00 == 0x0100 => (vec![addr + 4], None),
+        // So is XO-CHIP's F000 by all of I
+        0xF if opcode == 0xF000 => (vec![addr + 4], None),
+        0xA => (vec![addr + 2], Some((nnn, RefKind::Index))),
+        _ if is_skip(opcode) => (vec![addr + 2, addr + 4], Some((addr + 4, RefKind::Skip))),
+        _ => (vec![addr + 2], None),
+    }
+}
+
+// Maps every referenced address to the instructions referring to it
+pub fn cross_references(rom: &[u8], start: u16) -> BTreeMap<u16, Vec<(u16, RefKind)>> {
+    let mut xrefs: BTreeMap<u16, Vec<(u16, RefKind)>> = BTreeMap::new();
+
+    for addr in code_addresses(rom, start) {
+        if let Some(opcode) = opcode_at(rom, start, addr)
+            && let (_, Some((target, kind))) =
+                successors(addr, opcode, megachip::is_megachip_rom(rom))
+        {
+            xrefs.entry(target).or_default().push((addr, kind));
+        }
+    }
+
+    xrefs
+}
+
+// Follows control flow from `entry`, CALLs are not entered
+pub(crate) fn reachable(rom: &[u8], start: u16, entry: u16) -> BTreeSet<u16> {
+    let mut seen = BTreeSet::new();
+    let mut pending = vec![entry];
+
+    while let Some(addr) = pending.pop() {
+        if !seen.insert(addr) {
+            continue;
+        }
+        if let Some(opcode) = opcode_at(rom, start, addr) {
+            pending.extend(successors(addr, opcode, megachip::is_megachip_rom(rom)).0);
+        }
+    }
+
+    seen.retain(|&addr| opcode_at(rom, start, addr).is_some());
+    seen
+}
+
+// Subroutine entry points (the program start included) and the subroutines each one calls
+pub fn call_graph(rom: &[u8], start: u16) -> BTreeMap<u16, BTreeSet<u16>> {
+    let mut graph = BTreeMap::new();
+    let mut pending = vec![start];
+
+    while let Some(entry) = pending.pop() {
+        if graph.contains_key(&entry) {
+            continue;
+        }
+
+        let mut callees = BTreeSet::new();
+        for addr in reachable(rom, start, entry) {
+            if let Some(opcode) = opcode_at(rom, start, addr)
+                && opcode >> 12 == 2
+            {
+                callees.insert(opcode & 0x0FFF);
+            }
+        }
+
+        pending.extend(callees.iter().copied());
+        graph.insert(entry, callees);
+    }
+
+    graph
+}
+
+// Every address reachable from the program start or any subroutine it calls
+fn code_addresses(rom: &[u8], start: u16) -> BTreeSet<u16> {
+    call_graph(rom, start)
+        .keys()
+        .flat_map(|&entry| reachable(rom, start, entry))
+        .collect()
+}
+
+// Full listing with a "referenced from" comment above every referenced address
+pub fn listing(rom: &[u8], start: u16) -> String {
+    let xrefs = cross_references(rom, start);
+    let code = code_addresses(rom, start);
+    let mut out = String::new();
+
+    let mut addr = start;
+    while let Some(opcode) = opcode_at(rom, start, addr) {
+        if let Some(refs) = xrefs.get(&addr) {
+            let sources: Vec<String> = refs
+                .iter()
+                .map(|(source, kind)| format!("{:#05x} ({})", source, kind))
+                .collect();
+            out.push_str(&format!("; referenced from {}\n", sources.join(", ")));
+        }
+
+        let text = if code.contains(&addr) {
+            mnemonic_in(opcode, megachip::is_megachip_rom(rom))
+        } else {
+            format!("DB {:#04x}, {:#04x}", opcode >> 8, opcode & 0xFF)
+        };
+        out.push_str(&format!("{:#05x}: {:04x}  {}\n", addr, opcode, text));
+        addr += 2;
+    }
+
+    out
+}
@@ -0,0 +1,113 @@
+// The framebuffer DRW draws into, `width` x `height` pixels that are either lit or dark. It
+// changes size with the mode, 64x32 for plain CHIP-8, 128x64 in SUPER-CHIP's high resolution,
+// the taller ETI-660 screens and MegaChip's 256x192. XO-CHIP draws on two planes of that size,
+// everything else only ever touches the first one.
+
+// XO-CHIP's bit planes, a pixel lit on both shows the palette's fourth colour
+pub const PLANES: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Display {
+    width: usize,
+    height: usize,
+    // `width` per row, the first plane is the one `pixels` returns
+    planes: [Vec<bool>; PLANES],
+}
+
+impl Display {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            planes: [(); PLANES].map(|_| vec![false; width * height]),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Switches to another size with every pixel dark, the old picture doesn't fit the new layout
+    pub fn resize(&mut self, width: usize, height: usize) {
+        *self = Self::new(width, height);
+    }
+
+    pub fn clear(&mut self) {
+        self.clear_planes(u8::MAX);
+    }
+
+    // Only the planes whose bit is set in `planes`
+    pub fn clear_planes(&mut self, planes: u8) {
+        for plane in self.selected(planes) {
+            plane.fill(false);
+        }
+    }
+
+    pub fn pixels(&self) -> &[bool] {
+        &self.planes[0]
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [bool] {
+        &mut self.planes[0]
+    }
+
+    pub fn plane(&self, plane: usize) -> &[bool] {
+        &self.planes[plane]
+    }
+
+    pub fn plane_mut(&mut self, plane: usize) -> &mut [bool] {
+        &mut self.planes[plane]
+    }
+
+    // Which planes every pixel is lit on, bit 0 for the first. Frontends pick the palette colour
+    // with it: background, foreground, second plane or both.
+    pub fn masks(&self) -> Vec<u8> {
+        let mut masks = vec![0; self.width * self.height];
+        for (bit, plane) in self.planes.iter().enumerate() {
+            for (mask, &lit) in masks.iter_mut().zip(plane) {
+                *mask |= (lit as u8) << bit;
+            }
+        }
+        masks
+    }
+
+    // XORs a pixel the way sprites are drawn, true when that turned it off
+    pub fn flip(&mut self, x: usize, y: usize) -> bool {
+        self.flip_plane(0, x, y)
+    }
+
+    pub fn flip_plane(&mut self, plane: usize, x: usize, y: usize) -> bool {
+        let pixel = &mut self.planes[plane][x + y * self.width];
+        *pixel ^= true;
+        !*pixel
+    }
+
+    // Moves the picture on the planes in `planes` by dx/dy pixels, whatever scrolls in is dark
+    pub fn scroll(&mut self, planes: u8, dx: isize, dy: isize) {
+        let (width, height) = (self.width as isize, self.height as isize);
+
+        for plane in self.selected(planes) {
+            let old = plane.clone();
+            for y in 0..height {
+                for x in 0..width {
+                    let (src_x, src_y) = (x - dx, y - dy);
+                    plane[(x + y * width) as usize] = (0..width).contains(&src_x)
+                        && (0..height).contains(&src_y)
+                        && old[(src_x + src_y * width) as usize];
+                }
+            }
+        }
+    }
+
+    fn selected(&mut self, planes: u8) -> impl Iterator<Item = &mut Vec<bool>> {
+        self.planes
+            .iter_mut()
+            .enumerate()
+            .filter(move |(bit, _)| planes & (1 << bit) != 0)
+            .map(|(_, plane)| plane)
+    }
+}
@@ -1,2 +1,132 @@
+// What a frontend is made of. `run_loop::run` drives the emulator through these traits, so a
+// new frontend only has to draw frames, report input and play the buzzer. The SDL drivers are
+// one implementation of them.
+
+#[cfg(feature = "sdl")]
+pub mod audio_driver;
+#[cfg(feature = "sdl")]
 pub mod display_driver;
+#[cfg(feature = "sdl")]
 pub mod input_driver;
+#[cfg(feature = "midi")]
+pub mod midi_driver;
+#[cfg(feature = "sdl")]
+pub mod rumble_driver;
+pub mod shm_driver;
+#[cfg(feature = "terminal")]
+pub mod terminal_driver;
+pub mod vnc_driver;
+
+use crate::Chip8;
+use crate::audio::Buzzer;
+use crate::error::Chip8Error;
+use crate::pacing::Rates;
+use crate::palette::Palette;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    // A keypad key went down or up
+    Key { key: usize, pressed: bool },
+    // The same on CHIP-8X's second keypad
+    SecondKey { key: usize, pressed: bool },
+    // A turbo key for this keypad key went down or up, see `Turbo`
+    Turbo { key: usize, held: bool },
+    Quit,
+    NextRom,
+    PreviousRom,
+    BootMenu,
+    // A ROM file dropped on the window
+    OpenRom(String),
+    // Asks the input driver for a ROM file with `pick_rom`
+    PickRom,
+    // Starts the ROM over, or reads it from disk again for a reload
+    Reset,
+    ReloadRom,
+    // Runs the recorded frames backwards while held
+    Rewind(bool),
+    SaveState,
+    NextSlot,
+    LoadState,
+    ToggleBreakpoint,
+    ToggleDebugPause,
+    TogglePause,
+    AdvanceFrame,
+    FastForward(bool),
+    SpeedUp,
+    SlowDown,
+    ToggleMute,
+    // Debugger single step of this many instructions
+    Step(usize),
+    ToggleGifRecording,
+    // Passed on to the display driver
+    ToggleFullscreen,
+    ScaleUp,
+    ScaleDown,
+    ToggleOverlay,
+    ToggleCrt,
+    Screenshot,
+    // The window lost what it showed, like after being uncovered
+    Redraw,
+    // The window came to the front or went to the background or was minimized
+    Focus(bool),
+}
+
+pub trait DisplayDriver {
+    // Presents the screen, or the slow draw animation while one is playing
+    fn draw(&mut self, emu: &Chip8) -> Result<(), Chip8Error>;
+
+    // The colours on screen, GIF recordings start with them
+    fn palette(&self) -> Palette;
+
+    // A ROM started, `title` is its database title or file name
+    fn set_title(&mut self, _title: &str) {}
+
+    // The colours a ROM asks for, None goes back to the ones the display started with
+    fn set_rom_palette(&mut self, _palette: Option<Palette>) {}
+
+    // The speed the emulator is set to and what it reached over the last second, once a second
+    fn show_speed(&mut self, _cpu_hz: u32, _rates: Rates) {}
+
+    // A short message for the player like "State saved to slot 2", gone after a moment
+    fn show_message(&mut self, _message: &str) {}
+
+    // True when drawing a frame waits for the display's refresh, the frame loop then doesn't
+    // sleep on top of it
+    fn paces_frames(&self) -> bool {
+        false
+    }
+
+    // Fullscreen, overlay, screenshot and fast forward events, the display ignores whatever it can't do
+    fn handle(&mut self, _emu: &Chip8, _event: InputEvent) {}
+}
+
+pub trait InputDriver {
+    // Appends everything that happened since the last frame
+    fn poll(&mut self, events: &mut Vec<InputEvent>);
+
+    // Called every frame with whether the sound timer runs, for gamepads that can rumble
+    fn rumble(&mut self, _active: bool) {}
+
+    // Asks the player for a ROM file, None when they cancel or the frontend can't ask
+    fn pick_rom(&mut self) -> Option<String> {
+        None
+    }
+}
+
+pub trait AudioDriver {
+    // Tops the output back up from the frames the buzzer has queued, called once per frame
+    fn update(&mut self, buzzer: &mut Buzzer);
+}
+
+// Silence, for frontends without sound or when the audio device didn't open
+impl AudioDriver for () {
+    fn update(&mut self, _buzzer: &mut Buzzer) {}
+}
+
+impl<T: AudioDriver> AudioDriver for Option<T> {
+    fn update(&mut self, buzzer: &mut Buzzer) {
+        if let Some(audio) = self {
+            audio.update(buzzer);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::rc::Rc;
+
+use sdl3::AudioSubsystem;
+use sdl3::audio::{AudioFormat, AudioSpec};
+
+use crate::audio::Buzzer;
+use crate::drivers::AudioDriver;
+
+const SAMPLE_RATE: u32 = 44100;
+// Keep about two frames of audio queued, enough to cover a late frame without the beep
+// noticeably lagging behind the sound timer
+const BUFFERED_SAMPLES: usize = SAMPLE_RATE as usize / 30;
+
+type QueueFn = Box<dyn Fn(&[f32])>;
+
+// Streams the buzzer's frames to the default playback device as they are ticked.
+pub struct SdlAudio {
+    samples: Vec<f32>,
+    // The stream type differs between sdl3 releases, so it is only reached through these
+    queued_samples: Box<dyn Fn() -> usize>,
+    queue: QueueFn,
+}
+
+impl SdlAudio {
+    pub fn new(subsystem: &AudioSubsystem) -> Result<Self, sdl3::Error> {
+        let spec = AudioSpec {
+            freq: Some(SAMPLE_RATE as i32),
+            channels: Some(1),
+            format: Some(AudioFormat::f32_sys()),
+        };
+        let stream = subsystem
+            .open_playback_device(&spec)?
+            .open_device_stream(Some(&spec))?;
+        stream.resume()?;
+
+        let stream = Rc::new(stream);
+        let queued = Rc::clone(&stream);
+        Ok(Self {
+            samples: Vec::with_capacity(BUFFERED_SAMPLES),
+            queued_samples: Box::new(move || {
+                queued.available_bytes().unwrap_or(0).max(0) as usize / size_of::<f32>()
+            }),
+            queue: Box::new(move |samples| {
+                let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_ne_bytes()).collect();
+                if let Err(e) = stream.put_data(&bytes) {
+                    eprintln!("Failed to queue audio: {}", e);
+                }
+            }),
+        })
+    }
+}
+
+impl AudioDriver for SdlAudio {
+    fn update(&mut self, buzzer: &mut Buzzer) {
+        // Every ticked frame gets played, silence only pads the device out when the emulator
+        // falls behind. Far ahead and the buzzer drops the stale frames instead.
+        let queued = (self.queued_samples)();
+        if queued >= BUFFERED_SAMPLES * 2 {
+            return;
+        }
+        let count = buzzer
+            .owed_samples(SAMPLE_RATE)
+            .max(BUFFERED_SAMPLES.saturating_sub(queued));
+        if count == 0 {
+            return;
+        }
+
+        self.samples.resize(count, 0.0);
+        buzzer.render(&mut self.samples, SAMPLE_RATE);
+        (self.queue)(&self.samples);
+    }
+}
@@ -1,36 +1,830 @@
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sdl3::VideoSubsystem;
 use sdl3::pixels::Color;
 use sdl3::rect::Rect;
-use sdl3::render::Canvas;
+use sdl3::render::{BlendMode, Canvas, FRect};
 use sdl3::video::Window;
 
 use crate::CHIP8_HEIGHT;
 use crate::CHIP8_WIDTH;
 use crate::Chip8;
+use crate::debugger;
+use crate::drivers::{DisplayDriver, InputEvent};
+use crate::error::Chip8Error;
+use crate::geometry::Geometry;
+use crate::megachip::{MEGA_HEIGHT, MEGA_WIDTH};
+use crate::osd::{self, Osd};
+use crate::pacing::{FRAMES_PER_SECOND, Rates};
+use crate::palette::{Palette, Rgb};
+use crate::phosphor::Phosphor;
+use crate::settings::{self, Settings};
+use crate::slow_draw::SlowDraw;
 
 pub const SCALE_FACTOR: u32 = 15;
-pub const WINDOW_WIDTH: u32 = (CHIP8_WIDTH as u32) * SCALE_FACTOR;
-pub const WINDOW_HEIGHT: u32 = (CHIP8_HEIGHT as u32) * SCALE_FACTOR;
+// Ctrl+Plus and Ctrl+Minus step the window between 1x and this
+pub const MAX_SCALE: u32 = 20;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayOptions {
+    pub flip_x: bool,
+    pub flip_y: bool,
+    // Window pixels per low resolution CHIP-8 pixel, the window opens at this size
+    pub scale: u32,
+    // Only grow the screen by whole multiples of the CHIP-8 resolution in a resized window
+    pub integer_scale: bool,
+    pub fullscreen: bool,
+    pub palette: Palette,
+    // Registers, timers, recent opcodes and keypad drawn over the game, F1 toggles it
+    pub overlay: bool,
+    // A light in the corner while the sound timer runs, always on when there is nothing to
+    // hear
+    pub sound_indicator: bool,
+    // Time frames by the display's vertical sync instead of sleeping, on 60 Hz displays
+    pub vsync: bool,
+    // Let pixels fade out keeping this much brightness a frame, see `Phosphor`
+    pub phosphor: Option<f32>,
+    // Lines between the pixels in this colour once they are big enough to leave room
+    pub grid: Option<Rgb>,
+    // Scanlines, gaps between pixels and darker corners, F3 toggles it
+    pub crt: bool,
+    // The window opens with this screen's aspect ratio
+    pub geometry: Geometry,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            flip_x: false,
+            flip_y: false,
+            scale: SCALE_FACTOR,
+            integer_scale: false,
+            fullscreen: false,
+            palette: Palette::default(),
+            overlay: false,
+            sound_indicator: false,
+            vsync: false,
+            phosphor: None,
+            grid: None,
+            crt: false,
+            geometry: Geometry::default(),
+        }
+    }
+}
+
+impl DisplayOptions {
+    pub fn window_size(&self) -> (u32, u32) {
+        let (width, height) = self.geometry.size();
+        (width as u32 * self.scale, height as u32 * self.scale)
+    }
+
+    // The largest area of a `width` x `height` window with the CHIP-8 aspect ratio, centred
+    // so the rest is left as black bars
+    // `base` is the smallest picture that scales by whole multiples, see `base_size`
+    pub fn viewport(
+        &self,
+        (width, height): (u32, u32),
+        (base_width, base_height): (u32, u32),
+    ) -> Rect {
+        let (screen_width, screen_height) = if self.integer_scale {
+            let factor = (width / base_width).min(height / base_height).max(1);
+            (base_width * factor, base_height * factor)
+        } else {
+            let factor = (width as f64 / base_width as f64).min(height as f64 / base_height as f64);
+            (
+                ((base_width as f64 * factor) as u32).max(1),
+                ((base_height as f64 * factor) as u32).max(1),
+            )
+        };
+
+        Rect::new(
+            (width as i32 - screen_width as i32) / 2,
+            (height as i32 - screen_height as i32) / 2,
+            screen_width,
+            screen_height,
+        )
+    }
+}
+
+// The emulator window
+pub struct SdlDisplay {
+    canvas: Canvas<Window>,
+    options: DisplayOptions,
+    // The palette it opened with, for ROMs without colours of their own
+    palette: Palette,
+    osd: Osd,
+    // Whether presenting waits for vsync, see `DisplayOptions::vsync`
+    vsync: bool,
+    // What the window shows, None when it needs redrawing whatever the emulator did
+    presented: Option<Presented>,
+    // The window title is the ROM's title followed by the speed
+    title: String,
+    speed: Option<String>,
+    phosphor: Option<Phosphor>,
+}
+
+// Everything on screen that can change without the display being told, a frame that would
+// look the same isn't drawn again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Presented {
+    video_generation: u64,
+    output_size: (u32, u32),
+    sound_indicator: bool,
+    message: bool,
+}
+
+impl SdlDisplay {
+    pub fn open(video: &VideoSubsystem, options: DisplayOptions) -> Result<Self, Chip8Error> {
+        let (window_width, window_height) = options.window_size();
+        let window = video
+            .window("Chip8 Emulator", window_width, window_height)
+            .position_centered()
+            .resizable()
+            .opengl()
+            .build()
+            .map_err(|e| Chip8Error::Sdl(e.to_string()))?;
+
+        let mut canvas = window.into_canvas();
+        if options.fullscreen {
+            canvas.window_mut().set_fullscreen(true)?;
+        }
+        canvas.clear();
+        canvas.present();
+
+        let mut display = Self {
+            canvas,
+            options,
+            palette: options.palette,
+            osd: Osd::default(),
+            vsync: false,
+            presented: None,
+            title: String::new(),
+            speed: None,
+            phosphor: options.phosphor.map(Phosphor::new),
+        };
+        if options.vsync {
+            match display.enable_vsync() {
+                Ok(()) => display.vsync = true,
+                Err(e) => eprintln!("VSync disabled, pacing frames with sleeps: {}", e),
+            }
+        }
+        Ok(display)
+    }
+
+    fn enable_vsync(&mut self) -> Result<(), String> {
+        let mode = self
+            .canvas
+            .window()
+            .get_display()
+            .and_then(|display| display.get_mode())
+            .map_err(|e| e.to_string())?;
+        // Every refresh is a frame, anything but 60 Hz would change the game speed
+        if (mode.refresh_rate - FRAMES_PER_SECOND as f32).abs() > 1.0 {
+            return Err(format!("the display runs at {} Hz", mode.refresh_rate));
+        }
+        self.set_vsync(true)
+    }
+
+    fn set_vsync(&mut self, vsync: bool) -> Result<(), String> {
+        // The safe bindings don't wrap renderer vsync yet
+        let set = unsafe { sdl3::sys::render::SDL_SetRenderVSync(self.canvas.raw(), vsync as i32) };
+        if set {
+            Ok(())
+        } else {
+            Err(sdl3::get_error().to_string())
+        }
+    }
+
+    fn update_title(&mut self) {
+        let title = match &self.speed {
+            Some(speed) => format!("Chip8 Emulator - {} - {}", self.title, speed),
+            None => format!("Chip8 Emulator - {}", self.title),
+        };
+        let _ = self.canvas.window_mut().set_title(&title);
+    }
+
+    // Sizes the window to the next or previous whole scale
+    fn step_scale(&mut self, step: i32) {
+        let scale = self
+            .options
+            .scale
+            .saturating_add_signed(step)
+            .clamp(1, MAX_SCALE);
+        if scale == self.options.scale {
+            return;
+        }
+        self.options.scale = scale;
+        self.osd.show(format!("Scale: {}x", scale));
+        if self.options.fullscreen {
+            return;
+        }
+        let (width, height) = self.options.window_size();
+        if let Err(e) = self.canvas.window_mut().set_size(width, height) {
+            eprintln!("Failed to resize the window: {}", e);
+        }
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        self.options.fullscreen = !self.options.fullscreen;
+        if let Err(e) = self
+            .canvas
+            .window_mut()
+            .set_fullscreen(self.options.fullscreen)
+        {
+            eprintln!("Failed to switch fullscreen: {}", e);
+            self.options.fullscreen = !self.options.fullscreen;
+        }
+    }
+
+    fn toggle_crt(&mut self) {
+        self.options.crt = !self.options.crt;
+        self.osd.show(if self.options.crt {
+            "CRT filter on"
+        } else {
+            "CRT filter off"
+        });
+
+        let path = settings::default_path();
+        let saved = Settings::load(&path).and_then(|mut settings| {
+            settings.crt = self.options.crt;
+            settings.save(&path)
+        });
+        if let Err(e) = saved {
+            eprintln!("Failed to save settings {}: {}", path.display(), e);
+        }
+    }
+
+    fn take_screenshot(&mut self, emu: &Chip8) {
+        let path = screenshot_path();
+        match save_screenshot(emu, &self.options, &path) {
+            Ok(()) => {
+                eprintln!("Screenshot saved to {}", path.display());
+                self.osd.show("Screenshot saved");
+            }
+            Err(e) => {
+                eprintln!("Failed to save screenshot {}: {}", path.display(), e);
+                self.osd.show("Screenshot failed");
+            }
+        }
+    }
+}
+
+impl DisplayDriver for SdlDisplay {
+    fn draw(&mut self, emu: &Chip8) -> Result<(), Chip8Error> {
+        if let Some(slow_draw) = &emu.slow_draw
+            && slow_draw.is_animating()
+        {
+            self.presented = None;
+            draw_slow_draw(emu, slow_draw, &mut self.canvas, &self.options, &self.osd)?;
+            return Ok(());
+        }
+
+        let fading = match &mut self.phosphor {
+            Some(phosphor) => phosphor.update(emu.get_display()),
+            None => false,
+        };
+        let presented = Presented {
+            video_generation: emu.video_generation(),
+            output_size: self.canvas.output_size()?,
+            sound_indicator: sound_indicator_lit(emu, &self.options),
+            message: self.osd.current().is_some(),
+        };
+        // The overlay shows registers that change every frame, and with vsync presenting is
+        // what keeps time
+        if self.presented == Some(presented) && !fading && !self.options.overlay && !self.vsync {
+            return Ok(());
+        }
+        let glow = self.phosphor.as_ref().map(Phosphor::brightness);
+        draw_screen(emu, glow, &mut self.canvas, &self.options, &self.osd)?;
+        self.presented = Some(presented);
+        Ok(())
+    }
+
+    fn palette(&self) -> Palette {
+        self.options.palette
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+        self.update_title();
+    }
+
+    fn show_speed(&mut self, cpu_hz: u32, rates: Rates) {
+        self.speed = Some(format!(
+            "{} Hz - {:.0} FPS, {:.0} IPS",
+            cpu_hz, rates.fps, rates.ips
+        ));
+        self.update_title();
+    }
+
+    fn set_rom_palette(&mut self, palette: Option<Palette>) {
+        self.options.palette = palette.unwrap_or(self.palette);
+        self.presented = None;
+    }
+
+    fn show_message(&mut self, message: &str) {
+        self.osd.show(message);
+        self.presented = None;
+    }
+
+    fn paces_frames(&self) -> bool {
+        self.vsync
+    }
+
+    fn handle(&mut self, emu: &Chip8, event: InputEvent) {
+        self.presented = None;
+        match event {
+            InputEvent::ToggleFullscreen => self.toggle_fullscreen(),
+            InputEvent::ScaleUp => self.step_scale(1),
+            InputEvent::ScaleDown => self.step_scale(-1),
+            InputEvent::ToggleOverlay => self.options.overlay = !self.options.overlay,
+            InputEvent::ToggleCrt => self.toggle_crt(),
+            InputEvent::Screenshot => self.take_screenshot(emu),
+            // Presenting would hold fast-forwarding back to the refresh rate
+            InputEvent::FastForward(held) if self.vsync => {
+                if let Err(e) = self.set_vsync(!held) {
+                    eprintln!("Failed to switch vsync: {}", e);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Slow draw highlight colours
+const SPRITE_BOX: Color = Color::RGB(0, 160, 255);
+const SPRITE_ROW: Color = Color::RGB(255, 220, 0);
+const COLLISION: Color = Color::RGB(255, 40, 40);
+
+const SOUND_INDICATOR: Color = Color::RGB(255, 60, 30);
+
+// CRT filter shading, drawn over the game
+const SCANLINE: Color = Color::RGBA(0, 0, 0, 90);
+const PIXEL_GAP: Color = Color::RGBA(0, 0, 0, 60);
+// Smallest pixel in window pixels that still gets a gap around it
+const PIXEL_GAP_MIN_SIZE: u32 = 4;
+// How dark the vignette gets right at the edge
+const VIGNETTE_ALPHA: u8 = 110;
+
+// Debug overlay, drawn with SDL's built-in 8x8 debug font
+const OVERLAY_BACKGROUND: Color = Color::RGBA(0, 0, 0, 190);
+const OVERLAY_TEXT: Color = Color::RGB(220, 220, 220);
+const OVERLAY_KEY_DOWN: Color = Color::RGB(255, 220, 0);
+const OVERLAY_LINE_HEIGHT: f32 = 10.0;
+const OVERLAY_OPCODES: usize = 10;
+// The keypad as laid out on the COSMAC VIP
+const KEYPAD_ROWS: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+fn color([r, g, b]: Rgb) -> Color {
+    Color::RGB(r, g, b)
+}
+
+// Pixel `i` of a `width` x `height` framebuffer stretched over the viewport. Edges are rounded
+// so high resolution pixels tile the viewport without gaps.
+fn pixel_rect(
+    i: usize,
+    width: usize,
+    height: usize,
+    viewport: Rect,
+    options: &DisplayOptions,
+) -> Rect {
+    let (width, height) = (width as u32, height as u32);
+    let mut x = i as u32 % width;
+    let mut y = i as u32 / width;
+
+    // Mirroring only affects presentation, the framebuffer stays untouched
+    if options.flip_x {
+        x = width - 1 - x;
+    }
+    if options.flip_y {
+        y = height - 1 - y;
+    }
+
+    let (viewport_width, viewport_height) = (viewport.width(), viewport.height());
+    let left = x * viewport_width / width;
+    let top = y * viewport_height / height;
+    Rect::new(
+        viewport.x() + left as i32,
+        viewport.y() + top as i32,
+        (x + 1) * viewport_width / width - left,
+        (y + 1) * viewport_height / height - top,
+    )
+}
 
-pub fn draw_screen(emu: &Chip8, canvas: &mut Canvas<Window>) {
+// MegaChip's and the ETI-660's screens have aspect ratios of their own, SUPER-CHIP's high
+// resolution scales like the low one
+fn base_size(emu: &Chip8) -> (u32, u32) {
+    let (width, height) = if emu.megachip.enabled {
+        (MEGA_WIDTH, MEGA_HEIGHT)
+    } else if emu.hires {
+        (CHIP8_WIDTH, CHIP8_HEIGHT)
+    } else {
+        emu.geometry().size()
+    };
+    (width as u32, height as u32)
+}
+
+// `planes` has the XO-CHIP planes each pixel is lit on, drawn in the palette colour for them
+fn fill_pixels(
+    canvas: &mut Canvas<Window>,
+    planes: &[u8],
+    width: usize,
+    viewport: Rect,
+    options: &DisplayOptions,
+) -> Result<(), sdl3::Error> {
+    let height = planes.len() / width;
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    canvas.clear();
+    canvas.set_draw_color(color(options.palette.background));
+    canvas.fill_rect(viewport)?;
+
+    for (i, &mask) in planes.iter().enumerate() {
+        if mask != 0 {
+            canvas.set_draw_color(color(options.palette.color(mask)));
+            canvas.fill_rect(pixel_rect(i, width, height, viewport, options))?;
+        }
+    }
+    Ok(())
+}
+
+// Like `fill_pixels` with every pixel somewhere between the background and foreground colour
+fn fill_glow(
+    canvas: &mut Canvas<Window>,
+    glow: &[f32],
+    width: usize,
+    viewport: Rect,
+    options: &DisplayOptions,
+) -> Result<(), sdl3::Error> {
+    let height = glow.len() / width;
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    canvas.clear();
+    canvas.set_draw_color(color(options.palette.background));
+    canvas.fill_rect(viewport)?;
+
+    let (background, foreground) = (options.palette.background, options.palette.foreground);
+    for (i, &brightness) in glow.iter().enumerate() {
+        if brightness > 0.0 {
+            let [r, g, b] = [0, 1, 2].map(|c| {
+                let (from, to) = (background[c] as f32, foreground[c] as f32);
+                (from + (to - from) * brightness).round() as u8
+            });
+            canvas.set_draw_color(Color::RGB(r, g, b));
+            canvas.fill_rect(pixel_rect(i, width, height, viewport, options))?;
+        }
+    }
+    Ok(())
+}
+
+// Like `fill_pixels` with a colour of its own for every pixel, black ones are left out
+fn fill_colors(
+    canvas: &mut Canvas<Window>,
+    colors: &[Rgb],
+    width: usize,
+    viewport: Rect,
+    options: &DisplayOptions,
+) -> Result<(), sdl3::Error> {
+    let height = colors.len() / width;
     canvas.set_draw_color(Color::RGB(0, 0, 0));
     canvas.clear();
 
-    let screen_buf = emu.get_display();
+    for (i, &pixel) in colors.iter().enumerate() {
+        if pixel != [0; 3] {
+            canvas.set_draw_color(color(pixel));
+            canvas.fill_rect(pixel_rect(i, width, height, viewport, options))?;
+        }
+    }
+    Ok(())
+}
+
+// `glow` is the phosphor brightness of every pixel, drawn instead of the plain framebuffer
+pub fn draw_screen(
+    emu: &Chip8,
+    glow: Option<&[f32]>,
+    canvas: &mut Canvas<Window>,
+    options: &DisplayOptions,
+    osd: &Osd,
+) -> Result<(), sdl3::Error> {
+    let viewport = options.viewport(canvas.output_size()?, base_size(emu));
+    match (emu.display_colors(), glow) {
+        (Some(colors), _) => fill_colors(canvas, &colors, emu.display_width(), viewport, options)?,
+        (None, Some(glow)) => fill_glow(canvas, glow, emu.display_width(), viewport, options)?,
+        (None, None) => fill_pixels(
+            canvas,
+            &emu.display_planes(),
+            emu.display_width(),
+            viewport,
+            options,
+        )?,
+    }
+    if let Some(grid) = options.grid {
+        canvas.set_draw_color(color(grid));
+        canvas.fill_rects(&grid_lines(
+            viewport,
+            emu.display_width(),
+            emu.display_height(),
+        ))?;
+    }
+    if options.crt {
+        draw_crt(canvas, viewport, emu.display_width(), emu.display_height())?;
+    }
+    if sound_indicator_lit(emu, options) {
+        draw_sound_indicator(canvas, viewport)?;
+    }
+    if options.overlay {
+        draw_overlay(emu, canvas)?;
+    }
+    if let Some(message) = osd.current() {
+        draw_osd(canvas, message)?;
+    }
+    canvas.present();
+    Ok(())
+}
+
+// Shows the sprite being drawn with only the first `rows_shown` rows XORed in, its bounding box,
+// the row currently being applied and every pixel that caused a collision
+pub fn draw_slow_draw(
+    emu: &Chip8,
+    slow_draw: &SlowDraw,
+    canvas: &mut Canvas<Window>,
+    options: &DisplayOptions,
+    osd: &Osd,
+) -> Result<(), sdl3::Error> {
+    let Some(draw) = slow_draw.current() else {
+        return draw_screen(emu, None, canvas, options, osd);
+    };
+    let rows_shown = slow_draw.rows_shown();
+    let (width, height) = (draw.screen_width, draw.screen_height);
+
+    // The framebuffer already holds the finished draw, undo the rows not shown yet
+    let mut screen_buf = emu.display_planes();
+    for (row, idx) in draw.pixels() {
+        if row >= rows_shown {
+            screen_buf[idx] ^= 1;
+        }
+    }
+    let viewport = options.viewport(canvas.output_size()?, base_size(emu));
+    fill_pixels(canvas, &screen_buf, width, viewport, options)?;
 
-    canvas.set_draw_color(Color::RGB(255, 255, 255));
-    for (i, pixel) in screen_buf.iter().enumerate() {
-        if *pixel {
-            let x = (i % CHIP8_WIDTH) as u32;
-            let y = (i / CHIP8_WIDTH) as u32;
+    for (row, idx) in draw.pixels() {
+        if row >= rows_shown {
+            continue;
+        }
+        // A sprite bit that left the pixel off must have hit a lit pixel
+        if screen_buf[idx] & 1 == 0 {
+            canvas.set_draw_color(COLLISION);
+            canvas.fill_rect(pixel_rect(idx, width, height, viewport, options))?;
+        } else if row + 1 == rows_shown {
+            canvas.set_draw_color(SPRITE_ROW);
+            canvas.fill_rect(pixel_rect(idx, width, height, viewport, options))?;
+        }
+    }
 
-            let rect = Rect::new(
-                (x * SCALE_FACTOR) as i32,
-                (y * SCALE_FACTOR) as i32,
-                SCALE_FACTOR,
-                SCALE_FACTOR,
-            );
-            canvas.fill_rect(rect).unwrap();
+    canvas.set_draw_color(SPRITE_BOX);
+    for row in 0..draw.rows.len() {
+        for bit in 0..draw.width {
+            let x = (draw.x + bit) % width;
+            let y = (draw.y + row) % height;
+            let on_edge =
+                row == 0 || row + 1 == draw.rows.len() || bit == 0 || bit + 1 == draw.width;
+            if on_edge {
+                canvas.draw_rect(
+                    pixel_rect(x + width * y, width, height, viewport, options).into(),
+                )?;
+            }
         }
     }
+
+    if options.overlay {
+        draw_overlay(emu, canvas)?;
+    }
+    if let Some(message) = osd.current() {
+        draw_osd(canvas, message)?;
+    }
     canvas.present();
+    Ok(())
+}
+
+fn draw_text(canvas: &mut Canvas<Window>, x: f32, y: f32, text: &str) -> Result<(), sdl3::Error> {
+    let text = CString::new(text).expect("overlay text contains a nul byte");
+    // The safe bindings don't wrap the debug text renderer yet
+    let drawn =
+        unsafe { sdl3::sys::render::SDL_RenderDebugText(canvas.raw(), x, y, text.as_ptr()) };
+    if drawn {
+        Ok(())
+    } else {
+        Err(sdl3::get_error())
+    }
+}
+
+fn sound_indicator_lit(emu: &Chip8, options: &DisplayOptions) -> bool {
+    (options.sound_indicator || emu.buzzer.muted) && emu.sound_active()
+}
+
+// One window pixel wide lines along the left and top edge of every pixel but the first, or none
+// while the pixels are too small to spare them
+fn grid_lines(viewport: Rect, width: usize, height: usize) -> Vec<FRect> {
+    let (width, height) = (width as u32, height as u32);
+    let (viewport_width, viewport_height) = (viewport.width(), viewport.height());
+    if viewport_width / width < PIXEL_GAP_MIN_SIZE || viewport_height / height < PIXEL_GAP_MIN_SIZE
+    {
+        return Vec::new();
+    }
+
+    let columns = (1..width).map(|column| {
+        let x = viewport.x() + (column * viewport_width / width) as i32;
+        Rect::new(x, viewport.y(), 1, viewport_height)
+    });
+    let rows = (1..height).map(|row| {
+        let y = viewport.y() + (row * viewport_height / height) as i32;
+        Rect::new(viewport.x(), y, viewport_width, 1)
+    });
+    columns.chain(rows).map(FRect::from).collect()
+}
+
+// Darkens every other row of window pixels, the lines between CHIP-8 pixels once they are big
+// enough to show them and the edges of the screen
+fn draw_crt(
+    canvas: &mut Canvas<Window>,
+    viewport: Rect,
+    width: usize,
+    height: usize,
+) -> Result<(), sdl3::Error> {
+    let (viewport_width, viewport_height) = (viewport.width(), viewport.height());
+    let (left, top) = (viewport.x(), viewport.y());
+    canvas.set_blend_mode(BlendMode::Blend);
+
+    canvas.set_draw_color(SCANLINE);
+    for y in (1..viewport_height).step_by(2) {
+        canvas.fill_rect(Rect::new(left, top + y as i32, viewport_width, 1))?;
+    }
+
+    canvas.set_draw_color(PIXEL_GAP);
+    canvas.fill_rects(&grid_lines(viewport, width, height))?;
+
+    // One window pixel wide rings, lighter the further in they are
+    let depth = viewport_width.min(viewport_height) / 8;
+    for inset in 0..depth {
+        let alpha = VIGNETTE_ALPHA as u32 * (depth - inset) / depth;
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, alpha as u8));
+        let ring = Rect::new(
+            left + inset as i32,
+            top + inset as i32,
+            viewport_width - inset * 2,
+            viewport_height - inset * 2,
+        );
+        canvas.draw_rect(ring.into())?;
+    }
+
+    canvas.set_blend_mode(BlendMode::None);
+    Ok(())
+}
+
+// A square light in the top right corner of the picture
+fn draw_sound_indicator(canvas: &mut Canvas<Window>, viewport: Rect) -> Result<(), sdl3::Error> {
+    let size = (viewport.height() / 16).max(4);
+    let margin = size / 2;
+    canvas.set_draw_color(SOUND_INDICATOR);
+    canvas.fill_rect(Rect::new(
+        viewport.right() - (margin + size) as i32,
+        viewport.y() + margin as i32,
+        size,
+        size,
+    ))
+}
+
+// The message in the bottom left corner, in the OSD font scaled to the window
+fn draw_osd(canvas: &mut Canvas<Window>, message: &str) -> Result<(), sdl3::Error> {
+    let (_, height) = canvas.output_size()?;
+    let scale = (height / 120).max(2);
+    let width = (osd::text_width(message) as u32 + 4) * scale;
+    let box_height = (osd::GLYPH_HEIGHT as u32 + 4) * scale;
+    let (left, top) = (
+        scale as i32,
+        height.saturating_sub(box_height + scale) as i32,
+    );
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(OVERLAY_BACKGROUND);
+    canvas.fill_rect(Rect::new(left, top, width, box_height))?;
+    canvas.set_blend_mode(BlendMode::None);
+
+    canvas.set_draw_color(OVERLAY_TEXT);
+    for (x, y) in osd::text_pixels(message) {
+        canvas.fill_rect(Rect::new(
+            left + ((x as u32 + 2) * scale) as i32,
+            top + ((y as u32 + 2) * scale) as i32,
+            scale,
+            scale,
+        ))?;
+    }
+    Ok(())
+}
+
+fn draw_overlay(emu: &Chip8, canvas: &mut Canvas<Window>) -> Result<(), sdl3::Error> {
+    let cpu = debugger::cpu_state(emu);
+    let mut lines = vec![
+        format!("PC {:03X}  I {:03X}  SP {:X}", cpu.pc, cpu.index, cpu.sp),
+        format!("DT {:02X}  ST {:02X}", cpu.delay_timer, cpu.sound_timer),
+    ];
+    for (row, values) in cpu.registers.chunks(4).enumerate() {
+        let registers = values
+            .iter()
+            .enumerate()
+            .map(|(col, value)| format!("V{:X} {:02X}", row * 4 + col, value))
+            .collect::<Vec<_>>();
+        lines.push(registers.join(" "));
+    }
+    lines.push(String::new());
+    for (pc, opcode) in debugger::recent_instructions(emu, OVERLAY_OPCODES) {
+        lines.push(format!(
+            "{:03X} {:04X} {}",
+            pc,
+            opcode,
+            emu.decode_opcode(opcode)
+        ));
+    }
+    let keypad_top = (lines.len() + 1) as f32 * OVERLAY_LINE_HEIGHT;
+
+    let width = 8 + 8 * lines.iter().map(String::len).max().unwrap_or(0) as u32;
+    let height = (lines.len() + 1 + KEYPAD_ROWS.len()) as u32 * OVERLAY_LINE_HEIGHT as u32 + 8;
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(OVERLAY_BACKGROUND);
+    canvas.fill_rect(Rect::new(0, 0, width, height))?;
+    canvas.set_blend_mode(BlendMode::None);
+
+    canvas.set_draw_color(OVERLAY_TEXT);
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(canvas, 4.0, 4.0 + i as f32 * OVERLAY_LINE_HEIGHT, line)?;
+    }
+    for (row, keys) in KEYPAD_ROWS.iter().enumerate() {
+        for (col, &key) in keys.iter().enumerate() {
+            let down = emu.keypad[key as usize];
+            canvas.set_draw_color(if down { OVERLAY_KEY_DOWN } else { OVERLAY_TEXT });
+            let label = if down {
+                format!("[{:X}]", key)
+            } else {
+                format!(" {:X} ", key)
+            };
+            draw_text(
+                canvas,
+                4.0 + col as f32 * 32.0,
+                4.0 + keypad_top + row as f32 * OVERLAY_LINE_HEIGHT,
+                &label,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// chip8-<unix time in milliseconds>.png in the working directory
+pub fn screenshot_path() -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    PathBuf::from(format!("chip8-{}.png", timestamp))
+}
+
+// Writes the screen as a PNG the size of the unresized window, in the palette's colours and
+// mirrored like the window
+pub fn save_screenshot(emu: &Chip8, options: &DisplayOptions, path: &Path) -> io::Result<()> {
+    let (width, height) = options.window_size();
+    let (screen_width, screen_height) = (emu.display_width(), emu.display_height());
+    let screen = emu.display_planes();
+    let colors = emu.display_colors();
+
+    let mut data = Vec::with_capacity(width as usize * height as usize * 3);
+    for y in 0..height as usize {
+        let mut screen_y = y * screen_height / height as usize;
+        if options.flip_y {
+            screen_y = screen_height - 1 - screen_y;
+        }
+        for x in 0..width as usize {
+            let mut screen_x = x * screen_width / width as usize;
+            if options.flip_x {
+                screen_x = screen_width - 1 - screen_x;
+            }
+            let i = screen_x + screen_width * screen_y;
+            data.extend_from_slice(&match &colors {
+                Some(colors) => colors[i],
+                None => options.palette.color(screen[i]),
+            });
+        }
+    }
+
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(())
 }
@@ -1,23 +1,325 @@
-use sdl3::keyboard::Keycode;
-
-pub fn process_input(key: Keycode) -> Option<usize> {
-    match key {
-        Keycode::_1 => Some(0x1),
-        Keycode::_2 => Some(0x2),
-        Keycode::_3 => Some(0x3),
-        Keycode::_4 => Some(0xC),
-        Keycode::Q => Some(0x4),
-        Keycode::W => Some(0x5),
-        Keycode::E => Some(0x6),
-        Keycode::R => Some(0xD),
-        Keycode::A => Some(0x7),
-        Keycode::S => Some(0x8),
-        Keycode::D => Some(0x9),
-        Keycode::F => Some(0xE),
-        Keycode::Z => Some(0xA),
-        Keycode::X => Some(0x0),
-        Keycode::C => Some(0xB),
-        Keycode::V => Some(0xF),
-        _ => None,
+use sdl3::event::{Event, WindowEvent};
+use sdl3::gamepad::{Button, Gamepad};
+use sdl3::keyboard::{Keycode, Mod, Scancode};
+use sdl3::{EventPump, GamepadSubsystem};
+
+use crate::debugger;
+use crate::drivers::rumble_driver::Rumble;
+use crate::drivers::{InputDriver, InputEvent};
+use crate::keymap::{self, KeyMap};
+use crate::keypad::KEY_COUNT;
+
+// D-pad on the usual 2/4/6/8 movement keys, face buttons on keys games tend to use for
+// actions
+pub const DEFAULT_GAMEPAD_MAPPING: [(Button, usize); 8] = [
+    (Button::DPadUp, 0x2),
+    (Button::DPadDown, 0x8),
+    (Button::DPadLeft, 0x4),
+    (Button::DPadRight, 0x6),
+    (Button::South, 0x5),
+    (Button::East, 0x0),
+    (Button::West, 0xA),
+    (Button::North, 0xB),
+];
+
+// A keymap key name resolved either way `KeyMap::symbolic_keys` can ask for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyId {
+    Scancode(Scancode),
+    Keycode(Keycode),
+}
+
+impl KeyId {
+    fn from_name(name: &str, symbolic: bool) -> Option<Self> {
+        if symbolic {
+            Keycode::from_name(name).map(Self::Keycode)
+        } else {
+            Scancode::from_name(name).map(Self::Scancode)
+        }
+    }
+
+    fn matches(self, keycode: Keycode, scancode: Option<Scancode>) -> bool {
+        match self {
+            Self::Scancode(mapped) => scancode == Some(mapped),
+            Self::Keycode(mapped) => keycode == mapped,
+        }
+    }
+}
+
+// Resolves the key names of a keymap to SDL keys, unknown names are skipped with a warning
+pub fn keyboard_mapping(keymap: &KeyMap) -> Vec<(KeyId, usize)> {
+    resolve_keys(keymap, &keymap.keys, "keypad")
+}
+
+// The same for the second player's keys
+pub fn second_keyboard_mapping(keymap: &KeyMap) -> Vec<(KeyId, usize)> {
+    resolve_keys(keymap, &keymap.second_keys, "second keypad")
+}
+
+// And for the auto-fire keys
+pub fn turbo_mapping(keymap: &KeyMap) -> Vec<(KeyId, usize)> {
+    resolve_keys(keymap, &keymap.turbo, "turbo")
+}
+
+fn resolve_keys(
+    keymap: &KeyMap,
+    keys: &[Vec<String>; KEY_COUNT],
+    keypad: &str,
+) -> Vec<(KeyId, usize)> {
+    keymap::bindings(keys)
+        .filter_map(
+            |(key, name)| match KeyId::from_name(name, keymap.symbolic_keys) {
+                Some(id) => Some((id, key)),
+                None => {
+                    eprintln!("Unknown key name '{}' for {} key {:X}", name, keypad, key);
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+pub fn process_input(
+    keycode: Keycode,
+    scancode: Option<Scancode>,
+    mapping: &[(KeyId, usize)],
+) -> Option<usize> {
+    mapping
+        .iter()
+        .find(|(mapped, _)| mapped.matches(keycode, scancode))
+        .map(|&(_, k)| k)
+}
+
+// Keeps every connected gamepad open so its button events arrive, and maps the buttons to
+// keypad keys.
+pub struct GamepadInput {
+    subsystem: GamepadSubsystem,
+    gamepads: Vec<(u32, Gamepad)>,
+    mapping: Vec<(Button, usize)>,
+}
+
+impl GamepadInput {
+    // `remap` holds (SDL button name, key) pairs that replace the default binding of that
+    // button, e.g. ("a", 0x1)
+    pub fn new(subsystem: GamepadSubsystem, remap: &[(String, usize)]) -> Self {
+        let mut mapping = DEFAULT_GAMEPAD_MAPPING.to_vec();
+        for (name, key) in remap {
+            match Button::from_string(name) {
+                Some(button) => {
+                    mapping.retain(|(mapped, _)| *mapped != button);
+                    mapping.push((button, *key));
+                }
+                None => eprintln!("Unknown gamepad button '{}'", name),
+            }
+        }
+
+        Self {
+            subsystem,
+            gamepads: Vec::new(),
+            mapping,
+        }
+    }
+
+    // SDL reports gamepads that were already connected at startup as added too
+    pub fn gamepad_added(&mut self, id: u32) {
+        if self.gamepads.iter().any(|(open_id, _)| *open_id == id) {
+            return;
+        }
+
+        match self.subsystem.open(id) {
+            Ok(gamepad) => self.gamepads.push((id, gamepad)),
+            Err(e) => eprintln!("Failed to open gamepad {}: {}", id, e),
+        }
+    }
+
+    pub fn gamepad_removed(&mut self, id: u32) {
+        self.gamepads.retain(|(open_id, _)| *open_id != id);
+    }
+
+    pub fn key(&self, button: Button) -> Option<usize> {
+        self.mapping
+            .iter()
+            .find(|(mapped, _)| *mapped == button)
+            .map(|&(_, key)| key)
+    }
+}
+
+// Keyboard and gamepads through the SDL event queue, which also carries the window's close
+// button and the frontend hotkeys
+pub struct SdlInput {
+    event_pump: EventPump,
+    keyboard: Vec<(KeyId, usize)>,
+    second_keyboard: Vec<(KeyId, usize)>,
+    turbo: Vec<(KeyId, usize)>,
+    gamepads: Option<GamepadInput>,
+    rumble: Option<Rumble>,
+}
+
+impl SdlInput {
+    pub fn new(
+        event_pump: EventPump,
+        keyboard: Vec<(KeyId, usize)>,
+        second_keyboard: Vec<(KeyId, usize)>,
+        turbo: Vec<(KeyId, usize)>,
+        gamepads: Option<GamepadInput>,
+        rumble: Option<Rumble>,
+    ) -> Self {
+        Self {
+            event_pump,
+            keyboard,
+            second_keyboard,
+            turbo,
+            gamepads,
+            rumble,
+        }
+    }
+
+    // A key of the first keypad, then the second one and then a turbo key, whichever has a
+    // binding for it first
+    fn key_event(
+        &self,
+        keycode: Keycode,
+        scancode: Option<Scancode>,
+        pressed: bool,
+    ) -> Option<InputEvent> {
+        if let Some(key) = process_input(keycode, scancode, &self.keyboard) {
+            return Some(InputEvent::Key { key, pressed });
+        }
+        if let Some(key) = process_input(keycode, scancode, &self.second_keyboard) {
+            return Some(InputEvent::SecondKey { key, pressed });
+        }
+        let key = process_input(keycode, scancode, &self.turbo)?;
+        Some(InputEvent::Turbo { key, held: pressed })
+    }
+
+    fn event(&mut self, event: Event) -> Option<InputEvent> {
+        let input = match event {
+            Event::Quit { .. }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => InputEvent::Quit,
+            Event::KeyDown {
+                keycode: Some(keycode),
+                scancode,
+                keymod,
+                repeat,
+                ..
+            } => {
+                let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+                let alt = keymod.intersects(Mod::LALTMOD | Mod::RALTMOD);
+                let ctrl = keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+                match keycode {
+                    Keycode::PageDown if !repeat => InputEvent::NextRom,
+                    Keycode::PageUp if !repeat => InputEvent::PreviousRom,
+                    Keycode::Home if !repeat => InputEvent::BootMenu,
+                    Keycode::F2 if !repeat => InputEvent::Reset,
+                    Keycode::F3 if !repeat => InputEvent::ToggleCrt,
+                    Keycode::R if ctrl && !repeat => InputEvent::ReloadRom,
+                    Keycode::O if ctrl && !repeat => InputEvent::PickRom,
+                    Keycode::Backspace => InputEvent::Rewind(true),
+                    Keycode::F5 if !repeat => InputEvent::SaveState,
+                    Keycode::F6 if !repeat => InputEvent::NextSlot,
+                    Keycode::F7 if !repeat => InputEvent::LoadState,
+                    Keycode::F8 if !repeat => InputEvent::ToggleBreakpoint,
+                    Keycode::F9 if !repeat => InputEvent::ToggleDebugPause,
+                    Keycode::Space if !repeat => InputEvent::TogglePause,
+                    Keycode::N => InputEvent::AdvanceFrame,
+                    Keycode::Tab => InputEvent::FastForward(true),
+                    Keycode::Equals | Keycode::Plus | Keycode::KpPlus if ctrl => {
+                        InputEvent::ScaleUp
+                    }
+                    Keycode::Minus | Keycode::KpMinus if ctrl => InputEvent::ScaleDown,
+                    Keycode::Equals | Keycode::Plus | Keycode::KpPlus => InputEvent::SpeedUp,
+                    Keycode::Minus | Keycode::KpMinus => InputEvent::SlowDown,
+                    Keycode::M if !repeat => InputEvent::ToggleMute,
+                    Keycode::F10 if shift => InputEvent::Step(debugger::DEFAULT_STEP_COUNT),
+                    Keycode::F10 => InputEvent::Step(1),
+                    Keycode::F11 if !repeat => InputEvent::ToggleFullscreen,
+                    Keycode::Return if alt && !repeat => InputEvent::ToggleFullscreen,
+                    Keycode::F1 if !repeat => InputEvent::ToggleOverlay,
+                    Keycode::F12 if shift && !repeat => InputEvent::ToggleGifRecording,
+                    Keycode::F12 if !repeat => InputEvent::Screenshot,
+                    key => self.key_event(key, scancode, true)?,
+                }
+            }
+            Event::KeyUp {
+                keycode: Some(keycode),
+                scancode,
+                ..
+            } => match keycode {
+                Keycode::Backspace => InputEvent::Rewind(false),
+                Keycode::Tab => InputEvent::FastForward(false),
+                key => self.key_event(key, scancode, false)?,
+            },
+            Event::DropFile { filename, .. } => InputEvent::OpenRom(filename),
+            Event::Window {
+                win_event: WindowEvent::Exposed,
+                ..
+            } => InputEvent::Redraw,
+            Event::Window {
+                win_event: WindowEvent::FocusGained | WindowEvent::Restored,
+                ..
+            } => InputEvent::Focus(true),
+            Event::Window {
+                win_event: WindowEvent::FocusLost | WindowEvent::Minimized,
+                ..
+            } => InputEvent::Focus(false),
+            Event::ControllerDeviceAdded { which, .. } => {
+                if let Some(rumble) = &mut self.rumble {
+                    rumble.gamepad_added(which);
+                }
+                if let Some(gamepads) = &mut self.gamepads {
+                    gamepads.gamepad_added(which);
+                }
+                return None;
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if let Some(rumble) = &mut self.rumble {
+                    rumble.gamepad_removed(which);
+                }
+                if let Some(gamepads) = &mut self.gamepads {
+                    gamepads.gamepad_removed(which);
+                }
+                return None;
+            }
+            Event::ControllerButtonDown { button, .. } => InputEvent::Key {
+                key: self.gamepads.as_ref()?.key(button)?,
+                pressed: true,
+            },
+            Event::ControllerButtonUp { button, .. } => InputEvent::Key {
+                key: self.gamepads.as_ref()?.key(button)?,
+                pressed: false,
+            },
+            _ => return None,
+        };
+        Some(input)
+    }
+}
+
+impl InputDriver for SdlInput {
+    fn poll(&mut self, events: &mut Vec<InputEvent>) {
+        while let Some(event) = self.event_pump.poll_event() {
+            if let Some(input) = self.event(event) {
+                events.push(input);
+            }
+        }
+    }
+
+    fn rumble(&mut self, active: bool) {
+        if let Some(rumble) = &mut self.rumble {
+            rumble.update(active);
+        }
+    }
+
+    #[cfg(feature = "file-dialog")]
+    fn pick_rom(&mut self) -> Option<String> {
+        let path = rfd::FileDialog::new()
+            .add_filter("CHIP-8 ROM", &["ch8", "c8", "hex", "txt", "gif"])
+            .add_filter("All files", &["*"])
+            .pick_file()?;
+        // Key releases while the dialog was open never arrived
+        self.event_pump.pump_events();
+        Some(path.to_string_lossy().into_owned())
     }
 }
@@ -0,0 +1,57 @@
+use std::error::Error;
+
+use midir::{Ignore, MidiInput, MidiInputConnection};
+
+use crate::keypad::KeyInjector;
+
+// Lowest pad on most 4x4 controllers (GM kick drum)
+pub const DEFAULT_BASE_NOTE: u8 = 36;
+
+// Pads are numbered from the bottom-left, so the bottom pad row gets the bottom keypad row
+const PAD_LAYOUT: [u8; 16] = [
+    0xA, 0x0, 0xB, 0xF, //
+    0x7, 0x8, 0x9, 0xE, //
+    0x4, 0x5, 0x6, 0xD, //
+    0x1, 0x2, 0x3, 0xC, //
+];
+
+pub fn note_to_key(note: u8, base_note: u8) -> Option<u8> {
+    let offset = note.checked_sub(base_note)?;
+    PAD_LAYOUT.get(offset as usize).copied()
+}
+
+// Connects to the first MIDI input port and feeds note on/off messages into the keypad.
+// Keep the returned connection alive for as long as input should be received.
+pub fn connect(
+    injector: KeyInjector,
+    base_note: u8,
+) -> Result<MidiInputConnection<()>, Box<dyn Error>> {
+    let mut midi_in = MidiInput::new("chip8-emu")?;
+    midi_in.ignore(Ignore::All);
+
+    let ports = midi_in.ports();
+    let port = ports.first().ok_or("no MIDI input ports found")?;
+    println!("MIDI input: {}", midi_in.port_name(port)?);
+
+    let connection = midi_in.connect(
+        port,
+        "chip8-emu-keypad",
+        move |_timestamp, message, _| {
+            if let [status, note, velocity, ..] = *message {
+                // Note on with velocity 0 is a note off by convention
+                let pressed = match status & 0xF0 {
+                    0x90 => velocity > 0,
+                    0x80 => false,
+                    _ => return,
+                };
+
+                if let Some(key) = note_to_key(note, base_note) {
+                    let _ = injector.send(key, pressed);
+                }
+            }
+        },
+        (),
+    )?;
+
+    Ok(connection)
+}
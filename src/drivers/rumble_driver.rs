@@ -0,0 +1,60 @@
+use sdl3::GamepadSubsystem;
+use sdl3::gamepad::Gamepad;
+
+// Each pulse is refreshed every frame while the sound timer runs, so a short duration
+// stops the motor on its own if the emulator stalls.
+const PULSE_MS: u32 = 100;
+
+// Pulses the rumble motor of every connected gamepad while the sound timer is active.
+pub struct Rumble {
+    subsystem: GamepadSubsystem,
+    gamepads: Vec<(u32, Gamepad)>,
+    strength: u16,
+    active: bool,
+}
+
+impl Rumble {
+    // `intensity` ranges from 0.0 (off) to 1.0 (full strength)
+    pub fn new(subsystem: GamepadSubsystem, intensity: f32) -> Self {
+        Self {
+            subsystem,
+            gamepads: Vec::new(),
+            strength: (intensity.clamp(0.0, 1.0) * u16::MAX as f32) as u16,
+            active: false,
+        }
+    }
+
+    // SDL reports gamepads that were already connected at startup as added too
+    pub fn gamepad_added(&mut self, id: u32) {
+        if self.gamepads.iter().any(|(open_id, _)| *open_id == id) {
+            return;
+        }
+
+        match self.subsystem.open(id) {
+            Ok(gamepad) => self.gamepads.push((id, gamepad)),
+            Err(e) => eprintln!("Failed to open gamepad {}: {}", id, e),
+        }
+    }
+
+    pub fn gamepad_removed(&mut self, id: u32) {
+        self.gamepads.retain(|(open_id, _)| *open_id != id);
+    }
+
+    pub fn update(&mut self, sound_active: bool) {
+        if !sound_active && !self.active {
+            return;
+        }
+
+        let (strength, duration) = if sound_active {
+            (self.strength, PULSE_MS)
+        } else {
+            (0, 0)
+        };
+
+        // Gamepads without a rumble motor return an error, which is fine to ignore
+        for (_, gamepad) in &mut self.gamepads {
+            let _ = gamepad.set_rumble(strength, strength, duration);
+        }
+        self.active = sound_active;
+    }
+}
@@ -0,0 +1,96 @@
+// Publishes the framebuffer into a memory-mapped file so other processes can read frames
+// without copies. On Linux the file lives in /dev/shm, elsewhere in the temp directory.
+//
+// Layout (all integers little-endian):
+//   0   [u8; 4]  magic "CH8F"
+//   4   u32      layout version (2)
+//   8   u32      width in pixels of the current frame
+//   12  u32      height in pixels of the current frame
+//   16  u64      frame counter, odd while a frame is being written
+//   24  [u8]     width * height pixels, row-major, 0x00 = off, 0xFF = on
+//
+// The pixel area is sized for the biggest screen, MegaChip's, width and height change with the
+// display mode. Readers should read the counter, then the size and pixels, and retry if the
+// counter was odd or changed in the meantime.
+
+use std::fs::OpenOptions;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering, fence};
+
+use memmap2::MmapMut;
+
+use crate::megachip::{MEGA_HEIGHT, MEGA_WIDTH};
+use crate::{CHIP8_HEIGHT, CHIP8_WIDTH};
+
+pub const MAGIC: &[u8; 4] = b"CH8F";
+pub const LAYOUT_VERSION: u32 = 2;
+pub const HEADER_SIZE: usize = 24;
+const COUNTER_OFFSET: usize = 16;
+
+pub struct SharedFramebuffer {
+    map: MmapMut,
+    path: PathBuf,
+    frame: u64,
+}
+
+impl SharedFramebuffer {
+    pub fn create(name: &str) -> io::Result<Self> {
+        let path = shm_dir().join(name);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        file.set_len((HEADER_SIZE + MEGA_WIDTH * MEGA_HEIGHT) as u64)?;
+
+        // Safety: the file was just created by us and is never truncated while mapped
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        map[0..4].copy_from_slice(MAGIC);
+        map[4..8].copy_from_slice(&LAYOUT_VERSION.to_le_bytes());
+        map[8..12].copy_from_slice(&(CHIP8_WIDTH as u32).to_le_bytes());
+        map[12..16].copy_from_slice(&(CHIP8_HEIGHT as u32).to_le_bytes());
+
+        Ok(Self {
+            map,
+            path,
+            frame: 0,
+        })
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn publish(&mut self, video: &[bool], width: usize, height: usize) {
+        let counter = self.counter();
+
+        counter.store(self.frame * 2 + 1, Ordering::Release);
+        fence(Ordering::Release);
+
+        self.map[8..12].copy_from_slice(&(width as u32).to_le_bytes());
+        self.map[12..16].copy_from_slice(&(height as u32).to_le_bytes());
+        for (dst, &pixel) in self.map[HEADER_SIZE..].iter_mut().zip(video) {
+            *dst = if pixel { 0xFF } else { 0x00 };
+        }
+
+        self.frame += 1;
+        self.counter().store(self.frame * 2, Ordering::Release);
+    }
+
+    fn counter(&self) -> &AtomicU64 {
+        // Safety: the mapping is page aligned so offset 16 is 8-byte aligned, and the
+        // counter is only ever accessed atomically
+        unsafe { &*(self.map.as_ptr().add(COUNTER_OFFSET) as *const AtomicU64) }
+    }
+}
+
+fn shm_dir() -> PathBuf {
+    let dev_shm = PathBuf::from("/dev/shm");
+    if cfg!(target_os = "linux") && dev_shm.is_dir() {
+        dev_shm
+    } else {
+        std::env::temp_dir()
+    }
+}
@@ -0,0 +1,375 @@
+// Plays in the terminal, for SSH sessions and machines without a display. Every two CHIP-8
+// pixel rows become one row of upper half blocks, the top pixel in the foreground colour and
+// the bottom one in the background colour, so the terminal needs 24-bit colour and 64x17
+// cells (128x33 for hires). Only cells that changed are redrawn.
+//
+// Most terminals only report key presses, so a key counts as held for HOLD_FRAMES after its
+// last press or auto-repeat. Terminals that speak the kitty keyboard protocol report releases
+// and get exact timing.
+
+use std::io::{self, Stdout, Write};
+use std::time::Duration;
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{
+    self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
+};
+use crossterm::{execute, queue};
+
+use crate::drivers::{DisplayDriver, InputDriver, InputEvent};
+use crate::error::Chip8Error;
+use crate::keymap::{self, KeyMap};
+use crate::osd::Osd;
+use crate::palette::{Palette, Rgb};
+use crate::playlist::Playlist;
+use crate::run_loop::{self, FrontendOptions};
+use crate::{Chip8, debugger};
+
+// Long enough to bridge the usual delay before a held key starts repeating
+const HOLD_FRAMES: u32 = 30;
+
+pub fn run_playlist(
+    emu: &mut Chip8,
+    options: &mut FrontendOptions,
+    playlist: &mut Playlist,
+    cpu_hz: u32,
+    palette: Palette,
+) -> Result<(), Chip8Error> {
+    let mut display = TerminalDisplay::open(palette)?;
+    // Dropped first, so the keyboard mode is reset before the screen
+    let mut input = TerminalInput::new(&options.keymap)?;
+    run_loop::run(
+        emu,
+        options,
+        playlist,
+        cpu_hz,
+        &mut display,
+        &mut input,
+        &mut (),
+    )
+}
+
+fn color([r, g, b]: Rgb) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+pub struct TerminalDisplay {
+    stdout: Stdout,
+    palette: Palette,
+    // The palette it opened with, for ROMs without colours of their own
+    default_palette: Palette,
+    title: String,
+    // The pixels on the terminal and its size when they were drawn, anything else redraws all
+    shown: Vec<bool>,
+    shown_width: usize,
+    shown_generation: u64,
+    terminal_size: (u16, u16),
+    // Whether the status line shows the buzzer, there is no sound in the terminal
+    shown_sound: bool,
+    // Messages replace the title in the status line while they are up
+    osd: Osd,
+    shown_message: Option<String>,
+}
+
+impl TerminalDisplay {
+    pub fn open(palette: Palette) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        if let Err(e) = execute!(stdout, EnterAlternateScreen, Hide) {
+            let _ = terminal::disable_raw_mode();
+            return Err(e);
+        }
+
+        Ok(Self {
+            stdout,
+            palette,
+            default_palette: palette,
+            title: String::new(),
+            shown: Vec::new(),
+            shown_width: 0,
+            shown_generation: 0,
+            terminal_size: (0, 0),
+            shown_sound: false,
+            osd: Osd::default(),
+            shown_message: None,
+        })
+    }
+
+    fn draw_status(&mut self, row: u16) -> io::Result<()> {
+        queue!(
+            self.stdout,
+            MoveTo(0, row),
+            ResetColor,
+            Clear(ClearType::CurrentLine),
+            Print(match &self.shown_message {
+                Some(message) => message.clone(),
+                None => format!("{} - Esc quits", self.title),
+            }),
+            Print(if self.shown_sound { "  ♪" } else { "" })
+        )
+    }
+}
+
+impl Drop for TerminalDisplay {
+    fn drop(&mut self) {
+        let _ = execute!(self.stdout, ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl DisplayDriver for TerminalDisplay {
+    fn draw(&mut self, emu: &Chip8) -> Result<(), Chip8Error> {
+        let (width, height) = (emu.display_width(), emu.display_height());
+        let screen = emu.get_display();
+
+        let size = terminal::size()?;
+        let redraw = size != self.terminal_size || width != self.shown_width;
+        if redraw {
+            queue!(self.stdout, ResetColor, Clear(ClearType::All))?;
+            self.shown = vec![false; screen.len()];
+            self.shown_width = width;
+            self.terminal_size = size;
+        }
+        let message = self.osd.current();
+        if redraw
+            || emu.sound_active() != self.shown_sound
+            || message != self.shown_message.as_deref()
+        {
+            self.shown_sound = emu.sound_active();
+            self.shown_message = message.map(str::to_string);
+            self.draw_status((height / 2) as u16)?;
+        }
+        if !redraw && emu.video_generation() == self.shown_generation {
+            self.stdout.flush()?;
+            return Ok(());
+        }
+        self.shown_generation = emu.video_generation();
+
+        let (foreground, background) = (self.palette.foreground, self.palette.background);
+        // What the cursor and colours were left at, to skip repeating them
+        let mut cursor = None;
+        let mut colors = None;
+        for row in 0..height / 2 {
+            for x in 0..width {
+                let (top, bottom) = (x + 2 * row * width, x + (2 * row + 1) * width);
+                if !redraw && self.shown[top] == screen[top] && self.shown[bottom] == screen[bottom]
+                {
+                    continue;
+                }
+
+                if cursor != Some((x, row)) {
+                    queue!(self.stdout, MoveTo(x as u16, row as u16))?;
+                }
+                let cell = (screen[top], screen[bottom]);
+                if colors != Some(cell) {
+                    let shade = |lit| color(if lit { foreground } else { background });
+                    queue!(
+                        self.stdout,
+                        SetForegroundColor(shade(cell.0)),
+                        SetBackgroundColor(shade(cell.1))
+                    )?;
+                    colors = Some(cell);
+                }
+                queue!(self.stdout, Print('▀'))?;
+                cursor = Some((x + 1, row));
+            }
+        }
+        self.shown.copy_from_slice(screen);
+
+        self.stdout.flush()?;
+        Ok(())
+    }
+
+    fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+        let _ = execute!(self.stdout, SetTitle(format!("Chip8 Emulator - {}", title)));
+        // Shown with the next full redraw
+        self.terminal_size = (0, 0);
+    }
+
+    fn set_rom_palette(&mut self, palette: Option<Palette>) {
+        self.palette = palette.unwrap_or(self.default_palette);
+        self.terminal_size = (0, 0);
+    }
+
+    fn show_message(&mut self, message: &str) {
+        self.osd.show(message);
+    }
+}
+
+// Resolves the SDL key names of a keymap to terminal keys, unknown names are skipped with a
+// warning
+pub fn keyboard_mapping(keymap: &KeyMap) -> Vec<(KeyCode, usize)> {
+    keymap::bindings(&keymap.keys)
+        .filter_map(|(key, name)| match key_code(name) {
+            Some(code) => Some((code, key)),
+            None => {
+                eprintln!("Unknown key name '{}' for keypad key {:X}", name, key);
+                None
+            }
+        })
+        .collect()
+}
+
+fn key_code(name: &str) -> Option<KeyCode> {
+    // Terminals can't tell the number pad apart from the other digits
+    let name = name.strip_prefix("Keypad ").unwrap_or(name);
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c.to_ascii_lowercase()));
+    }
+
+    match name.to_ascii_lowercase().as_str() {
+        "space" => Some(KeyCode::Char(' ')),
+        "return" | "enter" => Some(KeyCode::Enter),
+        "up" => Some(KeyCode::Up),
+        "down" => Some(KeyCode::Down),
+        "left" => Some(KeyCode::Left),
+        "right" => Some(KeyCode::Right),
+        "insert" => Some(KeyCode::Insert),
+        "delete" => Some(KeyCode::Delete),
+        "end" => Some(KeyCode::End),
+        _ => None,
+    }
+}
+
+// The SDL frontend's hotkeys as far as terminals send them, Ctrl+C quits as well since raw
+// mode swallows the signal
+pub struct TerminalInput {
+    keyboard: Vec<(KeyCode, usize)>,
+    // Whether the terminal reports key releases
+    releases: bool,
+    // Keys held down and, without releases, the frames left until they count as let go
+    held: Vec<(KeyCode, u32)>,
+}
+
+impl TerminalInput {
+    pub fn new(keymap: &KeyMap) -> io::Result<Self> {
+        let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if releases {
+            execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        Ok(Self {
+            keyboard: keyboard_mapping(keymap),
+            releases,
+            held: Vec::new(),
+        })
+    }
+
+    // What a key that is held down means, Some for keypad keys, rewind and fast forward
+    fn hold(&self, code: KeyCode, pressed: bool) -> Option<InputEvent> {
+        match code {
+            KeyCode::Backspace => Some(InputEvent::Rewind(pressed)),
+            KeyCode::Tab => Some(InputEvent::FastForward(pressed)),
+            code => {
+                let code = match code {
+                    KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+                    code => code,
+                };
+                self.keyboard
+                    .iter()
+                    .find(|(mapped, _)| *mapped == code)
+                    .map(|&(_, key)| InputEvent::Key { key, pressed })
+            }
+        }
+    }
+
+    fn press(&self, key: KeyEvent) -> Option<InputEvent> {
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        let input = match key.code {
+            KeyCode::Esc => InputEvent::Quit,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => InputEvent::Quit,
+            KeyCode::PageDown => InputEvent::NextRom,
+            KeyCode::PageUp => InputEvent::PreviousRom,
+            KeyCode::Home => InputEvent::BootMenu,
+            KeyCode::F(2) => InputEvent::Reset,
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                InputEvent::ReloadRom
+            }
+            KeyCode::F(5) => InputEvent::SaveState,
+            KeyCode::F(6) => InputEvent::NextSlot,
+            KeyCode::F(7) => InputEvent::LoadState,
+            KeyCode::F(8) => InputEvent::ToggleBreakpoint,
+            KeyCode::F(9) => InputEvent::ToggleDebugPause,
+            KeyCode::F(10) if shift => InputEvent::Step(debugger::DEFAULT_STEP_COUNT),
+            KeyCode::F(10) => InputEvent::Step(1),
+            KeyCode::F(12) => InputEvent::ToggleGifRecording,
+            KeyCode::Char(' ') => InputEvent::TogglePause,
+            KeyCode::Char('n') => InputEvent::AdvanceFrame,
+            KeyCode::Char('+' | '=') => InputEvent::SpeedUp,
+            KeyCode::Char('-') => InputEvent::SlowDown,
+            KeyCode::Char('m') => InputEvent::ToggleMute,
+            _ => return None,
+        };
+        Some(input)
+    }
+}
+
+impl Drop for TerminalInput {
+    fn drop(&mut self) {
+        if self.releases {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
+    }
+}
+
+impl InputDriver for TerminalInput {
+    fn poll(&mut self, events: &mut Vec<InputEvent>) {
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            let Ok(Event::Key(key)) = event::read() else {
+                continue;
+            };
+
+            let held = self.held.iter().position(|(code, _)| *code == key.code);
+            match (key.kind, held) {
+                (KeyEventKind::Release, Some(index)) => {
+                    self.held.remove(index);
+                    events.extend(self.hold(key.code, false));
+                }
+                (KeyEventKind::Release, None) => {}
+                // Auto-repeat only keeps a held key down
+                (_, Some(index)) => self.held[index].1 = HOLD_FRAMES,
+                (KeyEventKind::Repeat, None) => {}
+                (_, None) => {
+                    if let Some(input) = self.press(key) {
+                        events.push(input);
+                    } else if let Some(input) = self.hold(key.code, true) {
+                        self.held.push((key.code, HOLD_FRAMES));
+                        events.push(input);
+                    }
+                }
+            }
+        }
+
+        if !self.releases {
+            for (_, frames) in &mut self.held {
+                *frames = frames.saturating_sub(1);
+            }
+            let released: Vec<KeyCode> = self
+                .held
+                .iter()
+                .filter(|(_, frames)| *frames == 0)
+                .map(|&(code, _)| code)
+                .collect();
+            self.held.retain(|(_, frames)| *frames > 0);
+            events.extend(
+                released
+                    .into_iter()
+                    .filter_map(|code| self.hold(code, false)),
+            );
+        }
+    }
+}
@@ -0,0 +1,366 @@
+use std::io::{self, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::keypad::KeyInjector;
+use crate::metrics::Metrics;
+use crate::{CHIP8_HEIGHT, CHIP8_WIDTH};
+
+// There is no authentication, so only local clients unless asked otherwise
+pub const DEFAULT_ADDR: &str = "127.0.0.1:5900";
+pub const VNC_SCALE: usize = 8;
+
+// Every mode is scaled to this width, the height follows the mode's aspect ratio
+const FB_WIDTH: usize = CHIP8_WIDTH * VNC_SCALE;
+
+// Pseudo-encoding a client lists when it can follow the framebuffer changing size
+const DESKTOP_SIZE_ENCODING: i32 = -223;
+
+fn framebuffer_size(width: usize, height: usize) -> (usize, usize) {
+    let scale = (FB_WIDTH / width).max(1);
+    (width * scale, height * scale)
+}
+
+// Keysyms for printable ASCII characters are the characters themselves
+fn keysym_to_key(keysym: u32) -> Option<u8> {
+    let c = char::from_u32(keysym)?.to_ascii_lowercase();
+
+    match c {
+        '1' => Some(0x1),
+        '2' => Some(0x2),
+        '3' => Some(0x3),
+        '4' => Some(0xC),
+        'q' => Some(0x4),
+        'w' => Some(0x5),
+        'e' => Some(0x6),
+        'r' => Some(0xD),
+        'a' => Some(0x7),
+        's' => Some(0x8),
+        'd' => Some(0x9),
+        'f' => Some(0xE),
+        'z' => Some(0xA),
+        'x' => Some(0x0),
+        'c' => Some(0xB),
+        'v' => Some(0xF),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PixelFormat {
+    bits_per_pixel: u8,
+    big_endian: bool,
+    red_max: u16,
+    green_max: u16,
+    blue_max: u16,
+    red_shift: u8,
+    green_shift: u8,
+    blue_shift: u8,
+}
+
+impl PixelFormat {
+    const DEFAULT: PixelFormat = PixelFormat {
+        bits_per_pixel: 32,
+        big_endian: false,
+        red_max: 255,
+        green_max: 255,
+        blue_max: 255,
+        red_shift: 16,
+        green_shift: 8,
+        blue_shift: 0,
+    };
+
+    fn to_bytes(self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[0] = self.bits_per_pixel;
+        bytes[1] = 24;
+        bytes[2] = self.big_endian as u8;
+        bytes[3] = 1; // true colour
+        bytes[4..6].copy_from_slice(&self.red_max.to_be_bytes());
+        bytes[6..8].copy_from_slice(&self.green_max.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.blue_max.to_be_bytes());
+        bytes[10] = self.red_shift;
+        bytes[11] = self.green_shift;
+        bytes[12] = self.blue_shift;
+        bytes
+    }
+
+    // Only true colour formats `encode` can pack into a whole pixel are accepted
+    fn from_bytes(bytes: &[u8; 16]) -> io::Result<Self> {
+        let format = Self {
+            bits_per_pixel: bytes[0],
+            big_endian: bytes[2] != 0,
+            red_max: u16::from_be_bytes([bytes[4], bytes[5]]),
+            green_max: u16::from_be_bytes([bytes[6], bytes[7]]),
+            blue_max: u16::from_be_bytes([bytes[8], bytes[9]]),
+            red_shift: bytes[10],
+            green_shift: bytes[11],
+            blue_shift: bytes[12],
+        };
+
+        let bits = format.bits_per_pixel;
+        let shifts = [format.red_shift, format.green_shift, format.blue_shift];
+        if !matches!(bits, 8 | 16 | 32)
+            || bytes[3] == 0
+            || shifts.iter().any(|&shift| shift >= bits)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported pixel format {:?}", format),
+            ));
+        }
+        Ok(format)
+    }
+
+    // Encodes a greyscale pixel (the display is monochrome) in the client's format
+    fn encode(&self, on: bool, out: &mut Vec<u8>) {
+        let level = |max: u16| if on { max as u32 } else { 0 };
+        let value = (level(self.red_max) << self.red_shift)
+            | (level(self.green_max) << self.green_shift)
+            | (level(self.blue_max) << self.blue_shift);
+
+        let bytes = (self.bits_per_pixel / 8).max(1) as usize;
+        if self.big_endian {
+            out.extend_from_slice(&value.to_be_bytes()[4 - bytes..]);
+        } else {
+            out.extend_from_slice(&value.to_le_bytes()[..bytes]);
+        }
+    }
+}
+
+struct Frame {
+    pixels: Vec<bool>,
+    width: usize,
+    height: usize,
+    number: u64,
+}
+
+struct Shared {
+    frame: Mutex<Frame>,
+    new_frame: Condvar,
+}
+
+// Minimal RFB 3.8 server without authentication, sending raw-encoded full frames.
+pub struct VncServer {
+    shared: Arc<Shared>,
+}
+
+impl VncServer {
+    pub fn start(addr: &str, keys: KeyInjector, metrics: Option<Arc<Metrics>>) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let shared = Arc::new(Shared {
+            frame: Mutex::new(Frame {
+                pixels: vec![false; CHIP8_WIDTH * CHIP8_HEIGHT],
+                width: CHIP8_WIDTH,
+                height: CHIP8_HEIGHT,
+                number: 0,
+            }),
+            new_frame: Condvar::new(),
+        });
+
+        let server_shared = Arc::clone(&shared);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = Arc::clone(&server_shared);
+                let keys = keys.clone();
+                let metrics = metrics.clone();
+
+                thread::spawn(move || {
+                    if let Some(metrics) = &metrics {
+                        metrics.connected_clients.fetch_add(1, Ordering::Relaxed);
+                    }
+                    if let Err(e) = serve_client(stream, &shared, &keys) {
+                        eprintln!("VNC client disconnected: {}", e);
+                    }
+                    if let Some(metrics) = &metrics {
+                        metrics.connected_clients.fetch_sub(1, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { shared })
+    }
+
+    pub fn publish(&self, video: &[bool], width: usize, height: usize) {
+        let mut frame = self.shared.frame.lock().unwrap();
+        if frame.pixels != video || frame.width != width || frame.height != height {
+            frame.pixels.clear();
+            frame.pixels.extend_from_slice(video);
+            frame.width = width;
+            frame.height = height;
+            frame.number += 1;
+            self.shared.new_frame.notify_all();
+        }
+    }
+}
+
+fn serve_client(stream: TcpStream, shared: &Shared, keys: &KeyInjector) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    // Handshake: version, security type None, client/server init
+    writer.write_all(b"RFB 003.008\n")?;
+    let mut version = [0; 12];
+    reader.read_exact(&mut version)?;
+
+    writer.write_all(&[1, 1])?;
+    let mut security = [0; 1];
+    reader.read_exact(&mut security)?;
+    writer.write_all(&0u32.to_be_bytes())?;
+
+    let mut shared_flag = [0; 1];
+    reader.read_exact(&mut shared_flag)?;
+
+    let mut size = {
+        let frame = shared.frame.lock().unwrap();
+        framebuffer_size(frame.width, frame.height)
+    };
+
+    let name = b"Chip8 Emulator";
+    let mut init = Vec::new();
+    init.extend_from_slice(&(size.0 as u16).to_be_bytes());
+    init.extend_from_slice(&(size.1 as u16).to_be_bytes());
+    init.extend_from_slice(&PixelFormat::DEFAULT.to_bytes());
+    init.extend_from_slice(&(name.len() as u32).to_be_bytes());
+    init.extend_from_slice(name);
+    writer.write_all(&init)?;
+
+    let mut format = PixelFormat::DEFAULT;
+    let mut desktop_size = false;
+    let mut last_sent = None;
+
+    loop {
+        let mut message_type = [0; 1];
+        reader.read_exact(&mut message_type)?;
+
+        match message_type[0] {
+            // SetPixelFormat
+            0 => {
+                let mut buf = [0; 19];
+                reader.read_exact(&mut buf)?;
+                let mut pixel_format = [0; 16];
+                pixel_format.copy_from_slice(&buf[3..]);
+                format = PixelFormat::from_bytes(&pixel_format)?;
+            }
+            // SetEncodings, raw is always supported so only DesktopSize matters
+            2 => {
+                let mut header = [0; 3];
+                reader.read_exact(&mut header)?;
+                let count = u16::from_be_bytes([header[1], header[2]]) as usize;
+                let mut encodings = vec![0; count * 4];
+                reader.read_exact(&mut encodings)?;
+                desktop_size = encodings
+                    .chunks_exact(4)
+                    .any(|e| i32::from_be_bytes([e[0], e[1], e[2], e[3]]) == DESKTOP_SIZE_ENCODING);
+            }
+            // FramebufferUpdateRequest
+            3 => {
+                let mut request = [0; 9];
+                reader.read_exact(&mut request)?;
+                let incremental = request[0] != 0;
+
+                let (pixels, width, height) = {
+                    let mut frame = shared.frame.lock().unwrap();
+                    if incremental {
+                        while Some(frame.number) == last_sent {
+                            frame = shared
+                                .new_frame
+                                .wait_timeout(frame, Duration::from_millis(100))
+                                .unwrap()
+                                .0;
+                        }
+                    }
+                    last_sent = Some(frame.number);
+                    (frame.pixels.clone(), frame.width, frame.height)
+                };
+
+                // Clients that can't resize get the new mode letterboxed into the old size
+                let resized = framebuffer_size(width, height);
+                let resize = desktop_size && resized != size;
+                if resize {
+                    size = resized;
+                }
+                send_frame(&mut writer, &pixels, (width, height), size, resize, &format)?;
+            }
+            // KeyEvent
+            4 => {
+                let mut event = [0; 7];
+                reader.read_exact(&mut event)?;
+                let down = event[0] != 0;
+                let keysym = u32::from_be_bytes([event[3], event[4], event[5], event[6]]);
+
+                if let Some(key) = keysym_to_key(keysym) {
+                    let _ = keys.send(key, down);
+                }
+            }
+            // PointerEvent
+            5 => {
+                let mut event = [0; 5];
+                reader.read_exact(&mut event)?;
+            }
+            // ClientCutText
+            6 => {
+                let mut header = [0; 7];
+                reader.read_exact(&mut header)?;
+                let len = u32::from_be_bytes([header[3], header[4], header[5], header[6]]);
+                io::copy(&mut (&mut reader).take(len as u64), &mut io::sink())?;
+            }
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unsupported client message {}", other),
+                ));
+            }
+        }
+    }
+}
+
+fn send_frame(
+    writer: &mut TcpStream,
+    pixels: &[bool],
+    (width, height): (usize, usize),
+    (fb_width, fb_height): (usize, usize),
+    resize: bool,
+    format: &PixelFormat,
+) -> io::Result<()> {
+    let scale = (fb_width / width).min(fb_height / height).max(1);
+    let (left, top) = (
+        fb_width.saturating_sub(width * scale) / 2,
+        fb_height.saturating_sub(height * scale) / 2,
+    );
+    let bytes_per_pixel = (format.bits_per_pixel / 8).max(1) as usize;
+    let mut message = Vec::with_capacity(28 + fb_width * fb_height * bytes_per_pixel);
+
+    message.extend_from_slice(&[0, 0]);
+    message.extend_from_slice(&(1 + resize as u16).to_be_bytes());
+    let mut rectangle = |encoding: i32| {
+        message.extend_from_slice(&0u16.to_be_bytes());
+        message.extend_from_slice(&0u16.to_be_bytes());
+        message.extend_from_slice(&(fb_width as u16).to_be_bytes());
+        message.extend_from_slice(&(fb_height as u16).to_be_bytes());
+        message.extend_from_slice(&encoding.to_be_bytes());
+    };
+    if resize {
+        rectangle(DESKTOP_SIZE_ENCODING);
+    }
+    rectangle(0);
+
+    for y in 0..fb_height {
+        for x in 0..fb_width {
+            let source = x
+                .checked_sub(left)
+                .zip(y.checked_sub(top))
+                .map(|(x, y)| (x / scale, y / scale))
+                .filter(|&(x, y)| x < width && y < height);
+            let pixel = source.is_some_and(|(x, y)| pixels[x + width * y]);
+            format.encode(pixel, &mut message);
+        }
+    }
+
+    writer.write_all(&message)
+}
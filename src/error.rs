@@ -0,0 +1,110 @@
+use std::fmt;
+use std::io;
+
+use crate::unknown_opcode::UnknownOpcode;
+
+#[derive(Debug)]
+pub enum Chip8Error {
+    // Window, renderer or event pump creation failed
+    Sdl(String),
+    Io(io::Error),
+    RomTooLarge {
+        size: usize,
+        max: usize,
+        start: usize,
+    },
+    StackOverflow {
+        address: u16,
+        pc: u16,
+        depth: usize,
+    },
+    // A RET with nothing on the stack
+    StackUnderflow {
+        pc: u16,
+    },
+    // A write below 0x200 with `Chip8::protect_interpreter_area` set
+    ProtectedWrite {
+        address: u16,
+        pc: u16,
+    },
+    // An access past the end of memory with `OnMemoryFault::Fault`
+    MemoryFault {
+        address: u16,
+        pc: u16,
+    },
+    InvalidOpcode(UnknownOpcode),
+    // A panic in the core, caught where it would have unwound into C
+    Crashed(String),
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::Sdl(e) => write!(f, "SDL error: {}", e),
+            Chip8Error::Io(e) => write!(f, "{}", e),
+            Chip8Error::RomTooLarge { size, max, start } => write!(
+                f,
+                "ROM is {} bytes, only {} fit in memory after {:#05x}",
+                size, max, start
+            ),
+            Chip8Error::StackOverflow { address, pc, depth } => write!(
+                f,
+                "Stack overflow: CALL {:#05x} at {:#05x} exceeds depth {}",
+                address, pc, depth
+            ),
+            Chip8Error::StackUnderflow { pc } => {
+                write!(f, "Stack underflow: RET at {:#05x} with an empty stack", pc)
+            }
+            Chip8Error::ProtectedWrite { address, pc } => write!(
+                f,
+                "Write to protected memory at {:#05x} by the instruction at {:#05x}",
+                address, pc
+            ),
+            Chip8Error::MemoryFault { address, pc } => write!(
+                f,
+                "Memory fault: the instruction at {:#05x} accessed {:#06x}, past the end of memory",
+                pc, address
+            ),
+            Chip8Error::InvalidOpcode(unknown) => write!(f, "{}", unknown),
+            Chip8Error::Crashed(message) => write!(f, "Emulator crashed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Chip8Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Chip8Error {
+    fn from(e: io::Error) -> Self {
+        Chip8Error::Io(e)
+    }
+}
+
+impl From<UnknownOpcode> for Chip8Error {
+    fn from(unknown: UnknownOpcode) -> Self {
+        Chip8Error::InvalidOpcode(unknown)
+    }
+}
+
+#[cfg(feature = "sdl")]
+impl From<sdl3::Error> for Chip8Error {
+    fn from(e: sdl3::Error) -> Self {
+        Chip8Error::Sdl(e.to_string())
+    }
+}
+
+// Lets the tools built on `io::Result` keep using `?` on emulator calls
+impl From<Chip8Error> for io::Error {
+    fn from(e: Chip8Error) -> Self {
+        match e {
+            Chip8Error::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+        }
+    }
+}
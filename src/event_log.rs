@@ -0,0 +1,73 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{Value, json};
+
+use crate::rom::LoadReport;
+
+// Writes one JSON object per line so external tools can follow a session
+pub struct EventLog {
+    writer: Box<dyn Write + Send>,
+}
+
+impl EventLog {
+    // "-" logs to stdout, anything else is treated as a file path
+    pub fn open(target: &str) -> io::Result<Self> {
+        let writer: Box<dyn Write + Send> = if target == "-" {
+            Box::new(io::stdout())
+        } else {
+            Box::new(BufWriter::new(File::create(target)?))
+        };
+
+        Ok(Self { writer })
+    }
+
+    pub fn emit(&mut self, event: &str, mut fields: Value) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or(0.0);
+
+        if let Value::Object(map) = &mut fields {
+            map.insert("event".to_string(), json!(event));
+            map.insert("time".to_string(), json!(timestamp));
+        }
+
+        // Logging must never take the emulator down
+        let _ = writeln!(self.writer, "{}", fields);
+        let _ = self.writer.flush();
+    }
+
+    pub fn rom_loaded(&mut self, rom: &str, sha1: Option<&str>, report: &LoadReport) {
+        let hints: Vec<String> = report.variant_hints.iter().map(|h| h.to_string()).collect();
+
+        self.emit(
+            "rom_loaded",
+            json!({
+                "rom": rom,
+                "sha1": sha1,
+                "bytes": report.bytes_loaded,
+                "start": report.load_range.start,
+                "end": report.load_range.end,
+                "variant_hints": hints,
+            }),
+        );
+    }
+
+    pub fn frame(&mut self, frame: u64, pc: u16) {
+        self.emit("frame", json!({ "frame": frame, "pc": pc }));
+    }
+
+    pub fn state_saved(&mut self, slot: usize, path: &str) {
+        self.emit("state_saved", json!({ "slot": slot, "path": path }));
+    }
+
+    pub fn error(&mut self, message: &str, pc: u16) {
+        self.emit("error", json!({ "message": message, "pc": pc }));
+    }
+
+    pub fn halt(&mut self, reason: &str, frame: u64) {
+        self.emit("halt", json!({ "reason": reason, "frame": frame }));
+    }
+}
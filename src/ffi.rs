@@ -0,0 +1,148 @@
+// C API for embedding the core in C, C++ or anything else with a C FFI, declared in
+// include/chip8.h. That header is generated from this file by cbindgen when building with
+// `--features ffi`, see cbindgen.toml.
+//
+// `Chip8` is opaque on the C side, every call takes the pointer `chip8_new` returned. The host
+// owns the timing like the browser bindings do: call `chip8_tick` 60 times a second and draw
+// `chip8_framebuffer` after each. Nothing here panics across the boundary, bad opcodes halt
+// the emulator and `chip8_tick` reports it. A panic in the core halts it the same way instead of
+// unwinding into the host.
+//
+// Everything exported here keeps its signature, add new functions instead of changing these.
+
+use std::cell::RefCell;
+use std::ffi::{CString, c_char};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::Chip8;
+use crate::crash_dump::panic_message;
+use crate::error::Chip8Error;
+use crate::unknown_opcode::OnUnknownOpcode;
+
+pub const CHIP8_DEFAULT_TICKS_PER_FRAME: u32 = 10;
+
+thread_local! {
+    // Why the last call that failed on this thread did, see `chip8_last_error`
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+// Returns a new emulator with nothing loaded, free it with `chip8_free`
+#[unsafe(no_mangle)]
+pub extern "C" fn chip8_new() -> *mut Chip8 {
+    let mut emu = Chip8::new();
+    emu.on_unknown_opcode = OnUnknownOpcode::Halt;
+    // Rewinding is up to the host, don't pay for the states
+    emu.rewind = None;
+    Box::into_raw(Box::new(emu))
+}
+
+// Accepts null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_free(emu: *mut Chip8) {
+    if !emu.is_null() {
+        // Safety: `emu` came from `chip8_new` and isn't used again
+        drop(unsafe { Box::from_raw(emu) });
+    }
+}
+
+// Resets the emulator and loads `len` bytes of a plain binary ROM, false if it doesn't fit.
+// `data` may be null when `len` is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_load_rom(emu: *mut Chip8, data: *const u8, len: usize) -> bool {
+    let data: &[u8] = match (data.is_null(), len) {
+        (true, 0) => &[],
+        (true, _) => return false,
+        // Safety: `data` points at `len` bytes
+        (false, _) => unsafe { slice::from_raw_parts(data, len) },
+    };
+    // Safety: `emu` came from `chip8_new`
+    let emu = unsafe { &mut *emu };
+    emu.reset();
+    match emu.load_rom_bytes(data) {
+        Ok(_) => true,
+        Err(e) => {
+            set_last_error(format!("Failed to load ROM: {}", e));
+            false
+        }
+    }
+}
+
+// Runs one 60 Hz frame of `ticks_per_frame` instructions, 0 for
+// CHIP8_DEFAULT_TICKS_PER_FRAME. Returns false once the ROM has halted.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_tick(emu: *mut Chip8, ticks_per_frame: u32) -> bool {
+    // Safety: `emu` came from `chip8_new`
+    let emu = unsafe { &mut *emu };
+    let ticks = match ticks_per_frame {
+        0 => CHIP8_DEFAULT_TICKS_PER_FRAME,
+        ticks => ticks,
+    };
+
+    // Left set after a halt, so the emulator stays stopped
+    if emu.halted.is_some() {
+        return false;
+    }
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| emu.run_cycles(ticks as usize))) {
+        emu.halted = Some(Chip8Error::Crashed(panic_message(&*payload).to_string()));
+    }
+    if let Some(error) = &emu.halted {
+        set_last_error(format!("Emulation halted: {}", error));
+        return false;
+    }
+    emu.tick_timers();
+    true
+}
+
+// The visible screen, `width * height` bools row by row, true for a lit pixel. `width` and
+// `height` may be null. The pointer stays valid until the next call that takes `emu` mutably.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_framebuffer(
+    emu: *const Chip8,
+    width: *mut u32,
+    height: *mut u32,
+) -> *const bool {
+    // Safety: `emu` came from `chip8_new`, `width` and `height` are null or writable
+    unsafe {
+        let emu = &*emu;
+        if let Some(width) = width.as_mut() {
+            *width = emu.display_width() as u32;
+        }
+        if let Some(height) = height.as_mut() {
+            *height = emu.display_height() as u32;
+        }
+        emu.get_display().as_ptr()
+    }
+}
+
+// Presses or releases keypad key 0x0-0xF, false for anything else
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_keypress(emu: *mut Chip8, key: u8, pressed: bool) -> bool {
+    // Safety: `emu` came from `chip8_new`
+    let emu = unsafe { &mut *emu };
+    emu.set_key(key, pressed).is_ok()
+}
+
+// Whether the buzzer should sound right now
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_sound_active(emu: *const Chip8) -> bool {
+    // Safety: `emu` came from `chip8_new`
+    unsafe { &*emu }.sound_active()
+}
+
+// Why the last `chip8_load_rom` or `chip8_tick` on this thread returned false, null if none did.
+// The string stays valid until the next call that fails.
+#[unsafe(no_mangle)]
+pub extern "C" fn chip8_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
@@ -0,0 +1,78 @@
+// The SDL window, event loop and audio/rumble output around the emulator core. The core in
+// `Chip8` knows nothing about SDL, so it can be embedded elsewhere without this module.
+
+use crate::Chip8;
+use crate::drivers::audio_driver::SdlAudio;
+use crate::drivers::display_driver::{DisplayOptions, SdlDisplay};
+use crate::drivers::input_driver::{
+    GamepadInput, SdlInput, keyboard_mapping, second_keyboard_mapping, turbo_mapping,
+};
+use crate::drivers::rumble_driver::Rumble;
+use crate::error::Chip8Error;
+use crate::playlist::Playlist;
+use crate::run_loop::{self, FrontendOptions};
+
+pub fn run(
+    emu: &mut Chip8,
+    options: &mut FrontendOptions,
+    rom: &str,
+    cpu_hz: u32,
+    display_options: DisplayOptions,
+) -> Result<(), Chip8Error> {
+    let mut playlist = Playlist::new(vec![rom.to_string()]);
+    run_playlist(emu, options, &mut playlist, cpu_hz, display_options)
+}
+
+pub fn run_playlist(
+    emu: &mut Chip8,
+    options: &mut FrontendOptions,
+    playlist: &mut Playlist,
+    cpu_hz: u32,
+    display_options: DisplayOptions,
+) -> Result<(), Chip8Error> {
+    let sdl_context = sdl3::init()?;
+
+    let video_subsystem = sdl_context.video()?;
+    let mut display = SdlDisplay::open(&video_subsystem, display_options)?;
+
+    let rumble = options.rumble_intensity.and_then(|intensity| {
+        sdl_context
+            .gamepad()
+            .map(|subsystem| Rumble::new(subsystem, intensity))
+            .map_err(|e| eprintln!("Gamepad rumble disabled: {}", e))
+            .ok()
+    });
+    let gamepads = sdl_context
+        .gamepad()
+        .map(|subsystem| GamepadInput::new(subsystem, &options.gamepad_remap))
+        .map_err(|e| eprintln!("Gamepad input disabled: {}", e))
+        .ok();
+    let mut input = SdlInput::new(
+        sdl_context.event_pump()?,
+        keyboard_mapping(&options.keymap),
+        second_keyboard_mapping(&options.keymap),
+        turbo_mapping(&options.keymap),
+        gamepads,
+        rumble,
+    );
+
+    let mut audio = if options.sound {
+        sdl_context
+            .audio()
+            .and_then(|subsystem| SdlAudio::new(&subsystem))
+            .map_err(|e| eprintln!("Sound disabled: {}", e))
+            .ok()
+    } else {
+        None
+    };
+
+    run_loop::run(
+        emu,
+        options,
+        playlist,
+        cpu_hz,
+        &mut display,
+        &mut input,
+        &mut audio,
+    )
+}
@@ -0,0 +1,41 @@
+// The size of the low resolution screen. The COSMAC VIP's is 64x32, the ETI-660 ran CHIP-8 on
+// 64x48 and a 64x64 mode some of its interpreters offered.
+
+use crate::{CHIP8_HEIGHT, CHIP8_WIDTH};
+
+// Names `Geometry::from_name` knows
+pub const GEOMETRIES: [&str; 3] = ["64x32", "64x48", "64x64"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Geometry {
+    #[default]
+    Vip,
+    Eti660,
+    Eti660Tall,
+}
+
+impl Geometry {
+    pub fn size(self) -> (usize, usize) {
+        match self {
+            Self::Vip => (CHIP8_WIDTH, CHIP8_HEIGHT),
+            Self::Eti660 => (CHIP8_WIDTH, 48),
+            Self::Eti660Tall => (CHIP8_WIDTH, 64),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "64x32" => Some(Self::Vip),
+            "64x48" => Some(Self::Eti660),
+            "64x64" => Some(Self::Eti660Tall),
+            _ => None,
+        }
+    }
+
+    // The geometry a low resolution screen of `height` rows has, None for sizes it doesn't have
+    pub fn from_height(height: usize) -> Option<Self> {
+        [Self::Vip, Self::Eti660, Self::Eti660Tall]
+            .into_iter()
+            .find(|geometry| geometry.size().1 == height)
+    }
+}
@@ -0,0 +1,137 @@
+// Records the screen to an animated GIF, Shift+F12 in the SDL frontend or `--record-gif`. Pixels
+// are scaled up with nearest neighbour and the GIF is sized for the resolution recording
+// started in, a later switch to or from hires is stretched to fit.
+//
+// GIF delays are whole hundredths of a second, so frames are timed against a 60 Hz clock
+// rounded to centiseconds. Frames that would show for less than MIN_DELAY are merged into
+// the next one since most viewers slow down anything shorter.
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gif::{Encoder, Frame, Repeat};
+
+use crate::palette::Palette;
+
+pub const DEFAULT_GIF_SCALE: u32 = 4;
+const MIN_DELAY: u16 = 2;
+
+pub struct GifRecorder {
+    encoder: Encoder<BufWriter<File>>,
+    path: PathBuf,
+    width: usize,
+    height: usize,
+    // Frames captured so far, the 60 Hz clock the delays are taken from
+    frame: u64,
+    // The frame on screen and when it appeared, written once it changes
+    pending: Option<(Vec<u8>, u64)>,
+}
+
+// chip8-<unix time>.gif in the working directory
+pub fn default_path() -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    PathBuf::from(format!("chip8-{}.gif", timestamp))
+}
+
+fn centiseconds(frame: u64) -> u64 {
+    (frame * 100 + 30) / 60
+}
+
+fn encoding_error(e: gif::EncodingError) -> io::Error {
+    io::Error::other(e)
+}
+
+impl GifRecorder {
+    pub fn create(
+        path: impl Into<PathBuf>,
+        palette: &Palette,
+        (screen_width, screen_height): (usize, usize),
+        scale: u32,
+    ) -> io::Result<Self> {
+        let path = path.into();
+        let width = screen_width * scale as usize;
+        let height = screen_height * scale as usize;
+
+        let colors = [palette.background, palette.foreground].concat();
+        let file = BufWriter::new(File::create(&path)?);
+        let mut encoder =
+            Encoder::new(file, width as u16, height as u16, &colors).map_err(encoding_error)?;
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(encoding_error)?;
+
+        Ok(Self {
+            encoder,
+            path,
+            width,
+            height,
+            frame: 0,
+            pending: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Adds one 60 Hz frame of a `screen_width` pixels wide framebuffer
+    pub fn capture(&mut self, screen: &[bool], screen_width: usize) -> io::Result<()> {
+        let pixels = self.scale(screen, screen_width);
+
+        match self.pending.take() {
+            Some((held, start)) if held == pixels => self.pending = Some((held, start)),
+            Some((held, start)) => {
+                let delay = (centiseconds(self.frame) - centiseconds(start)) as u16;
+                if delay >= MIN_DELAY {
+                    self.write_frame(held, delay)?;
+                    self.pending = Some((pixels, self.frame));
+                } else {
+                    self.pending = Some((pixels, start));
+                }
+            }
+            None => self.pending = Some((pixels, self.frame)),
+        }
+
+        self.frame += 1;
+        Ok(())
+    }
+
+    // Writes the last frame and the end of the file
+    pub fn finish(mut self) -> io::Result<PathBuf> {
+        if let Some((held, start)) = self.pending.take() {
+            let delay = (centiseconds(self.frame) - centiseconds(start)) as u16;
+            self.write_frame(held, delay.max(MIN_DELAY))?;
+        }
+        self.encoder
+            .into_inner()
+            .map_err(encoding_error)?
+            .into_inner()
+            .map_err(|e| e.into_error())?;
+        Ok(self.path)
+    }
+
+    fn write_frame(&mut self, pixels: Vec<u8>, delay: u16) -> io::Result<()> {
+        let mut frame =
+            Frame::from_indexed_pixels(self.width as u16, self.height as u16, pixels, None);
+        frame.delay = delay;
+        self.encoder.write_frame(&frame).map_err(encoding_error)
+    }
+
+    // Palette indices for the whole GIF, nearest neighbour from whatever resolution is showing
+    fn scale(&self, screen: &[bool], screen_width: usize) -> Vec<u8> {
+        let screen_height = screen.len() / screen_width;
+        let mut pixels = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height {
+            let row = y * screen_height / self.height * screen_width;
+            for x in 0..self.width {
+                pixels.push(screen[row + x * screen_width / self.width] as u8);
+            }
+        }
+        pixels
+    }
+}
@@ -0,0 +1,445 @@
+// Decoded instructions, what `Chip8::execute` runs and the disassembler, debugger and tracer
+// print. `decode` accepts every 16-bit value, opcodes no supported instruction set defines
+// become `Unknown`. Displayed in the Cowgod mnemonics the disassembler always used.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    // 0NNN, a machine code routine on the COSMAC VIP. 0000 is a no-op, anything else unknown.
+    Sys(u16),
+    Cls,
+    Ret,
+    // SUPER-CHIP scrolling and resolution switching
+    ScrollDown(u8),
+    ScrollRight,
+    ScrollLeft,
+    Exit,
+    Low,
+    High,
+    // MegaChip, see `megachip` and `decode_megachip`
+    MegaOff,
+    MegaOn,
+    // 01NN NNNN, the top byte of I, the next word is the rest
+    LoadHighIndex(u8),
+    LoadPalette(u8),
+    SpriteWidth(u8),
+    SpriteHeight(u8),
+    ScreenAlpha(u8),
+    PlaySample(u8),
+    StopSample,
+    BlendMode(u8),
+    CollisionIndex(u8),
+    // MegaChip's 00BN and XO-CHIP's 00DN
+    ScrollUp(u8),
+    // CHIP-8X, see `chip8x` and `decode_chip8x`
+    CycleBackground,
+    // 5XY1, VX + VY with each nibble added on its own and kept below 8
+    AddNibbles { x: u8, y: u8 },
+    // BXY0, the zones VX and VX+1 pick get VY's colour
+    ColorBlocks { x: u8, y: u8 },
+    // BXYN, the zones under a sprite of `rows` rows at VX, VX+1 get VY's colour
+    ColorZones { x: u8, y: u8, rows: u8 },
+    // EXF2 and EXF5, the second keypad
+    SkipKey2(u8),
+    SkipNotKey2(u8),
+    Jump(u16),
+    Call(u16),
+    SkipEqByte { x: u8, byte: u8 },
+    SkipNeByte { x: u8, byte: u8 },
+    SkipEq { x: u8, y: u8 },
+    LoadByte { x: u8, byte: u8 },
+    AddByte { x: u8, byte: u8 },
+    Load { x: u8, y: u8 },
+    Or { x: u8, y: u8 },
+    And { x: u8, y: u8 },
+    Xor { x: u8, y: u8 },
+    Add { x: u8, y: u8 },
+    Sub { x: u8, y: u8 },
+    Shr { x: u8, y: u8 },
+    SubN { x: u8, y: u8 },
+    Shl { x: u8, y: u8 },
+    SkipNe { x: u8, y: u8 },
+    LoadIndex(u16),
+    // BNNN, `x` is the register the jump quirk adds instead of V0
+    JumpOffset { x: u8, address: u16 },
+    Random { x: u8, byte: u8 },
+    // DXYN, a 16x16 sprite when `rows` is 0
+    Draw { x: u8, y: u8, rows: u8 },
+    SkipKey(u8),
+    SkipNotKey(u8),
+    LoadDelay(u8),
+    WaitKey(u8),
+    SetDelay(u8),
+    SetSound(u8),
+    AddIndex(u8),
+    LoadFont(u8),
+    LoadBigFont(u8),
+    Bcd(u8),
+    // FX55 and FX65, V0 to VX
+    Store(u8),
+    Restore(u8),
+    // FX75 and FX85, the HP48 flag registers
+    StoreFlags(u8),
+    RestoreFlags(u8),
+    // FN01, the XO-CHIP planes to draw on as a bitmask
+    Plane(u8),
+    // XO-CHIP's F000 NNNN, all of I from the next word
+    LoadLongIndex,
+    // XO-CHIP's F002, the 16 byte sound pattern at I, and FX3A, the pitch it plays at
+    LoadPattern,
+    SetPitch(u8),
+    // XO-CHIP's 5XY2 and 5XY3, VX to VY in either direction without changing I
+    StoreRange { x: u8, y: u8 },
+    RestoreRange { x: u8, y: u8 },
+    Unknown(u16),
+}
+
+pub fn decode(opcode: u16) -> Instruction {
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let byte = (opcode & 0x00FF) as u8;
+    let address = opcode & 0x0FFF;
+
+    match (opcode >> 12, x, y, n) {
+        (0, 0, 0xC, _) => Instruction::ScrollDown(n),
+        (0, 0, 0xD, _) => Instruction::ScrollUp(n),
+        (0, 0, 0xE, 0) => Instruction::Cls,
+        (0, 0, 0xE, 0xE) => Instruction::Ret,
+        (0, 0, 0xF, 0xB) => Instruction::ScrollRight,
+        (0, 0, 0xF, 0xC) => Instruction::ScrollLeft,
+        (0, 0, 0xF, 0xD) => Instruction::Exit,
+        (0, 0, 0xF, 0xE) => Instruction::Low,
+        (0, 0, 0xF, 0xF) => Instruction::High,
+        (0, _, _, _) => Instruction::Sys(address),
+        (1, _, _, _) => Instruction::Jump(address),
+        (2, _, _, _) => Instruction::Call(address),
+        (3, _, _, _) => Instruction::SkipEqByte { x, byte },
+        (4, _, _, _) => Instruction::SkipNeByte { x, byte },
+        (5, _, _, 0) => Instruction::SkipEq { x, y },
+        (5, _, _, 2) => Instruction::StoreRange { x, y },
+        (5, _, _, 3) => Instruction::RestoreRange { x, y },
+        (6, _, _, _) => Instruction::LoadByte { x, byte },
+        (7, _, _, _) => Instruction::AddByte { x, byte },
+        (8, _, _, 0) => Instruction::Load { x, y },
+        (8, _, _, 1) => Instruction::Or { x, y },
+        (8, _, _, 2) => Instruction::And { x, y },
+        (8, _, _, 3) => Instruction::Xor { x, y },
+        (8, _, _, 4) => Instruction::Add { x, y },
+        (8, _, _, 5) => Instruction::Sub { x, y },
+        (8, _, _, 6) => Instruction::Shr { x, y },
+        (8, _, _, 7) => Instruction::SubN { x, y },
+        (8, _, _, 0xE) => Instruction::Shl { x, y },
+        (9, _, _, 0) => Instruction::SkipNe { x, y },
+        (0xA, _, _, _) => Instruction::LoadIndex(address),
+        (0xB, _, _, _) => Instruction::JumpOffset { x, address },
+        (0xC, _, _, _) => Instruction::Random { x, byte },
+        (0xD, _, _, _) => Instruction::Draw { x, y, rows: n },
+        (0xE, _, 9, 0xE) => Instruction::SkipKey(x),
+        (0xE, _, 0xA, 1) => Instruction::SkipNotKey(x),
+        (0xF, 0, 0, 0) => Instruction::LoadLongIndex,
+        (0xF, _, 0, 1) => Instruction::Plane(x),
+        (0xF, 0, 0, 2) => Instruction::LoadPattern,
+        (0xF, _, 3, 0xA) => Instruction::SetPitch(x),
+        (0xF, _, 0, 7) => Instruction::LoadDelay(x),
+        (0xF, _, 0, 0xA) => Instruction::WaitKey(x),
+        (0xF, _, 1, 5) => Instruction::SetDelay(x),
+        (0xF, _, 1, 8) => Instruction::SetSound(x),
+        (0xF, _, 1, 0xE) => Instruction::AddIndex(x),
+        (0xF, _, 2, 9) => Instruction::LoadFont(x),
+        (0xF, _, 3, 0) => Instruction::LoadBigFont(x),
+        (0xF, _, 3, 3) => Instruction::Bcd(x),
+        (0xF, _, 5, 5) => Instruction::Store(x),
+        (0xF, _, 6, 5) => Instruction::Restore(x),
+        (0xF, _, 7, 5) => Instruction::StoreFlags(x),
+        (0xF, _, 8, 5) => Instruction::RestoreFlags(x),
+        _ => Instruction::Unknown(opcode),
+    }
+}
+
+// MegaChip's 0010, 0011, 00BN and 01NN-09NN are machine code routines everywhere else, so only
+// MegaChip ROMs are decoded with this
+pub fn decode_megachip(opcode: u16) -> Instruction {
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let byte = (opcode & 0x00FF) as u8;
+
+    match (opcode >> 12, x, y, n) {
+        (0, 0, 1, 0) => Instruction::MegaOff,
+        (0, 0, 1, 1) => Instruction::MegaOn,
+        (0, 0, 0xB, _) => Instruction::ScrollUp(n),
+        (0, 1, _, _) => Instruction::LoadHighIndex(byte),
+        (0, 2, _, _) => Instruction::LoadPalette(byte),
+        (0, 3, _, _) => Instruction::SpriteWidth(byte),
+        (0, 4, _, _) => Instruction::SpriteHeight(byte),
+        (0, 5, _, _) => Instruction::ScreenAlpha(byte),
+        (0, 6, 0, _) => Instruction::PlaySample(n),
+        (0, 7, 0, 0) => Instruction::StopSample,
+        (0, 8, 0, _) => Instruction::BlendMode(n),
+        (0, 9, _, _) => Instruction::CollisionIndex(byte),
+        _ => decode(opcode),
+    }
+}
+
+// CHIP-8X gives 02A0 and BNNN other meanings, so its ROMs are decoded with this instead
+pub fn decode_chip8x(opcode: u16) -> Instruction {
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+
+    match (opcode >> 12, x, y, n) {
+        (0, 2, 0xA, 0) => Instruction::CycleBackground,
+        (5, _, _, 1) => Instruction::AddNibbles { x, y },
+        (0xB, _, _, 0) => Instruction::ColorBlocks { x, y },
+        (0xB, _, _, _) => Instruction::ColorZones { x, y, rows: n },
+        (0xE, _, 0xF, 2) => Instruction::SkipKey2(x),
+        (0xE, _, 0xF, 5) => Instruction::SkipNotKey2(x),
+        _ => decode(opcode),
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Sys(address) => write!(f, "SYS {:#05x}", address),
+            Instruction::Cls => write!(f, "CLS"),
+            Instruction::Ret => write!(f, "RET"),
+            Instruction::ScrollDown(n) => write!(f, "SCD {}", n),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::Low => write!(f, "LOW"),
+            Instruction::High => write!(f, "HIGH"),
+            Instruction::MegaOff => write!(f, "MEGAOFF"),
+            Instruction::MegaOn => write!(f, "MEGAON"),
+            Instruction::LoadHighIndex(high) => write!(f, "LDHI I, {:#04x}xxxx", high),
+            Instruction::LoadPalette(count) => write!(f, "LDPAL {}", count),
+            Instruction::SpriteWidth(width) => write!(f, "SPRW {}", width),
+            Instruction::SpriteHeight(height) => write!(f, "SPRH {}", height),
+            Instruction::ScreenAlpha(alpha) => write!(f, "ALPHA {}", alpha),
+            Instruction::PlaySample(n) => write!(f, "DIGISND {}", n),
+            Instruction::StopSample => write!(f, "STOPSND"),
+            Instruction::BlendMode(mode) => write!(f, "BMODE {}", mode),
+            Instruction::CollisionIndex(index) => write!(f, "CCOL {}", index),
+            Instruction::ScrollUp(n) => write!(f, "SCU {}", n),
+            Instruction::CycleBackground => write!(f, "BGNEXT"),
+            Instruction::AddNibbles { x, y } => write!(f, "ADDN V{:X}, V{:X}", x, y),
+            Instruction::ColorBlocks { x, y } => write!(f, "COL V{:X}, V{:X}", x, y),
+            Instruction::ColorZones { x, y, rows } => {
+                write!(f, "COL V{:X}, V{:X}, {}", x, y, rows)
+            }
+            Instruction::SkipKey2(x) => write!(f, "SKP2 V{:X}", x),
+            Instruction::SkipNotKey2(x) => write!(f, "SKNP2 V{:X}", x),
+            Instruction::Jump(address) => write!(f, "JP {:#05x}", address),
+            Instruction::Call(address) => write!(f, "CALL {:#05x}", address),
+            Instruction::SkipEqByte { x, byte } => write!(f, "SE V{:X}, {:#04x}", x, byte),
+            Instruction::SkipNeByte { x, byte } => write!(f, "SNE V{:X}, {:#04x}", x, byte),
+            Instruction::SkipEq { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::LoadByte { x, byte } => write!(f, "LD V{:X}, {:#04x}", x, byte),
+            Instruction::AddByte { x, byte } => write!(f, "ADD V{:X}, {:#04x}", x, byte),
+            Instruction::Load { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::Add { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::Sub { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::Shr { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SubN { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::Shl { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SkipNe { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LoadIndex(address) => write!(f, "LD I, {:#05x}", address),
+            Instruction::JumpOffset { address, .. } => write!(f, "JP V0, {:#05x}", address),
+            Instruction::Random { x, byte } => write!(f, "RND V{:X}, {:#04x}", x, byte),
+            Instruction::Draw { x, y, rows } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, rows),
+            Instruction::SkipKey(x) => write!(f, "SKP V{:X}", x),
+            Instruction::SkipNotKey(x) => write!(f, "SKNP V{:X}", x),
+            Instruction::LoadDelay(x) => write!(f, "LD V{:X}, DT", x),
+            Instruction::WaitKey(x) => write!(f, "LD V{:X}, K", x),
+            Instruction::SetDelay(x) => write!(f, "LD DT, V{:X}", x),
+            Instruction::SetSound(x) => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddIndex(x) => write!(f, "ADD I, V{:X}", x),
+            Instruction::LoadFont(x) => write!(f, "LD F, V{:X}", x),
+            Instruction::LoadBigFont(x) => write!(f, "LD HF, V{:X}", x),
+            Instruction::Bcd(x) => write!(f, "LD B, V{:X}", x),
+            Instruction::Store(x) => write!(f, "LD [I], V{:X}", x),
+            Instruction::Restore(x) => write!(f, "LD V{:X}, [I]", x),
+            Instruction::StoreFlags(x) => write!(f, "LD R, V{:X}", x),
+            Instruction::RestoreFlags(x) => write!(f, "LD V{:X}, R", x),
+            Instruction::Plane(planes) => write!(f, "PLANE {}", planes),
+            Instruction::LoadLongIndex => write!(f, "LD I, LONG"),
+            Instruction::LoadPattern => write!(f, "LD AUDIO, [I]"),
+            Instruction::SetPitch(x) => write!(f, "LD PITCH, V{:X}", x),
+            Instruction::StoreRange { x, y } => write!(f, "LD [I], V{:X}-V{:X}", x, y),
+            Instruction::RestoreRange { x, y } => write!(f, "LD V{:X}-V{:X}, [I]", x, y),
+            Instruction::Unknown(opcode) => write!(f, "DB {:#06x}", opcode),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_splits_opcode_fields() {
+        assert_eq!(
+            decode(0xD12F),
+            Instruction::Draw {
+                x: 1,
+                y: 2,
+                rows: 0xF
+            }
+        );
+        assert_eq!(decode(0x5121), Instruction::Unknown(0x5121));
+        assert_eq!(decode(0x0000), Instruction::Sys(0));
+        assert_eq!(decode(0xFA33).to_string(), "LD B, VA");
+    }
+
+    #[test]
+    fn every_instruction_decodes_and_displays() {
+        use Instruction::*;
+
+        let chip8 = [
+            (0x0000, Sys(0), "SYS 0x000"),
+            (0x00E0, Cls, "CLS"),
+            (0x00EE, Ret, "RET"),
+            (0x00C3, ScrollDown(3), "SCD 3"),
+            (0x00FB, ScrollRight, "SCR"),
+            (0x00FC, ScrollLeft, "SCL"),
+            (0x00FD, Exit, "EXIT"),
+            (0x00FE, Low, "LOW"),
+            (0x00FF, High, "HIGH"),
+            (0x1234, Jump(0x234), "JP 0x234"),
+            (0x2ABC, Call(0xABC), "CALL 0xabc"),
+            (0x312A, SkipEqByte { x: 1, byte: 0x2A }, "SE V1, 0x2a"),
+            (0x4F00, SkipNeByte { x: 0xF, byte: 0 }, "SNE VF, 0x00"),
+            (0x5120, SkipEq { x: 1, y: 2 }, "SE V1, V2"),
+            (0x6A42, LoadByte { x: 0xA, byte: 0x42 }, "LD VA, 0x42"),
+            (0x7301, AddByte { x: 3, byte: 1 }, "ADD V3, 0x01"),
+            (0x8120, Load { x: 1, y: 2 }, "LD V1, V2"),
+            (0x8121, Or { x: 1, y: 2 }, "OR V1, V2"),
+            (0x8122, And { x: 1, y: 2 }, "AND V1, V2"),
+            (0x8123, Xor { x: 1, y: 2 }, "XOR V1, V2"),
+            (0x8124, Add { x: 1, y: 2 }, "ADD V1, V2"),
+            (0x8125, Sub { x: 1, y: 2 }, "SUB V1, V2"),
+            (0x8126, Shr { x: 1, y: 2 }, "SHR V1, V2"),
+            (0x8127, SubN { x: 1, y: 2 }, "SUBN V1, V2"),
+            (0x812E, Shl { x: 1, y: 2 }, "SHL V1, V2"),
+            (0x9120, SkipNe { x: 1, y: 2 }, "SNE V1, V2"),
+            (0xA300, LoadIndex(0x300), "LD I, 0x300"),
+            (
+                0xB234,
+                JumpOffset {
+                    x: 2,
+                    address: 0x234,
+                },
+                "JP V0, 0x234",
+            ),
+            (0xC0FF, Random { x: 0, byte: 0xFF }, "RND V0, 0xff"),
+            (
+                0xD125,
+                Draw {
+                    x: 1,
+                    y: 2,
+                    rows: 5,
+                },
+                "DRW V1, V2, 5",
+            ),
+            (
+                0xD120,
+                Draw {
+                    x: 1,
+                    y: 2,
+                    rows: 0,
+                },
+                "DRW V1, V2, 0",
+            ),
+            (0xE59E, SkipKey(5), "SKP V5"),
+            (0xE5A1, SkipNotKey(5), "SKNP V5"),
+            (0xF501, Plane(5), "PLANE 5"),
+            (0xF000, LoadLongIndex, "LD I, LONG"),
+            (0xF002, LoadPattern, "LD AUDIO, [I]"),
+            (0xF53A, SetPitch(5), "LD PITCH, V5"),
+            (0x5122, StoreRange { x: 1, y: 2 }, "LD [I], V1-V2"),
+            (0x5213, RestoreRange { x: 2, y: 1 }, "LD V2-V1, [I]"),
+            (0x00D3, ScrollUp(3), "SCU 3"),
+            (0xF507, LoadDelay(5), "LD V5, DT"),
+            (0xF50A, WaitKey(5), "LD V5, K"),
+            (0xF515, SetDelay(5), "LD DT, V5"),
+            (0xF518, SetSound(5), "LD ST, V5"),
+            (0xF51E, AddIndex(5), "ADD I, V5"),
+            (0xF529, LoadFont(5), "LD F, V5"),
+            (0xF530, LoadBigFont(5), "LD HF, V5"),
+            (0xF533, Bcd(5), "LD B, V5"),
+            (0xF555, Store(5), "LD [I], V5"),
+            (0xF565, Restore(5), "LD V5, [I]"),
+            (0xF575, StoreFlags(5), "LD R, V5"),
+            (0xF585, RestoreFlags(5), "LD V5, R"),
+            (0x5121, Unknown(0x5121), "DB 0x5121"),
+            (0xFFFF, Unknown(0xFFFF), "DB 0xffff"),
+        ];
+        for (opcode, instruction, text) in chip8 {
+            assert_eq!(decode(opcode), instruction, "{:#06x}", opcode);
+            assert_eq!(instruction.to_string(), text);
+        }
+
+        let megachip = [
+            (0x0010, MegaOff, "MEGAOFF"),
+            (0x0011, MegaOn, "MEGAON"),
+            (0x0112, LoadHighIndex(0x12), "LDHI I, 0x12xxxx"),
+            (0x0203, LoadPalette(3), "LDPAL 3"),
+            (0x0310, SpriteWidth(16), "SPRW 16"),
+            (0x0408, SpriteHeight(8), "SPRH 8"),
+            (0x0580, ScreenAlpha(128), "ALPHA 128"),
+            (0x0602, PlaySample(2), "DIGISND 2"),
+            (0x0700, StopSample, "STOPSND"),
+            (0x0801, BlendMode(1), "BMODE 1"),
+            (0x0905, CollisionIndex(5), "CCOL 5"),
+            (0x00B4, ScrollUp(4), "SCU 4"),
+            // Everything else decodes the same
+            (0x0000, Sys(0), "SYS 0x000"),
+            (0x00E0, Cls, "CLS"),
+        ];
+        for (opcode, instruction, text) in megachip {
+            assert_eq!(decode_megachip(opcode), instruction, "{:#06x}", opcode);
+            assert_eq!(instruction.to_string(), text);
+        }
+        // Anywhere else they are machine code routines
+        assert_eq!(decode(0x0011), Sys(0x011));
+        assert_eq!(decode(0x0112), Sys(0x112));
+
+        let chip8x = [
+            (0x02A0, CycleBackground, "BGNEXT"),
+            (0x5121, AddNibbles { x: 1, y: 2 }, "ADDN V1, V2"),
+            (0xB120, ColorBlocks { x: 1, y: 2 }, "COL V1, V2"),
+            (
+                0xB123,
+                ColorZones {
+                    x: 1,
+                    y: 2,
+                    rows: 3,
+                },
+                "COL V1, V2, 3",
+            ),
+            (0xE5F2, SkipKey2(5), "SKP2 V5"),
+            (0xE5F5, SkipNotKey2(5), "SKNP2 V5"),
+            // Everything else decodes the same
+            (0x00E0, Cls, "CLS"),
+            (
+                0xD125,
+                Draw {
+                    x: 1,
+                    y: 2,
+                    rows: 5,
+                },
+                "DRW V1, V2, 5",
+            ),
+        ];
+        for (opcode, instruction, text) in chip8x {
+            assert_eq!(decode_chip8x(opcode), instruction, "{:#06x}", opcode);
+            assert_eq!(instruction.to_string(), text);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+// One line per executed instruction for diffing runs against other emulators:
+//
+//   0x21a  8124  ADD V1, V2  V1 05->0c VF 01->00
+//
+// After the address, opcode and mnemonic come the registers the instruction changed with their
+// old and new values. V0-VF, I and SP are compared, anything unchanged is left out.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::Chip8;
+use crate::instruction::Instruction;
+
+// What an instruction can change that the trace reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TracedRegisters {
+    registers: [u8; 16],
+    index: u32,
+    sp: u8,
+}
+
+impl TracedRegisters {
+    pub fn of(emu: &Chip8) -> Self {
+        Self {
+            registers: emu.registers,
+            index: emu.index,
+            sp: emu.sp,
+        }
+    }
+}
+
+pub struct InstructionTrace {
+    path: PathBuf,
+    out: BufWriter<File>,
+}
+
+impl InstructionTrace {
+    pub fn create(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let out = BufWriter::new(File::create(&path)?);
+        Ok(Self { path, out })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(
+        &mut self,
+        pc: u16,
+        opcode: u16,
+        instruction: Instruction,
+        before: &TracedRegisters,
+        after: &TracedRegisters,
+    ) -> io::Result<()> {
+        let mnemonic = instruction.to_string();
+        write!(self.out, "{:#05x}  {:04x}  {:<16}", pc, opcode, mnemonic)?;
+        for (register, (old, new)) in before.registers.iter().zip(&after.registers).enumerate() {
+            if old != new {
+                write!(self.out, " V{:X} {:02x}->{:02x}", register, old, new)?;
+            }
+        }
+        if before.index != after.index {
+            write!(self.out, " I {:03x}->{:03x}", before.index, after.index)?;
+        }
+        if before.sp != after.sp {
+            write!(self.out, " SP {:x}->{:x}", before.sp, after.sp)?;
+        }
+        writeln!(self.out)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,171 @@
+// Which keyboard key drives each keypad key, loaded from a TOML file like
+//
+//     [keypad]
+//     1 = "1"
+//     4 = "A"
+//     C = "4"
+//     5 = ["W", "Up"]
+//
+// Keypad keys are hex digits, keyboard keys use SDL key names, a list binds several keyboard
+// keys to one keypad key so two players can share the keypad. Unlisted keys keep their default
+// binding. A [keypad2] table binds the second player's keys the same way, they go to CHIP-8X's
+// second keypad or, for every other ROM, press the same keys as the first player's.
+//
+// Keys are matched by their place on the keyboard, named after what they type on a US layout,
+// so the default block is in the same spot on AZERTY or Dvorak. `symbolic_keys = true` matches
+// them by what they type in the current layout instead.
+//
+// Keys in a [turbo] table fire their keypad key over and over while held, `turbo_rate` times
+// a second:
+//
+//     turbo_rate = 15
+//
+//     [turbo]
+//     5 = "Left Shift"
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::keypad::KEY_COUNT;
+use crate::turbo::DEFAULT_TURBO_RATE;
+
+// The classic 1234/QWER/ASDF/ZXCV block on a QWERTY keyboard, indexed by keypad key
+const DEFAULT_KEYS: [&str; KEY_COUNT] = [
+    "X", "1", "2", "3", "Q", "W", "E", "A", "S", "D", "Z", "C", "4", "R", "F", "V",
+];
+
+// The numpad, digits on their own keys. The numpad's + and - change the speed, so E and F
+// sit on the Insert and Delete keys above the arrows.
+const DEFAULT_SECOND_KEYS: [&str; KEY_COUNT] = [
+    "Keypad 0",
+    "Keypad 1",
+    "Keypad 2",
+    "Keypad 3",
+    "Keypad 4",
+    "Keypad 5",
+    "Keypad 6",
+    "Keypad 7",
+    "Keypad 8",
+    "Keypad 9",
+    "Keypad .",
+    "Keypad Enter",
+    "Keypad /",
+    "Keypad *",
+    "Insert",
+    "Delete",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    // The keyboard keys bound to each keypad key
+    pub keys: [Vec<String>; KEY_COUNT],
+    pub second_keys: [Vec<String>; KEY_COUNT],
+    // Auto-fire keys, none by default
+    pub turbo: [Vec<String>; KEY_COUNT],
+    // Presses a second
+    pub turbo_rate: u32,
+    // Match keys by what they type instead of where they are
+    pub symbolic_keys: bool,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self {
+            keys: DEFAULT_KEYS.map(|name| vec![name.to_string()]),
+            second_keys: DEFAULT_SECOND_KEYS.map(|name| vec![name.to_string()]),
+            turbo: Default::default(),
+            turbo_rate: DEFAULT_TURBO_RATE,
+            symbolic_keys: false,
+        }
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+// Replaces the bindings the `name` table lists, if there is one
+fn bind(table: &toml::Table, name: &str, keys: &mut [Vec<String>; KEY_COUNT]) -> io::Result<()> {
+    let Some(keypad) = table.get(name) else {
+        return Ok(());
+    };
+    let keypad = keypad
+        .as_table()
+        .ok_or_else(|| invalid(format!("'{}' must be a table", name)))?;
+
+    for (key, key_name) in keypad {
+        let index = usize::from_str_radix(key, 16)
+            .ok()
+            .filter(|&index| index < KEY_COUNT)
+            .ok_or_else(|| invalid(format!("'{}' is not a keypad key (0-F)", key)))?;
+        let names = match key_name {
+            toml::Value::String(name) => vec![name.clone()],
+            toml::Value::Array(names) => names
+                .iter()
+                .map(|name| name.as_str().map(str::to_string))
+                .collect::<Option<_>>()
+                .ok_or_else(|| invalid(format!("key {} must list key names", key)))?,
+            _ => return Err(invalid(format!("key {} must be a key name", key))),
+        };
+        keys[index] = names;
+    }
+    Ok(())
+}
+
+// Every (keypad key, keyboard key name) pair of one of the keypads
+pub fn bindings(keys: &[Vec<String>; KEY_COUNT]) -> impl Iterator<Item = (usize, &str)> {
+    keys.iter()
+        .enumerate()
+        .flat_map(|(key, names)| names.iter().map(move |name| (key, name.as_str())))
+}
+
+impl KeyMap {
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let table: toml::Table = text.parse().map_err(|e| invalid(format!("{}", e)))?;
+        let mut keymap = Self::default();
+        bind(&table, "keypad", &mut keymap.keys)?;
+        bind(&table, "keypad2", &mut keymap.second_keys)?;
+        bind(&table, "turbo", &mut keymap.turbo)?;
+        if let Some(rate) = table.get("turbo_rate") {
+            keymap.turbo_rate = rate
+                .as_integer()
+                .and_then(|rate| u32::try_from(rate).ok())
+                .filter(|&rate| rate > 0)
+                .ok_or_else(|| invalid("'turbo_rate' must be a number of presses a second"))?;
+        }
+        if let Some(symbolic) = table.get("symbolic_keys") {
+            keymap.symbolic_keys = symbolic
+                .as_bool()
+                .ok_or_else(|| invalid("'symbolic_keys' must be true or false"))?;
+        }
+        Ok(keymap)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+}
+
+// `$XDG_CONFIG_HOME/chip8-emu/keymap.toml`, falling back to `~/.config`
+pub fn default_path() -> PathBuf {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default();
+
+    config_home.join("chip8-emu").join("keymap.toml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_bind_several_names_and_the_second_player_uses_the_numeric_keypad() {
+        let keymap = KeyMap::parse("[keypad]\n5 = [\"W\", \"Up\"]\n").unwrap();
+        assert_eq!(keymap.keys[5], ["W", "Up"]);
+        assert_eq!(keymap.second_keys[5], ["Keypad 5"]);
+    }
+}
@@ -0,0 +1,39 @@
+use std::fmt;
+use std::sync::mpsc::{Receiver, Sender, channel};
+
+pub const KEY_COUNT: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidKey(pub u8);
+
+impl fmt::Display for InvalidKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid keypad key {:#x}, expected 0x0-0xF", self.0)
+    }
+}
+
+impl std::error::Error for InvalidKey {}
+
+// Cloneable handle that lets other threads (automation, network input) queue key events.
+// The events are applied by the emulator thread through `Chip8::process_injected_keys`.
+#[derive(Debug, Clone)]
+pub struct KeyInjector {
+    sender: Sender<(u8, bool)>,
+}
+
+impl KeyInjector {
+    pub fn send(&self, key: u8, pressed: bool) -> Result<(), InvalidKey> {
+        if key as usize >= KEY_COUNT {
+            return Err(InvalidKey(key));
+        }
+
+        // The receiver lives as long as the emulator, a dropped emulator just discards the event
+        let _ = self.sender.send((key, pressed));
+        Ok(())
+    }
+}
+
+pub(crate) fn key_queue() -> (KeyInjector, Receiver<(u8, bool)>) {
+    let (sender, receiver) = channel();
+    (KeyInjector { sender }, receiver)
+}
@@ -0,0 +1,184 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::keypad::KEY_COUNT;
+use crate::movie::Movie;
+
+pub const DEFAULT_SECONDS_PER_ROM: u64 = 30;
+pub const DEFAULT_IDLE_TIMEOUT: u64 = 60;
+
+// How long a demo key stays held before a new one is picked
+const DEMO_PRESS_FRAMES: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DemoInput {
+    None,
+    Random,
+    // Replays the movie recorded next to each ROM, see `movie_path`. ROMs without one get
+    // random input.
+    Movie,
+}
+
+impl DemoInput {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "random" => Some(Self::Random),
+            "movie" => Some(Self::Movie),
+            _ => None,
+        }
+    }
+}
+
+// Where `DemoInput::Movie` looks for a ROM's movie, pong.ch8 plays pong.movie
+pub fn movie_path(rom: &str) -> PathBuf {
+    Path::new(rom).with_extension("movie")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KioskAction {
+    Continue,
+    NextRom,
+}
+
+// Attract mode: every ROM runs for a while with optional demo input, then the next one starts.
+// As soon as somebody touches the keypad the cycle stops until they walk away again.
+#[derive(Debug, Clone)]
+pub struct Kiosk {
+    pub rom_duration: Duration,
+    pub idle_timeout: Duration,
+    pub demo_input: DemoInput,
+    attract: bool,
+    rom_started: Instant,
+    last_activity: Instant,
+    demo_key: Option<u8>,
+    demo_frames: u32,
+    // The running ROM's movie and the next frame of it to play
+    demo_movie: Option<(Movie, u64)>,
+}
+
+impl Kiosk {
+    pub fn new(seconds_per_rom: u64, idle_timeout: u64, demo_input: DemoInput) -> Self {
+        let now = Instant::now();
+
+        Self {
+            rom_duration: Duration::from_secs(seconds_per_rom),
+            idle_timeout: Duration::from_secs(idle_timeout),
+            demo_input,
+            attract: true,
+            rom_started: now,
+            last_activity: now,
+            demo_key: None,
+            demo_frames: 0,
+            demo_movie: None,
+        }
+    }
+
+    pub fn is_attracting(&self) -> bool {
+        self.attract
+    }
+
+    pub fn user_activity(&mut self) {
+        self.attract = false;
+        self.last_activity = Instant::now();
+    }
+
+    pub fn rom_switched(&mut self) {
+        self.rom_started = Instant::now();
+        self.demo_key = None;
+        self.demo_frames = 0;
+        self.demo_movie = None;
+    }
+
+    // Picks up the movie recorded for `rom` when replaying movies, call after `rom_switched`.
+    // Returns the movie's RND seed for the emulator, the replay only matches with it.
+    pub fn load_movie(&mut self, rom: &str) -> io::Result<Option<u64>> {
+        if self.demo_input != DemoInput::Movie {
+            return Ok(None);
+        }
+        match Movie::read(movie_path(rom)) {
+            Ok(movie) => {
+                let seed = movie.seed;
+                self.demo_movie = Some((movie, 0));
+                Ok(Some(seed))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Called once per frame, drives the demo input while attracting
+    pub fn update(&mut self, keypad: &mut [bool; KEY_COUNT]) -> KioskAction {
+        let now = Instant::now();
+
+        if !self.attract {
+            if now - self.last_activity < self.idle_timeout {
+                return KioskAction::Continue;
+            }
+            self.attract = true;
+            return KioskAction::NextRom;
+        }
+
+        if now - self.rom_started >= self.rom_duration {
+            return KioskAction::NextRom;
+        }
+
+        if let Some((movie, frame)) = &mut self.demo_movie {
+            // Once the movie is over the ROM runs on by itself until its time is up
+            *keypad = movie.keys(*frame).unwrap_or_default();
+            *frame += 1;
+        } else if self.demo_input != DemoInput::None {
+            if self.demo_frames == 0 {
+                if let Some(key) = self.demo_key.take() {
+                    keypad[key as usize] = false;
+                }
+                let key = rand::rng().random_range(0..KEY_COUNT as u8);
+                keypad[key as usize] = true;
+                self.demo_key = Some(key);
+                self.demo_frames = DEMO_PRESS_FRAMES;
+            }
+            self.demo_frames -= 1;
+        }
+
+        KioskAction::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn movie_demo_replays_the_movie_next_to_the_rom() {
+        let dir = std::env::temp_dir().join(format!("chip8-kiosk-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let rom = dir.join("pong.ch8").to_string_lossy().into_owned();
+        let mut movie = Movie::new(7);
+        let mut keys = [false; KEY_COUNT];
+        keys[5] = true;
+        movie.record(&keys);
+        movie.write(movie_path(&rom)).unwrap();
+
+        let mut kiosk = Kiosk::new(
+            DEFAULT_SECONDS_PER_ROM,
+            DEFAULT_IDLE_TIMEOUT,
+            DemoInput::Movie,
+        );
+        assert_eq!(kiosk.load_movie(&rom).unwrap(), Some(7));
+        let mut keypad = [false; KEY_COUNT];
+        kiosk.update(&mut keypad);
+        assert_eq!(keypad, keys);
+        kiosk.update(&mut keypad);
+        assert_eq!(keypad, [false; KEY_COUNT]);
+
+        // Nothing recorded for this one, it gets random input
+        let other = dir.join("tetris.ch8").to_string_lossy().into_owned();
+        kiosk.rom_switched();
+        assert_eq!(kiosk.load_movie(&other).unwrap(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,25 +1,128 @@
-use drivers::display_driver::WINDOW_HEIGHT;
-use drivers::display_driver::WINDOW_WIDTH;
-use drivers::display_driver::draw_screen;
-use rand::Rng;
-use sdl3::event::Event;
-use sdl3::keyboard::Keycode;
+use drivers::shm_driver::SharedFramebuffer;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::fs;
 use std::io;
-use std::time::Duration;
-
-use sdl3;
-
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Receiver;
+
+pub mod audio;
+pub mod batch;
+pub mod bench;
+pub mod boot_menu;
+pub mod cfg;
+pub mod cheats;
+pub mod chip8x;
+pub mod chrome_trace;
+pub mod compare;
+pub mod crash_dump;
+pub mod debugger;
+pub mod disassembler;
+pub mod display;
 pub mod drivers;
-
-use drivers::input_driver::process_input;
-
-const START_ADDRESS: usize = 0x200;
+pub mod error;
+pub mod event_log;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(feature = "sdl")]
+pub mod frontend;
+pub mod geometry;
+pub mod gif_recorder;
+pub mod instruction;
+pub mod instruction_trace;
+pub mod keymap;
+pub mod keypad;
+pub mod kiosk;
+#[cfg(feature = "libretro")]
+mod libretro;
+pub mod megachip;
+pub mod memory_fault;
+pub mod metrics;
+pub mod movie;
+pub mod octocart;
+pub mod osd;
+pub mod pacing;
+pub mod palette;
+pub mod persistent_ram;
+pub mod phosphor;
+pub mod playlist;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod quirks;
+pub mod recent;
+pub mod rewind;
+pub mod rom;
+pub mod rom_database;
+pub mod rom_quirks;
+pub mod rom_watch;
+pub mod run_loop;
+pub mod settings;
+pub mod slow_draw;
+pub mod soak;
+pub mod splash;
+pub mod state;
+pub mod state_diff;
+pub mod test_suite;
+pub mod timing;
+#[cfg(feature = "tui")]
+pub mod tui_debugger;
+pub mod turbo;
+pub mod unknown_opcode;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod watchdog;
+
+use audio::{Buzzer, DEFAULT_PITCH, FrameSound, PATTERN_SIZE};
+
+use cheats::Cheats;
+use chip8x::ColorGrid;
+use chrome_trace::TraceRecorder;
+use debugger::{DebugControl, WatchHit};
+use display::Display;
+use error::Chip8Error;
+use event_log::EventLog;
+use geometry::Geometry;
+use instruction::{Instruction, decode, decode_chip8x, decode_megachip};
+use instruction_trace::{InstructionTrace, TracedRegisters};
+use keypad::{InvalidKey, KEY_COUNT, KeyInjector};
+use megachip::{BlendMode, MEGA_HEIGHT, MEGA_MEMORY_SIZE, MEGA_WIDTH, MegaChip};
+use memory_fault::OnMemoryFault;
+use metrics::Metrics;
+use movie::{MovieNotice, MovieSession};
+use pacing::Pacer;
+use palette::Rgb;
+use persistent_ram::PersistentRam;
+use playlist::Playlist;
+use quirks::Quirks;
+use rewind::Rewind;
+use rom::{DecodedRom, LoadReport, RomFormat};
+use rom_database::{RomInfo, UseDatabase};
+use rom_quirks::RomQuirks;
+use slow_draw::{SlowDraw, SpriteDraw};
+use state::SaveState;
+use timing::CycleTable;
+use unknown_opcode::{OnUnknownOpcode, UnknownOpcode};
+use watchdog::Watchdog;
+
+pub const START_ADDRESS: usize = 0x200;
+pub const MEMORY_SIZE: usize = 4096;
 const FONTSET_SIZE: usize = 80;
 const FONTSET_START_ADDRESS: usize = 0x50;
+const BIG_FONTSET_SIZE: usize = 160;
+const BIG_FONTSET_START_ADDRESS: usize = FONTSET_START_ADDRESS + FONTSET_SIZE;
+const RPL_FLAGS: usize = 8;
+const HISTORY_SIZE: usize = 32;
+pub const DEFAULT_STACK_DEPTH: usize = 16;
 
 pub const CHIP8_WIDTH: usize = 64;
 pub const CHIP8_HEIGHT: usize = 32;
+// SUPER-CHIP high resolution mode
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
 
 const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
@@ -40,148 +143,827 @@ const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+// SUPER-CHIP 8x10 digits for FX30, A-F follow Octo
+const BIG_FONTSET: [u8; BIG_FONTSET_SIZE] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
 pub struct Chip8 {
     pub registers: [u8; 16],
-    pub memory: [u8; 4096],
-    pub index: u16,
+    // MEMORY_SIZE bytes, MegaChip ROMs bigger than that grow it to fit
+    pub memory: Vec<u8>,
+    // 24 bits wide for MegaChip's LDHI, everything else only loads 12
+    pub index: u32,
     pub pc: u16,
-    pub stack: [u16; 16],
+    pub stack: Vec<u16>,
     pub sp: u8,
     pub dtimer: u8,
     pub stimer: u8,
-    pub keypad: [bool; 16],
-    pub video: [bool; 64 * 32],
+    pub keypad: [bool; KEY_COUNT],
+    // CHIP-8X's second keypad, for the other player
+    pub keypad2: [bool; KEY_COUNT],
+    // Always the size of the current mode's screen
+    pub video: Display,
+    pub hires: bool,
+    // The XO-CHIP planes DRW, CLS and scrolling work on, one bit each, FN01 picks them
+    pub planes: u8,
+    // The low resolution screen size, taller for ETI-660 ROMs, see `set_geometry`
+    geometry: Geometry,
+    // Where ROMs are loaded and run from, 0x600 on the ETI-660
+    pub start_address: usize,
+    // The 256 colour screen and its settings, shown instead of `video` while enabled
+    pub megachip: MegaChip,
+    // CHIP-8X's colour board, only used while `chip8x` is on
+    pub color_grid: ColorGrid,
+    // SUPER-CHIP user flags for FX75/FX85, kept across resets like on the HP48
+    pub rpl: [u8; RPL_FLAGS],
     pub opcode: u16,
+    pub buzzer: Buzzer,
+    // The XO-CHIP sound pattern F002 loaded, the plain beep plays until there is one
+    pub audio_pattern: Option<[u8; PATTERN_SIZE]>,
+    pub pitch: u8,
+    pub history: VecDeque<(u16, u16)>,
+    pub rom_hash: Option<String>,
+    // The file the running ROM came from, None for ROMs loaded from memory
+    pub rom_path: Option<String>,
+    pub watchdog: Option<Watchdog>,
+    pub cycle_table: CycleTable,
+    pub quirks: Quirks,
+    // Run CHIP-8X ROMs, whose colour and second keypad instructions take the place of BNNN and
+    // MegaChip's LDPAL
+    pub chip8x: bool,
+    // Quirks picked per ROM hash, applied when a matching ROM loads
+    pub rom_quirks: RomQuirks,
+    // Which settings the built-in ROM database may pick for ROMs it knows
+    pub rom_database: UseDatabase,
+    pub debug: DebugControl,
+    // Cheat searches and the addresses frozen to a value, kept across resets
+    pub cheats: Cheats,
+    pub on_unknown_opcode: OnUnknownOpcode,
+    pub on_memory_fault: OnMemoryFault,
+    // CALLs past the end of the stack make it deeper instead of halting, up to 255 levels
+    pub grow_stack: bool,
+    // Why execution stopped, a stack overflow or underflow, a memory fault or
+    // `OnUnknownOpcode::Halt`.
+    // Cleared by `reset`.
+    pub halted: Option<Chip8Error>,
+    // Halt on writes below `start_address` instead of letting a ROM clobber the fonts
+    pub protect_interpreter_area: bool,
+    cycle_credit: i64,
+    // Key FX0A saw pressed and is waiting to be released, see `Quirks::wait_key_release`
+    key_wait: Option<u8>,
+    // Counts the instructions that changed the screen, see `video_generation`
+    video_generation: u64,
+    // Source for RND, seeded from the OS unless `with_seed`/`seed_rng` picked a seed
+    rng: StdRng,
+    pub event_log: Option<EventLog>,
+    pub tracer: Option<TraceRecorder>,
+    pub instruction_trace: Option<InstructionTrace>,
+    // Why the instruction trace stopped early, `write_trace` reports it
+    instruction_trace_error: Option<io::Error>,
+    pub metrics: Option<Arc<Metrics>>,
+    pub shared_framebuffer: Option<SharedFramebuffer>,
+    pub slow_draw: Option<SlowDraw>,
+    pub persistent_ram: Option<PersistentRam>,
+    pub rewind: Option<Rewind>,
+    // Recording the keypad to a movie file or feeding one back in place of live input
+    pub movie: Option<MovieSession>,
+    // Overrides guessing the ROM format from the file extension
+    pub rom_format: Option<RomFormat>,
+    // Opcodes `OnUnknownOpcode::Log` skipped, for the frontend to report and clear
+    pub skipped_opcodes: Vec<UnknownOpcode>,
+    // What the running ROM put in memory, `restart` loads it again
+    rom_image: Option<Vec<u8>>,
+    key_injector: KeyInjector,
+    injected_keys: Receiver<(u8, bool)>,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chip8 {
     pub fn new() -> Self {
+        Self::with_stack_depth(DEFAULT_STACK_DEPTH)
+    }
+
+    // Some interpreters only had room for 12 return addresses, some modern ROMs nest deeper than 16
+    pub fn with_stack_depth(depth: usize) -> Self {
+        let (key_injector, injected_keys) = keypad::key_queue();
+
         let mut new_chip8 = Self {
             pc: START_ADDRESS as u16,
-            memory: [0; 4096],
-            video: [false; 64 * 32],
+            memory: vec![0; MEMORY_SIZE],
+            video: Display::new(CHIP8_WIDTH, CHIP8_HEIGHT),
+            hires: false,
+            planes: 1,
+            geometry: Geometry::default(),
+            start_address: START_ADDRESS,
+            megachip: MegaChip::default(),
+            color_grid: ColorGrid::default(),
+            rpl: [0; RPL_FLAGS],
             registers: [0; 16],
             index: 0,
             sp: 0,
-            stack: [0; 16],
-            keypad: [false; 16],
+            stack: vec![0; depth.min(u8::MAX as usize)],
+            keypad: [false; KEY_COUNT],
+            keypad2: [false; KEY_COUNT],
             dtimer: 0,
             stimer: 0,
             opcode: 0,
+            buzzer: Buzzer::default(),
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            history: VecDeque::with_capacity(HISTORY_SIZE),
+            rom_hash: None,
+            rom_path: None,
+            rom_image: None,
+            watchdog: None,
+            cycle_table: CycleTable::default(),
+            quirks: Quirks::default(),
+            chip8x: false,
+            rom_quirks: RomQuirks::default(),
+            rom_database: UseDatabase::default(),
+            debug: DebugControl::default(),
+            cheats: Cheats::default(),
+            on_unknown_opcode: OnUnknownOpcode::default(),
+            halted: None,
+            on_memory_fault: OnMemoryFault::default(),
+            grow_stack: false,
+            protect_interpreter_area: false,
+            cycle_credit: 0,
+            key_wait: None,
+            video_generation: 0,
+            rng: StdRng::from_os_rng(),
+            event_log: None,
+            tracer: None,
+            instruction_trace: None,
+            instruction_trace_error: None,
+            metrics: None,
+            shared_framebuffer: None,
+            slow_draw: None,
+            persistent_ram: None,
+            rewind: Some(Rewind::default()),
+            movie: None,
+            rom_format: None,
+            skipped_opcodes: Vec::new(),
+            key_injector,
+            injected_keys,
         };
 
-        new_chip8.memory[FONTSET_START_ADDRESS..FONTSET_START_ADDRESS + FONTSET_SIZE]
-            .copy_from_slice(&FONTSET);
+        new_chip8.load_fonts();
 
         new_chip8
     }
 
-    pub fn run(&mut self, rom: &str, ticks_per_frame: usize) {
-        let sdl_context = sdl3::init().unwrap();
+    // For ROMs that expect a bigger interpreter below them, like the ETI-660's at 0x600
+    pub fn with_start_address(address: usize) -> Self {
+        let mut emu = Self::new();
+        emu.start_address = address;
+        emu.pc = address as u16;
+        emu
+    }
 
-        let video_subsystem = sdl_context.video().unwrap();
+    // RND produces the same numbers on every run, for replays and tests
+    pub fn with_seed(seed: u64) -> Self {
+        let mut emu = Self::new();
+        emu.seed_rng(seed);
+        emu
+    }
 
-        let window = video_subsystem
-            .window("Chip8 Emulator", WINDOW_WIDTH, WINDOW_HEIGHT)
-            .position_centered()
-            .opengl()
-            .build()
-            .unwrap();
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    fn load_fonts(&mut self) {
+        self.memory[FONTSET_START_ADDRESS..FONTSET_START_ADDRESS + FONTSET_SIZE]
+            .copy_from_slice(&FONTSET);
+        self.memory[BIG_FONTSET_START_ADDRESS..BIG_FONTSET_START_ADDRESS + BIG_FONTSET_SIZE]
+            .copy_from_slice(&BIG_FONTSET);
+    }
+
+    pub fn reset(&mut self) {
+        self.registers = [0; 16];
+        self.memory = vec![0; MEMORY_SIZE];
+        self.load_fonts();
+        self.index = 0;
+        self.pc = self.start_address as u16;
+        self.stack.fill(0);
+        self.sp = 0;
+        self.dtimer = 0;
+        self.stimer = 0;
+        self.keypad = [false; KEY_COUNT];
+        self.keypad2 = [false; KEY_COUNT];
+        self.hires = false;
+        self.planes = 1;
+        self.audio_pattern = None;
+        self.pitch = DEFAULT_PITCH;
+        self.buzzer.clear();
+        self.megachip = MegaChip::default();
+        self.resize_video();
+        self.color_grid = ColorGrid::default();
+        self.opcode = 0;
+        self.history.clear();
+        self.rom_hash = None;
+        self.rom_path = None;
+        self.rom_image = None;
+        self.halted = None;
+        if let Some(rewind) = &mut self.rewind {
+            rewind.clear();
+        }
+        self.cycle_credit = 0;
+        self.key_wait = None;
+        self.video_generation += 1;
+        self.feed_watchdog();
+    }
+
+    // Like the reset button on the console, everything starts over with the same ROM in memory
+    pub fn restart(&mut self) {
+        let (rom_hash, rom_path, rom_image) = (
+            self.rom_hash.take(),
+            self.rom_path.take(),
+            self.rom_image.take(),
+        );
+        self.reset();
 
-        let mut canvas = window.into_canvas();
-        canvas.clear();
-        canvas.present();
+        if let Some(image) = &rom_image {
+            self.place_rom(image);
+        }
+        self.rom_hash = rom_hash;
+        self.rom_path = rom_path;
+        self.rom_image = rom_image;
+    }
 
-        let mut event_pump = sdl_context.event_pump().unwrap();
+    pub fn save_state(&self) -> SaveState {
+        SaveState::capture(self)
+    }
 
-        self.load_rom(rom).unwrap();
+    // Save states can't hold MegaChip's screen or the memory of ROMs that need it
+    pub fn supports_save_states(&self) -> bool {
+        !self.megachip.enabled && self.memory.len() == MEMORY_SIZE
+    }
 
-        let target_frame_duration = Duration::from_secs_f64(1.0 / 60.0);
+    // Restores everything a save state holds, the history and cycle credit start over
+    pub fn load_state(&mut self, state: &SaveState) {
+        self.rom_hash = state.rom_hash.clone();
+        self.registers = state.registers;
+        self.memory.clone_from(&state.memory);
+        self.index = state.index;
+        self.pc = state.pc;
+        self.stack = state.stack.clone();
+        self.sp = state.sp;
+        self.dtimer = state.dtimer;
+        self.stimer = state.stimer;
+        self.keypad = state.keypad;
+        self.hires = state.hires;
+        self.rpl = state.rpl;
+        self.megachip = MegaChip::default();
+        self.video.resize(state.width(), state.height());
+        self.video.pixels_mut().copy_from_slice(&state.video);
+        if !state.plane2.is_empty() {
+            self.video.plane_mut(1).copy_from_slice(&state.plane2);
+        }
+        self.planes = state.planes;
+        self.audio_pattern = state.audio_pattern;
+        self.pitch = state.pitch;
+        self.buzzer.clear();
+        self.history.clear();
+        self.cycle_credit = 0;
+        self.key_wait = None;
+        self.video_generation += 1;
+        self.feed_watchdog();
+    }
 
-        'gameloop: loop {
+    // Runs the loaded ROM without a window at 60 frames per second. `on_frame` gets what the
+    // movie had to say that frame and returns false to stop. A ROM the watchdog finds stuck is
+    // paused and left tripped for `on_frame` to report.
+    pub fn run_headless(
+        &mut self,
+        ticks_per_frame: usize,
+        mut on_frame: impl FnMut(&mut Chip8, Option<MovieNotice>) -> bool,
+    ) -> Result<(), Chip8Error> {
+        let mut pacer = Pacer::default();
+
+        loop {
             let frame_start = std::time::Instant::now();
 
-            for evt in event_pump.poll_iter() {
-                match evt {
-                    Event::Quit { .. }
-                    | Event::KeyDown {
-                        keycode: Some(Keycode::Escape),
-                        ..
-                    } => {
-                        break 'gameloop;
-                    }
-                    Event::KeyDown {
-                        keycode: Some(key), ..
-                    } => {
-                        if let Some(k) = process_input(key) {
-                            self.keypress(k, true);
-                        }
-                    }
-                    Event::KeyUp {
-                        keycode: Some(key), ..
-                    } => {
-                        if let Some(k) = process_input(key) {
-                            self.keypress(k, false);
-                        }
-                    }
-                    _ => (),
-                }
+            self.process_injected_keys();
+            let notice = self.movie_frame();
+            self.cheats.apply(&mut self.memory);
+            let executed = self.run_cycles(ticks_per_frame);
+            if let Some(error) = self.halted.take() {
+                return Err(error);
             }
-
-            for _ in 0..ticks_per_frame {
-                self.tick();
+            if self.watchdog_tripped() {
+                self.debug.pause();
             }
             self.tick_timers();
-            draw_screen(&self, &mut canvas);
 
-            let elapsed = frame_start.elapsed();
-            if elapsed < target_frame_duration {
-                let sleep_time = target_frame_duration - elapsed;
-                std::thread::sleep(sleep_time);
+            if let Some(metrics) = &self.metrics {
+                metrics.frames.fetch_add(1, Ordering::Relaxed);
+                metrics.instructions.fetch_add(executed, Ordering::Relaxed);
+            }
+
+            if !on_frame(self, notice) {
+                return Ok(());
+            }
+
+            if !pacer.wait(frame_start)
+                && let Some(metrics) = &self.metrics
+            {
+                metrics.dropped_frames.fetch_add(1, Ordering::Relaxed);
             }
         }
     }
 
-    pub fn load_rom(&mut self, filename: &str) -> io::Result<()> {
-        let rom_data = fs::read(filename)?;
+    // Writes the trace-event profile and flushes the instruction trace, or says why the
+    // instruction trace stopped early
+    pub fn write_trace(&mut self) -> io::Result<()> {
+        if let Some(e) = self.instruction_trace_error.take() {
+            return Err(e);
+        }
+        if let Some(tracer) = &self.tracer {
+            tracer.write().map_err(|e| file_error(tracer.path(), e))?;
+        }
+        if let Some(trace) = &mut self.instruction_trace {
+            trace.flush().map_err(|e| file_error(trace.path(), e))?;
+        }
+        Ok(())
+    }
 
-        let load_range = START_ADDRESS..START_ADDRESS + rom_data.len();
+    // Loads the RAM saved for the running ROM and returns where it came from, None without
+    // persistent RAM or anything saved. Loading a ROM leaves this to the caller.
+    pub fn restore_persistent_ram(&mut self) -> io::Result<Option<PathBuf>> {
+        match (&self.persistent_ram, &self.rom_hash) {
+            (Some(ram), Some(hash)) => {
+                Ok(ram.restore(hash, &mut self.memory)?.then(|| ram.path(hash)))
+            }
+            _ => Ok(None),
+        }
+    }
 
-        if load_range.end > self.memory.len() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "ROM too large to fit in memory",
-            ));
+    // Call before the ROM goes away, returns where its RAM went
+    pub fn save_persistent_ram(&self) -> io::Result<Option<PathBuf>> {
+        match (&self.persistent_ram, &self.rom_hash) {
+            (Some(ram), Some(hash)) => ram.save(hash, &self.memory).map(Some),
+            _ => Ok(None),
         }
+    }
+
+    // Replaces the running ROM with the boot menu listing `playlist`
+    pub fn show_boot_menu(&mut self, playlist: &Playlist) {
+        let names: Vec<String> = playlist
+            .roms()
+            .iter()
+            .map(|rom| {
+                Path::new(rom)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            })
+            .collect();
+        boot_menu::load(self, &names);
+    }
 
-        self.memory[load_range].copy_from_slice(&rom_data);
+    // Loads a playlist entry in place of the running ROM
+    pub fn switch_rom(&mut self, rom: &str) -> Result<LoadReport, Chip8Error> {
+        self.reset();
 
-        Ok(())
+        let report = match self.load_rom(rom) {
+            Ok(report) => report,
+            Err(e) => {
+                if let Some(log) = &mut self.event_log {
+                    log.error(&format!("failed to load {}: {}", rom, e), self.pc);
+                }
+                return Err(e);
+            }
+        };
+        if let Some(log) = &mut self.event_log {
+            log.rom_loaded(rom, self.rom_hash.as_deref(), &report);
+        }
+        Ok(report)
+    }
+
+    pub fn load_rom(&mut self, filename: &str) -> Result<LoadReport, Chip8Error> {
+        let format = self
+            .rom_format
+            .unwrap_or_else(|| RomFormat::from_path(filename));
+        self.load_rom_with_format(filename, format)
     }
 
+    pub fn load_rom_with_format(
+        &mut self,
+        filename: &str,
+        format: RomFormat,
+    ) -> Result<LoadReport, Chip8Error> {
+        let report = self.load_rom_bytes_with_format(&fs::read(filename)?, format)?;
+        self.rom_path = Some(filename.to_string());
+        Ok(report)
+    }
+
+    pub fn load_rom_bytes_with_format(
+        &mut self,
+        data: &[u8],
+        format: RomFormat,
+    ) -> Result<LoadReport, Chip8Error> {
+        self.load_decoded(format.decode(data.to_vec())?)
+    }
+
+    // Loads a plain binary ROM from memory, for embedders and ROMs built with `include_bytes!`
+    pub fn load_rom_bytes(&mut self, data: &[u8]) -> Result<LoadReport, Chip8Error> {
+        self.load_decoded(DecodedRom {
+            data: data.to_vec(),
+            cart_options: None,
+        })
+    }
+
+    fn load_decoded(&mut self, decoded: DecodedRom) -> Result<LoadReport, Chip8Error> {
+        let rom_data = decoded.data;
+
+        let load_range = self.start_address..self.start_address + rom_data.len();
+        let memory_limit = if megachip::is_megachip_rom(&rom_data) {
+            MEGA_MEMORY_SIZE
+        } else {
+            MEMORY_SIZE
+        };
+
+        if load_range.end > memory_limit {
+            return Err(Chip8Error::RomTooLarge {
+                size: rom_data.len(),
+                max: memory_limit.saturating_sub(self.start_address),
+                start: self.start_address,
+            });
+        }
+
+        let hash = sha1_smol::Sha1::from(&rom_data).digest().to_string();
+        let rom_quirks = self.rom_quirks.get(&hash);
+        let report = LoadReport {
+            bytes_loaded: rom_data.len(),
+            load_range: load_range.clone(),
+            overlaps_font: load_range.start < BIG_FONTSET_START_ADDRESS + BIG_FONTSET_SIZE
+                && load_range.end > FONTSET_START_ADDRESS,
+            reaches_memory_limit: load_range.end == memory_limit,
+            variant_hints: rom::detect_variants(&rom_data),
+            cart_options: decoded.cart_options,
+            quirks_preset: rom_quirks.map(|(name, _)| name.to_string()),
+            rom_info: rom_database::lookup(&hash),
+        };
+
+        if let Some(options) = &report.cart_options {
+            self.quirks = Quirks::from_cart(options);
+        } else if let Some(quirks) = report.rom_info.and_then(RomInfo::quirks)
+            && self.rom_database.quirks
+        {
+            self.quirks = quirks;
+        }
+        if let Some((_, quirks)) = rom_quirks {
+            self.quirks = quirks;
+        }
+        self.place_rom(&rom_data);
+        self.rom_hash = Some(hash);
+        self.rom_path = None;
+        self.rom_image = Some(rom_data);
+
+        Ok(report)
+    }
+
+    // Copies a ROM to `start_address`, memory grows to the next power of two for ROMs that
+    // don't fit
+    fn place_rom(&mut self, image: &[u8]) {
+        let end = self.start_address + image.len();
+        if end > self.memory.len() {
+            self.memory.resize(end.next_power_of_two(), 0);
+        }
+        self.memory[self.start_address..end].copy_from_slice(image);
+    }
+
+    pub fn display_width(&self) -> usize {
+        self.video.width()
+    }
+
+    pub fn display_height(&self) -> usize {
+        self.video.height()
+    }
+
+    pub fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    // Takes effect right away, a low resolution screen is cleared to the new size
+    pub fn set_geometry(&mut self, geometry: Geometry) {
+        self.geometry = geometry;
+        self.resize_video();
+    }
+
+    // Sizes `video` for the current mode with every pixel dark
+    fn resize_video(&mut self) {
+        let (width, height) = if self.megachip.enabled {
+            (MEGA_WIDTH, MEGA_HEIGHT)
+        } else if self.hires {
+            (HIRES_WIDTH, HIRES_HEIGHT)
+        } else {
+            self.geometry.size()
+        };
+        self.video.resize(width, height);
+        self.video_generation += 1;
+    }
+
+    // Changes whenever the screen might have, frontends skip redrawing while it stays the same
+    pub fn video_generation(&self) -> u64 {
+        self.video_generation
+    }
+
+    // The visible pixels, `display_width()` per row
     pub fn get_display(&self) -> &[bool] {
-        &self.video
+        self.video.pixels()
+    }
+
+    // Which XO-CHIP planes each visible pixel is lit on, `display_width()` per row. The same as
+    // `get_display` as 0s and 1s unless a ROM draws on the second plane.
+    pub fn display_planes(&self) -> Vec<u8> {
+        self.video.masks()
     }
 
+    // Each low resolution row as a bitmask, the leftmost pixel is the most significant bit.
+    // Larger screens only show their top left 64x32 pixels here, use `display_rows_wide` for
+    // high resolution mode and the taller ETI-660 screens.
+    pub fn display_rows(&self) -> [u64; CHIP8_HEIGHT] {
+        let mut rows = [0; CHIP8_HEIGHT];
+        let width = self.display_width();
+
+        for (row, pixels) in rows.iter_mut().zip(self.video.pixels().chunks(width)) {
+            *row = pixels[..width.min(CHIP8_WIDTH)]
+                .iter()
+                .fold(0, |bits, &pixel| (bits << 1) | pixel as u64);
+        }
+
+        rows
+    }
+
+    // Like `display_rows` but in the current resolution, one row per `display_height()`
+    pub fn display_rows_wide(&self) -> Vec<u128> {
+        self.get_display()
+            .chunks(self.display_width())
+            .map(|pixels| {
+                pixels
+                    .iter()
+                    .fold(0, |bits, &pixel| (bits << 1) | pixel as u128)
+            })
+            .collect()
+    }
+
+    // The screen of MegaChip and CHIP-8X, which bring their own colours, `display_width()` per
+    // row. None for the others, frontends colour those in themselves.
+    pub fn display_colors(&self) -> Option<Cow<'_, [Rgb]>> {
+        if self.megachip.enabled {
+            Some(Cow::Borrowed(self.megachip.frame()))
+        } else if self.chip8x {
+            Some(Cow::Owned(
+                self.color_grid
+                    .colorize(self.get_display(), self.display_width()),
+            ))
+        } else {
+            None
+        }
+    }
+
+    // `on` and `off` are for screens without colours of their own
+    pub fn get_display_rgba(&self, on: [u8; 4], off: [u8; 4]) -> Vec<u8> {
+        if let Some(colors) = self.display_colors() {
+            return colors
+                .iter()
+                .flat_map(|&[r, g, b]| [r, g, b, 0xFF])
+                .collect();
+        }
+        self.get_display()
+            .iter()
+            .flat_map(|&pixel| if pixel { on } else { off })
+            .collect()
+    }
+
+    // The screen as text, a line of `#` for lit and `.` for dark pixels per row. Readable enough
+    // to keep as a golden snapshot in tests.
+    pub fn display_snapshot(&self) -> String {
+        let mut text = String::with_capacity((self.display_width() + 1) * self.display_height());
+        for row in self.get_display().chunks(self.display_width()) {
+            text.extend(row.iter().map(|&pixel| if pixel { '#' } else { '.' }));
+            text.push('\n');
+        }
+        text
+    }
+
+    // SHA-1 of the screen size and pixels, equal only when the same picture is showing
+    pub fn frame_hash(&self) -> String {
+        let mut sha1 = sha1_smol::Sha1::new();
+        sha1.update(&(self.display_width() as u32).to_le_bytes());
+        sha1.update(&(self.display_height() as u32).to_le_bytes());
+        let pixels: Vec<u8> = self
+            .get_display()
+            .iter()
+            .map(|&pixel| pixel as u8)
+            .collect();
+        sha1.update(&pixels);
+        sha1.digest().to_string()
+    }
+
+    // Returns 0 and leaves PC alone when it points past memory under `OnMemoryFault::Fault`
     pub fn fetch(&mut self) -> u16 {
-        let higher_byte = self.memory[self.pc as usize] as u16;
-        let lower_byte = self.memory[(self.pc + 1) as usize] as u16;
-        let op = (higher_byte << 8) | lower_byte;
-        self.pc += 2;
-        op
+        let pc = self.pc;
+        let (Some(hi), Some(lo)) = (
+            self.memory_address(pc as usize, pc),
+            self.memory_address(pc as usize + 1, pc),
+        ) else {
+            return 0;
+        };
+        // Wrapped around as well, so PC never runs past 0x1001
+        self.pc = hi as u16 + 2;
+        u16::from_be_bytes([self.memory[hi], self.memory[lo]])
+    }
+
+    // Executes one frame's worth of instructions according to the cycle table.
+    // Cycles an instruction overshoots the budget by are paid back next frame.
+    pub fn run_cycles(&mut self, ticks_per_frame: usize) -> u64 {
+        let budget = self
+            .cycle_table
+            .frame_budget
+            .unwrap_or(ticks_per_frame as u64);
+        if self.debug.is_paused() || self.halted.is_some() {
+            return 0;
+        }
+        self.cycle_credit += budget as i64;
+
+        let mut executed = 0;
+        while self.cycle_credit > 0 {
+            let pc = self.pc as usize;
+            let opcode = match (self.memory.get(pc), self.memory.get(pc + 1)) {
+                (Some(&hi), Some(&lo)) => u16::from_be_bytes([hi, lo]),
+                _ => 0,
+            };
+
+            self.cycle_credit -= self.cycle_table.cost(opcode).max(1) as i64;
+            self.step();
+            executed += 1;
+
+            // A watchpoint pauses in the middle of the instruction
+            if self.debug.hit_breakpoint(self.pc) || self.debug.is_paused() || self.halted.is_some()
+            {
+                self.cycle_credit = 0;
+                break;
+            }
+
+            // Slow draw stops the frame at every DRW so the animation can play, and so does
+            // the display wait quirk since the original interpreter drew during vblank
+            if self.slow_draw.as_ref().is_some_and(SlowDraw::is_animating)
+                || (self.quirks.display_wait && opcode & 0xF000 == 0xD000)
+            {
+                self.cycle_credit = 0;
+                break;
+            }
+        }
+
+        executed
     }
 
-    pub fn tick(&mut self) {
+    // Runs the single steps queued on the paused debugger
+    pub fn run_pending_steps(&mut self) -> u64 {
+        let steps = self.debug.take_steps();
+        for _ in 0..steps {
+            self.step();
+        }
+        steps as u64
+    }
+
+    // Executes the instruction at PC
+    pub fn step(&mut self) {
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        let pc = self.pc;
         let op = self.fetch();
+        // PC ran off the end of memory and halted
+        if self.pc == pc && self.halted.is_some() {
+            return;
+        }
+        self.history.push_back((pc, op));
+
+        let instruction = self.decode_opcode(op);
+        let before = self
+            .instruction_trace
+            .as_ref()
+            .map(|_| TracedRegisters::of(self));
+        self.execute(instruction);
+        if let Some(before) = before {
+            self.trace_instruction(pc, op, instruction, &before);
+        }
+
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.step();
+        }
+    }
+
+    fn trace_instruction(
+        &mut self,
+        pc: u16,
+        opcode: u16,
+        instruction: Instruction,
+        before: &TracedRegisters,
+    ) {
+        let after = TracedRegisters::of(self);
+        if let Some(trace) = &mut self.instruction_trace
+            && let Err(e) = trace.record(pc, opcode, instruction, before, &after)
+        {
+            self.instruction_trace_error = Some(file_error(trace.path(), e));
+            self.instruction_trace = None;
+        }
+    }
+
+    // Like `with_stack_depth` for a machine that already exists, anything on the stack is dropped
+    pub fn set_stack_depth(&mut self, depth: usize) {
+        self.stack = vec![0; depth.min(u8::MAX as usize)];
+        self.sp = 0;
+    }
+
+    pub fn watchdog_tripped(&self) -> bool {
+        self.watchdog.is_some_and(|w| w.tripped())
+    }
 
-        self.execute(op);
+    // Starts the watchdog's count over, once whoever runs the ROM has dealt with it tripping
+    pub fn feed_watchdog(&mut self) {
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.feed();
+        }
     }
 
     pub fn keypress(&mut self, idx: usize, pressed: bool) {
         self.keypad[idx] = pressed;
     }
 
+    pub fn set_key(&mut self, key: u8, pressed: bool) -> Result<(), InvalidKey> {
+        let state = self.keypad.get_mut(key as usize).ok_or(InvalidKey(key))?;
+        *state = pressed;
+        Ok(())
+    }
+
+    pub fn is_key_down(&self, key: u8) -> Result<bool, InvalidKey> {
+        self.keypad
+            .get(key as usize)
+            .copied()
+            .ok_or(InvalidKey(key))
+    }
+
+    // Only CHIP-8X has a second keypad, for other ROMs the second player shares the first's
+    pub fn keypress2(&mut self, idx: usize, pressed: bool) {
+        if self.chip8x {
+            self.keypad2[idx] = pressed;
+        } else {
+            self.keypad[idx] = pressed;
+        }
+    }
+
+    pub fn clear_keys(&mut self) {
+        self.keypad = [false; KEY_COUNT];
+        self.keypad2 = [false; KEY_COUNT];
+    }
+
+    pub fn key_injector(&self) -> KeyInjector {
+        self.key_injector.clone()
+    }
+
+    pub fn process_injected_keys(&mut self) {
+        while let Ok((key, pressed)) = self.injected_keys.try_recv() {
+            self.keypad[key as usize] = pressed;
+        }
+    }
+
     pub fn tick_timers(&mut self) {
+        if self.dtimer > 0 || self.stimer > 0 {
+            self.feed_watchdog();
+        }
+
+        // The frame just run sounded if the timer was still going when it ended
+        self.buzzer.push_frame(FrameSound {
+            active: self.sound_active(),
+            pattern: self.audio_pattern,
+            pitch: self.pitch,
+        });
+
         if self.dtimer > 0 {
             self.dtimer -= 1;
         }
@@ -191,111 +973,358 @@ impl Chip8 {
         }
     }
 
-    pub fn execute(&mut self, opcode: u16) {
-        let nibbles = (
-            (opcode & 0xF000) >> 12, // First Digit
-            (opcode & 0x0F00) >> 8,  // Second Digit
-            (opcode & 0x00F0) >> 4,  // Third Digit
-            (opcode & 0x000F),       // Fourth Digit
-        );
+    // The buzzer sounds for as long as the sound timer is non-zero
+    pub fn sound_active(&self) -> bool {
+        self.stimer > 0
+    }
+
+    // Plays back the frames ticked since the last call, see `Buzzer::render`
+    pub fn render_audio(&mut self, out: &mut [f32], sample_rate: u32) {
+        self.buzzer.render(out, sample_rate);
+    }
+
+    // One 60 Hz frame for embedders: applies the keypad, runs the frame's instructions, ticks
+    // the timers and returns the visible framebuffer
+    pub fn run_frame(&mut self, keys: &[bool; KEY_COUNT], ticks_per_frame: usize) -> &[bool] {
+        self.keypad = *keys;
+        self.run_cycles(ticks_per_frame);
+        self.tick_timers();
+        self.get_display()
+    }
+
+    // Every memory access an instruction makes goes through these two so watchpoints and
+    // write protection see it. Fetching the instruction itself doesn't.
+    fn read_byte(&mut self, addr: usize) -> u8 {
+        let Some(addr) = self.memory_address(addr, self.pc.wrapping_sub(2)) else {
+            return 0;
+        };
+        let value = self.memory[addr];
+        self.debug.check_access(WatchHit {
+            address: addr as u16,
+            pc: self.pc.wrapping_sub(2),
+            value,
+            write: false,
+        });
+        value
+    }
+
+    fn write_byte(&mut self, addr: usize, value: u8) {
+        let pc = self.pc.wrapping_sub(2);
+        if self.protect_interpreter_area && addr < self.start_address {
+            if self.halted.is_none() {
+                // Stay on the instruction so dumps and the debugger show where it happened
+                self.pc = pc;
+                self.halted = Some(Chip8Error::ProtectedWrite {
+                    address: addr as u16,
+                    pc,
+                });
+            }
+            return;
+        }
+
+        let Some(addr) = self.memory_address(addr, pc) else {
+            return;
+        };
+        self.memory[addr] = value;
+        self.debug.check_access(WatchHit {
+            address: addr as u16,
+            pc,
+            value,
+            write: true,
+        });
+    }
+
+    // Where `addr` is in memory, wrapped around or None after halting with a memory fault when
+    // it is past the end. `pc` is the instruction making the access.
+    fn memory_address(&mut self, addr: usize, pc: u16) -> Option<usize> {
+        if addr < self.memory.len() {
+            return Some(addr);
+        }
+
+        match self.on_memory_fault {
+            OnMemoryFault::Wrap => Some(addr % self.memory.len()),
+            OnMemoryFault::Fault => {
+                if self.halted.is_none() {
+                    self.pc = pc;
+                    self.halted = Some(Chip8Error::MemoryFault {
+                        address: addr as u16,
+                        pc,
+                    });
+                }
+                None
+            }
+        }
+    }
+
+    // A sprite row left aligned in 16 bits, 16 pixel wide sprites take two bytes per row
+    // `offset` bytes past I, where the second plane's sprite starts
+    fn sprite_row(&mut self, offset: usize, row: usize, sprite_width: usize) -> u16 {
+        let bytes = sprite_width / 8;
+        let addr = self.index as usize + offset + row * bytes;
+
+        if bytes == 2 {
+            u16::from_be_bytes([self.read_byte(addr), self.read_byte(addr + 1)])
+        } else {
+            (self.read_byte(addr) as u16) << 8
+        }
+    }
+
+    fn unknown_opcode(&mut self, opcode: u16) {
+        let unknown = UnknownOpcode {
+            opcode,
+            pc: self.pc.wrapping_sub(2),
+        };
+
+        match self.on_unknown_opcode {
+            OnUnknownOpcode::Panic => panic!("{}", unknown),
+            OnUnknownOpcode::Skip => (),
+            OnUnknownOpcode::Halt => {
+                // Stay on the opcode so PC points at it in dumps and the debugger
+                self.pc = unknown.pc;
+                self.halted = Some(unknown.into());
+            }
+            OnUnknownOpcode::Log => {
+                if let Some(log) = &mut self.event_log {
+                    log.error(&unknown.to_string(), unknown.pc);
+                }
+                self.skipped_opcodes.push(unknown);
+            }
+        }
+    }
+
+    // What `opcode` means to the running ROM, CHIP-8X and MegaChip ROMs give some opcodes
+    // meanings of their own
+    pub fn decode_opcode(&self, opcode: u16) -> Instruction {
+        if self.chip8x {
+            decode_chip8x(opcode)
+        } else if self.megachip_opcodes() {
+            decode_megachip(opcode)
+        } else {
+            decode(opcode)
+        }
+    }
+
+    // MegaChip's opcodes only exist while its screen is on or for ROMs starting with MEGAON
+    fn megachip_opcodes(&self) -> bool {
+        self.megachip.enabled
+            || self
+                .rom_image
+                .as_deref()
+                .is_some_and(megachip::is_megachip_rom)
+    }
 
-        match nibbles {
+    // Skips the next instruction, all four bytes of it when that is a MegaChip LDHI or XO-CHIP's
+    // F000 NNNN
+    fn skip(&mut self) {
+        let pc = self.pc as usize;
+        let next = [pc, pc + 1].map(|addr| self.memory.get(addr).copied().unwrap_or(0));
+        self.pc += 2;
+        if (next[0] == 0x01 && self.megachip_opcodes()) || next == [0xF0, 0x00] {
+            self.pc += 2;
+        }
+    }
+
+    // The register 8XY6/8XYE shift, VY on the original interpreter and VX everywhere else
+    fn shift_source(&self, x: u8, y: u8) -> u8 {
+        if self.quirks.shift_uses_vy {
+            self.registers[y as usize]
+        } else {
+            self.registers[x as usize]
+        }
+    }
+
+    // Moves the picture by dx/dy pixels, whatever scrolls in is blank
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        // MegaChip scrolls what is being drawn, the screen only changes on CLS
+        if self.megachip.enabled {
+            self.megachip.scroll(dx, dy);
+            return;
+        }
+
+        self.video.scroll(self.planes, dx, dy);
+        self.video_generation += 1;
+    }
+
+    pub fn execute(&mut self, instruction: Instruction) {
+        match instruction {
             // NOP
-            (0, 0, 0, 0) => return,
-            // CLS
-            (0, 0, 0xE, 0) => self.video = [false; 64 * 32],
-            // RET
-            (0, 0, 0xE, 0xE) => {
-                self.sp -= 1;
-                self.pc = self.stack[self.sp as usize]
+            Instruction::Sys(0) => (),
+            Instruction::ScrollDown(n) => {
+                self.scroll(0, n as isize);
+                self.feed_watchdog();
             }
-            // JP addr
-            (1, _, _, _) => {
-                let address = opcode & 0x0FFF;
+            Instruction::Cls => {
+                if self.megachip.enabled {
+                    self.megachip.show(self.video.pixels_mut());
+                } else {
+                    self.video.clear_planes(self.planes);
+                }
+                self.video_generation += 1;
+                self.feed_watchdog();
+            }
+            Instruction::Ret => {
+                if self.sp == 0 {
+                    // Stay on the RET like an overflowing CALL does
+                    self.pc = self.pc.wrapping_sub(2);
+                    self.halted = Some(Chip8Error::StackUnderflow { pc: self.pc });
+                    return;
+                }
 
+                self.sp -= 1;
+                self.pc = self.stack[self.sp as usize];
+
+                if let Some(tracer) = &mut self.tracer {
+                    tracer.ret();
+                }
+            }
+            Instruction::ScrollRight => {
+                self.scroll(4, 0);
+                self.feed_watchdog();
+            }
+            Instruction::ScrollLeft => {
+                self.scroll(-4, 0);
+                self.feed_watchdog();
+            }
+            // Stays on this instruction like the interpreter returning to the HP48
+            Instruction::Exit => {
+                self.pc -= 2;
+            }
+            // The old contents don't fit the new layout so the screen is cleared
+            Instruction::Low | Instruction::High => {
+                self.hires = instruction == Instruction::High;
+                self.resize_video();
+                self.feed_watchdog();
+            }
+            Instruction::MegaOff | Instruction::MegaOn => {
+                self.megachip
+                    .set_enabled(instruction == Instruction::MegaOn);
+                self.resize_video();
+                self.feed_watchdog();
+            }
+            Instruction::LoadHighIndex(high) => {
+                let low = self.fetch();
+                self.index = (high as u32) << 16 | low as u32;
+            }
+            Instruction::LoadPalette(count) => {
+                let i = self.index as usize;
+                let colors: Vec<u8> = (0..count as usize * 4)
+                    .map(|offset| self.read_byte(i + offset))
+                    .collect();
+                self.megachip.load_palette(&colors);
+            }
+            Instruction::SpriteWidth(width) => self.megachip.sprite_width = width,
+            Instruction::SpriteHeight(height) => self.megachip.sprite_height = height,
+            // Screen alpha faded the original's window and sampled sound isn't played, the
+            // buzzer still follows the sound timer
+            Instruction::ScreenAlpha(_) | Instruction::PlaySample(_) | Instruction::StopSample => {}
+            Instruction::BlendMode(mode) => {
+                self.megachip.blend = BlendMode::from_operand(mode).unwrap_or_default();
+            }
+            Instruction::CollisionIndex(index) => self.megachip.collision_index = index,
+            Instruction::ScrollUp(n) => {
+                self.scroll(0, -(n as isize));
+                self.feed_watchdog();
+            }
+            Instruction::CycleBackground => {
+                self.color_grid.cycle_background();
+                self.video_generation += 1;
+            }
+            Instruction::AddNibbles { x, y } => {
+                let (vx, vy) = (self.registers[x as usize], self.registers[y as usize]);
+                self.registers[x as usize] = ((vx & 0x77) + (vy & 0x77)) & 0x77;
+            }
+            Instruction::ColorBlocks { x, y } => {
+                let (horizontal, vertical) = (
+                    self.registers[x as usize],
+                    self.registers[(x as usize + 1) % 16],
+                );
+                self.color_grid
+                    .fill_blocks(horizontal, vertical, self.registers[y as usize]);
+                self.video_generation += 1;
+            }
+            Instruction::ColorZones { x, y, rows } => {
+                let (column, row) = (
+                    self.registers[x as usize],
+                    self.registers[(x as usize + 1) % 16],
+                );
+                self.color_grid
+                    .fill_column(column, row, rows, self.registers[y as usize]);
+                self.video_generation += 1;
+            }
+            Instruction::Jump(address) => {
                 self.pc = address;
             }
-            // CALL addr
-            (2, _, _, _) => {
-                let address = opcode & 0x0FFF;
+            Instruction::Call(address) => {
+                if self.sp as usize >= self.stack.len()
+                    && self.grow_stack
+                    && self.stack.len() < u8::MAX as usize
+                {
+                    self.stack.push(0);
+                }
+                if self.sp as usize >= self.stack.len() {
+                    // Stay on the CALL so dumps and the debugger show where it happened
+                    self.pc -= 2;
+                    self.halted = Some(Chip8Error::StackOverflow {
+                        address,
+                        pc: self.pc,
+                        depth: self.stack.len(),
+                    });
+                    return;
+                }
 
                 self.stack[self.sp as usize] = self.pc;
                 self.sp += 1;
                 self.pc = address;
-            }
-            // SE Vx, byte
-            (3, _, _, _) => {
-                let vx = nibbles.1 as usize;
-                let byte = opcode & 0x00FF;
 
-                if self.registers[vx] == byte as u8 {
-                    self.pc += 2;
+                if let Some(tracer) = &mut self.tracer {
+                    tracer.call(address);
                 }
             }
-            // SNE Vx, byte
-            (4, _, _, _) => {
-                let vx = nibbles.1 as usize;
-                let byte = opcode & 0x00FF;
-
-                if self.registers[vx] != byte as u8 {
-                    self.pc += 2;
+            Instruction::SkipEqByte { x, byte } => {
+                if self.registers[x as usize] == byte {
+                    self.skip();
                 }
             }
-            // SE Vx, Vy
-            (5, _, _, _) => {
-                let vx = nibbles.1 as usize;
-                let vy = nibbles.2 as usize;
-
-                if self.registers[vx] == self.registers[vy] {
-                    self.pc += 2;
+            Instruction::SkipNeByte { x, byte } => {
+                if self.registers[x as usize] != byte {
+                    self.skip();
                 }
             }
-            // LD Vx, byte
-            (6, _, _, _) => {
-                let vx = nibbles.1 as usize;
-                let byte = opcode & 0x00FF;
-
-                self.registers[vx] = byte as u8;
+            Instruction::SkipEq { x, y } => {
+                if self.registers[x as usize] == self.registers[y as usize] {
+                    self.skip();
+                }
             }
-            // ADD Vx, byte
-            (7, _, _, _) => {
-                let vx = nibbles.1 as usize;
-                let byte = opcode & 0x00FF;
-
-                self.registers[vx] = self.registers[vx].wrapping_add(byte as u8);
+            Instruction::LoadByte { x, byte } => {
+                self.registers[x as usize] = byte;
             }
-            // LD Vx, Vy
-            (8, _, _, 0) => {
-                let vx = nibbles.1 as usize;
-                let vy = nibbles.2 as usize;
-
-                self.registers[vx] = self.registers[vy];
+            Instruction::AddByte { x, byte } => {
+                let vx = x as usize;
+                self.registers[vx] = self.registers[vx].wrapping_add(byte);
             }
-            // OR Vx, Vy
-            (8, _, _, 1) => {
-                let vx = nibbles.1 as usize;
-                let vy = nibbles.2 as usize;
-
-                self.registers[vx] |= self.registers[vy];
+            Instruction::Load { x, y } => {
+                self.registers[x as usize] = self.registers[y as usize];
             }
-            // AND Vx, Vy
-            (8, _, _, 2) => {
-                let vx = nibbles.1 as usize;
-                let vy = nibbles.2 as usize;
-
-                self.registers[vx] &= self.registers[vy];
+            Instruction::Or { x, y } => {
+                self.registers[x as usize] |= self.registers[y as usize];
+                if self.quirks.logic_resets_vf {
+                    self.registers[0xF] = 0;
+                }
             }
-            // XOR Vx, Vy
-            (8, _, _, 3) => {
-                let vx = nibbles.1 as usize;
-                let vy = nibbles.2 as usize;
-
-                self.registers[vx] ^= self.registers[vy];
+            Instruction::And { x, y } => {
+                self.registers[x as usize] &= self.registers[y as usize];
+                if self.quirks.logic_resets_vf {
+                    self.registers[0xF] = 0;
+                }
             }
-            // ADD Vx, Vy
-            (8, _, _, 4) => {
-                let vx = nibbles.1 as usize;
-                let vy = nibbles.2 as usize;
+            Instruction::Xor { x, y } => {
+                self.registers[x as usize] ^= self.registers[y as usize];
+                if self.quirks.logic_resets_vf {
+                    self.registers[0xF] = 0;
+                }
+            }
+            Instruction::Add { x, y } => {
+                let (vx, vy) = (x as usize, y as usize);
 
                 let (new_vx, carry) = self.registers[vx].overflowing_add(self.registers[vy]);
                 let new_vf = if carry { 1 } else { 0 };
@@ -303,10 +1332,8 @@ impl Chip8 {
                 self.registers[vx] = new_vx;
                 self.registers[0xF] = new_vf;
             }
-            // SUB Vx, Vy
-            (8, _, _, 5) => {
-                let vx = nibbles.1 as usize;
-                let vy = nibbles.2 as usize;
+            Instruction::Sub { x, y } => {
+                let (vx, vy) = (x as usize, y as usize);
 
                 let (new_vx, borrow) = self.registers[vx].overflowing_sub(self.registers[vy]);
                 let new_vf = if borrow { 0 } else { 1 };
@@ -314,192 +1341,823 @@ impl Chip8 {
                 self.registers[vx] = new_vx;
                 self.registers[0xF] = new_vf;
             }
-            // SHR Vx
-            (8, _, _, 6) => {
-                let vx = nibbles.1 as usize;
+            Instruction::Shr { x, y } => {
+                let source = self.shift_source(x, y);
 
+                self.registers[x as usize] = source >> 1;
                 // Save LSB in VF
-                self.registers[0xF] = self.registers[vx] & 0x1;
-
-                self.registers[vx] >>= 1;
+                self.registers[0xF] = source & 0x1;
             }
-            // SUBN Vx, Vy
-            (8, _, _, 7) => {
-                let vx = nibbles.1 as usize;
-                let vy = nibbles.2 as usize;
+            Instruction::SubN { x, y } => {
+                let (vx, vy) = (x as usize, y as usize);
 
-                if self.registers[vy] > self.registers[vx] {
-                    self.registers[0xF] = 1;
-                } else {
-                    self.registers[0xF] = 0;
-                }
+                let (new_vx, borrow) = self.registers[vy].overflowing_sub(self.registers[vx]);
+                let new_vf = if borrow { 0 } else { 1 };
 
-                self.registers[vx] = self.registers[vy] - self.registers[vx];
+                self.registers[vx] = new_vx;
+                self.registers[0xF] = new_vf;
             }
-            // SHL Vx {, Vy}
-            (8, _, _, 0xE) => {
-                let vx = nibbles.1 as usize;
+            Instruction::Shl { x, y } => {
+                let source = self.shift_source(x, y);
 
+                self.registers[x as usize] = source << 1;
                 // Save MSB in VF
-                self.registers[0xF] = (self.registers[vx] & 0x80) >> 7;
-
-                self.registers[vx] <<= 1;
+                self.registers[0xF] = (source & 0x80) >> 7;
             }
-            // SNE Vx, Vy
-            (9, _, _, 0) => {
-                let vx = nibbles.1 as usize;
-                let vy = nibbles.2 as usize;
-
-                if self.registers[vx] != self.registers[vy] {
-                    self.pc += 2;
+            Instruction::SkipNe { x, y } => {
+                if self.registers[x as usize] != self.registers[y as usize] {
+                    self.skip();
                 }
             }
-            // LD I, addr
-            (0xA, _, _, _) => {
-                let address = opcode & 0x0FFF;
-
-                self.index = address;
+            Instruction::LoadIndex(address) => {
+                self.index = address as u32;
             }
-            // JP V0, addr
-            (0xB, _, _, _) => {
-                let address = opcode & 0x0FFF;
+            // JP V0, addr (or JP Vx, addr with the jump quirk)
+            Instruction::JumpOffset { x, address } => {
+                let offset = if self.quirks.jump_uses_vx {
+                    self.registers[x as usize]
+                } else {
+                    self.registers[0]
+                };
 
-                self.pc = self.registers[0] as u16 + address;
+                self.pc = offset as u16 + address;
             }
-            // RND Vx, byte
-            (0xC, _, _, _) => {
-                let vx = nibbles.1 as usize;
-                let byte = opcode & 0x00FF;
-                let rng: u8 = rand::rng().random();
+            Instruction::Random { x, byte } => {
+                let rng: u8 = self.rng.random();
 
-                self.registers[vx] = rng & byte as u8;
+                self.registers[x as usize] = rng & byte;
             }
-            // DRW Vx, Vy, nibble
-            (0xD, _, _, _) => {
-                let x_coord = self.registers[nibbles.1 as usize] as u16;
-                let y_coord = self.registers[nibbles.2 as usize] as u16;
-                let num_rows = nibbles.3;
-
+            Instruction::Draw { x, y, .. } if self.megachip.enabled => {
+                let (x, y) = (self.registers[x as usize], self.registers[y as usize]);
+                let (width, height) = self.megachip.sprite_size();
+                let i = self.index as usize;
+                let sprite: Vec<u8> = (0..width * height)
+                    .map(|offset| self.read_byte(i + offset))
+                    .collect();
+                let collided = self.megachip.draw(x as usize, y as usize, &sprite);
+                self.registers[0xF] = collided as u8;
+                self.feed_watchdog();
+            }
+            Instruction::Draw { x, y, rows } => {
+                let width = self.display_width();
+                let height = self.display_height();
+                let x_coord = self.registers[x as usize] as usize % width;
+                let y_coord = self.registers[y as usize] as usize % height;
+                // DXY0 draws a SUPER-CHIP 16x16 sprite, two bytes per row
+                let (sprite_width, num_rows) = match rows {
+                    0 => (16, 16),
+                    n => (8, n as usize),
+                };
+
+                // With both XO-CHIP planes selected the second plane's sprite follows the first
+                let planes: Vec<usize> = (0..display::PLANES)
+                    .filter(|plane| self.planes & (1 << plane) != 0)
+                    .collect();
+                let sprite_size = num_rows * sprite_width / 8;
+                let sprites: Vec<Vec<u16>> = (0..planes.len())
+                    .map(|n| {
+                        (0..num_rows)
+                            .map(|row| self.sprite_row(n * sprite_size, row, sprite_width))
+                            .collect()
+                    })
+                    .collect();
                 let mut flipped = false;
-
-                for y_line in 0..num_rows {
-                    let addr = self.index + y_line as u16;
-                    let pixels = self.memory[addr as usize];
-
-                    for x_line in 0..8 {
-                        if (pixels & (0b1000_0000 >> x_line)) != 0 {
-                            let x = (x_coord + x_line) as usize % CHIP8_WIDTH;
-                            let y = (y_coord + y_line) as usize % CHIP8_HEIGHT;
-
-                            let idx = x + CHIP8_WIDTH * y;
-                            flipped |= self.video[idx];
-                            self.video[idx] ^= true;
+                let mut drawn = false;
+
+                for (&plane, sprite) in planes.iter().zip(&sprites) {
+                    for (y_line, &pixels) in sprite.iter().enumerate() {
+                        for x_line in 0..sprite_width {
+                            if (pixels & (0x8000 >> x_line)) != 0 {
+                                if self.quirks.clip_sprites
+                                    && (x_coord + x_line >= width || y_coord + y_line >= height)
+                                {
+                                    continue;
+                                }
+
+                                let x = (x_coord + x_line) % width;
+                                let y = (y_coord + y_line) % height;
+
+                                flipped |= self.video.flip_plane(plane, x, y);
+                                drawn = true;
+                            }
                         }
                     }
                 }
+                let rows = sprites.into_iter().next().unwrap_or_default();
+                if drawn {
+                    self.video_generation += 1;
+                }
                 if flipped {
                     self.registers[0xF] = 1;
                 } else {
                     self.registers[0xF] = 0;
                 }
-            }
-            // SKP Vx
-            (0xE, _, 9, 0xE) => {
-                let vx = nibbles.1 as usize;
-                let key = self.registers[vx];
+                self.feed_watchdog();
 
-                if self.keypad[key as usize] {
-                    self.pc += 2;
+                if let Some(tracer) = &mut self.tracer {
+                    tracer.draw(x_coord as u8, y_coord as u8, num_rows as u8, flipped);
+                }
+                if let Some(slow_draw) = &mut self.slow_draw {
+                    slow_draw.record(SpriteDraw {
+                        x: x_coord,
+                        y: y_coord,
+                        width: sprite_width,
+                        rows,
+                        collided: flipped,
+                        clipped: self.quirks.clip_sprites,
+                        screen_width: width,
+                        screen_height: height,
+                    });
                 }
             }
-            // SKNP Vx
-            (0xE, _, 0xA, 1) => {
-                let vx = nibbles.1 as usize;
-                let key = self.registers[vx];
+            // Only the low nibble of VX picks a key, like the VIP's keypad latch
+            Instruction::SkipKey(x) => {
+                let key = self.registers[x as usize];
+                self.feed_watchdog();
 
-                if !self.keypad[key as usize] {
-                    self.pc += 2;
+                if self.keypad[key as usize & 0xF] {
+                    self.skip();
                 }
             }
-            // LD Vx, DT
-            (0xF, _, 0, 7) => {
-                let vx = nibbles.1 as usize;
+            Instruction::SkipNotKey(x) => {
+                let key = self.registers[x as usize];
+                self.feed_watchdog();
 
-                self.registers[vx] = self.dtimer;
+                if !self.keypad[key as usize & 0xF] {
+                    self.skip();
+                }
             }
-            // LD Vx, K
-            (0xF, _, 0, 0xA) => {
-                let vx = nibbles.1 as usize;
-                let mut pressed = false;
+            Instruction::SkipKey2(x) => {
+                let key = self.registers[x as usize];
+                self.feed_watchdog();
 
-                for i in 0..self.keypad.len() {
-                    if self.keypad[i] {
-                        self.registers[vx] = i as u8;
-                        pressed = true;
-                        break;
-                    }
+                if self.keypad2[key as usize & 0xF] {
+                    self.skip();
                 }
+            }
+            Instruction::SkipNotKey2(x) => {
+                let key = self.registers[x as usize];
+                self.feed_watchdog();
 
-                if !pressed {
-                    self.pc -= 2;
+                if !self.keypad2[key as usize & 0xF] {
+                    self.skip();
                 }
             }
-            // LD DT, Vx
-            (0xF, _, 1, 5) => {
-                let vx = nibbles.1 as usize;
-
-                self.dtimer = self.registers[vx];
+            Instruction::LoadDelay(x) => {
+                self.registers[x as usize] = self.dtimer;
             }
-            // LD ST, Vx
-            (0xF, _, 1, 8) => {
-                let vx = nibbles.1 as usize;
-
-                self.stimer = self.registers[vx];
+            Instruction::WaitKey(x) => {
+                let vx = x as usize;
+                self.feed_watchdog();
+                let pressed = self.keypad.iter().position(|&down| down).map(|i| i as u8);
+
+                if self.quirks.wait_key_release {
+                    // Remember the first key pressed and only finish once it is released
+                    match self.key_wait {
+                        Some(key) if !self.keypad[key as usize] => {
+                            self.registers[vx] = key;
+                            self.key_wait = None;
+                        }
+                        Some(_) => self.pc -= 2,
+                        None => {
+                            self.key_wait = pressed;
+                            self.pc -= 2;
+                        }
+                    }
+                } else {
+                    match pressed {
+                        Some(key) => self.registers[vx] = key,
+                        None => self.pc -= 2,
+                    }
+                }
+            }
+            Instruction::SetDelay(x) => {
+                self.dtimer = self.registers[x as usize];
+                self.feed_watchdog();
+            }
+            Instruction::SetSound(x) => {
+                self.stimer = self.registers[x as usize];
+                self.feed_watchdog();
             }
-            // ADD I, Vx
-            (0xF, _, 1_, 0xE) => {
-                let vx = nibbles.1 as usize;
-                let x = self.registers[vx] as u16;
+            Instruction::AddIndex(x) => {
+                let x = self.registers[x as usize] as u32;
 
                 self.index = self.index.wrapping_add(x);
             }
-            // LD F, Vx
-            (0xF, _, 2, 9) => {
-                let vx = nibbles.1 as usize;
-                let digit = self.registers[vx] as u16;
+            Instruction::LoadFont(x) => {
+                let digit = self.registers[x as usize] as u32;
 
-                self.index = FONTSET_START_ADDRESS as u16 + (5 * digit);
+                self.index = FONTSET_START_ADDRESS as u32 + (5 * digit);
             }
-            // LD B, Vx
-            (0xF, _, 3, 3) => {
-                let vx = nibbles.1 as usize;
-                let value = self.registers[vx] as f32;
+            Instruction::LoadBigFont(x) => {
+                let digit = (self.registers[x as usize] & 0xF) as u32;
 
-                let hundreds = (value / 100.0).floor() as u8;
-                let tens = ((value / 10.0) % 10.0).floor() as u8;
-                let ones = (value % 10.0) as u8;
-
-                self.memory[self.index as usize] = hundreds;
-                self.memory[(self.index + 1) as usize] = tens;
-                self.memory[(self.index + 2) as usize] = ones;
+                self.index = BIG_FONTSET_START_ADDRESS as u32 + (10 * digit);
+            }
+            Instruction::Bcd(x) => {
+                let i = self.index as usize;
+                for (offset, digit) in bcd(self.registers[x as usize]).into_iter().enumerate() {
+                    self.write_byte(i + offset, digit);
+                }
             }
-            // LD [I], Vx
-            (0xF, _, 5, 5) => {
-                let vx = nibbles.1 as usize;
+            Instruction::Store(x) => {
+                let vx = x as usize;
                 let i = self.index as usize;
                 for idx in 0..=vx {
-                    self.memory[i + idx] = self.registers[idx];
+                    self.write_byte(i + idx, self.registers[idx]);
+                }
+                if self.quirks.load_store_increments_index {
+                    self.index = self.index.wrapping_add(vx as u32 + 1);
                 }
             }
-            // LD Vx, [I]
-            (0xF, _, 6, 5) => {
-                let vx = nibbles.1 as usize;
+            Instruction::Restore(x) => {
+                let vx = x as usize;
                 let i = self.index as usize;
                 for idx in 0..=vx {
-                    self.registers[idx] = self.memory[i + idx];
+                    self.registers[idx] = self.read_byte(i + idx);
+                }
+                if self.quirks.load_store_increments_index {
+                    self.index = self.index.wrapping_add(vx as u32 + 1);
+                }
+            }
+            Instruction::StoreFlags(x) => {
+                let vx = (x as usize).min(RPL_FLAGS - 1);
+                self.rpl[..=vx].copy_from_slice(&self.registers[..=vx]);
+            }
+            Instruction::RestoreFlags(x) => {
+                let vx = (x as usize).min(RPL_FLAGS - 1);
+                self.registers[..=vx].copy_from_slice(&self.rpl[..=vx]);
+            }
+            Instruction::Plane(planes) => {
+                self.planes = planes & ((1 << display::PLANES) - 1);
+            }
+            Instruction::LoadLongIndex => {
+                self.index = self.fetch() as u32;
+            }
+            Instruction::LoadPattern => {
+                let i = self.index as usize;
+                let mut pattern = [0; PATTERN_SIZE];
+                for (offset, byte) in pattern.iter_mut().enumerate() {
+                    *byte = self.read_byte(i + offset);
                 }
+                self.audio_pattern = Some(pattern);
             }
-            (_, _, _, _) => unimplemented!("Unimplemented opcode: {:#04x}", opcode),
+            Instruction::SetPitch(x) => {
+                self.pitch = self.registers[x as usize];
+            }
+            Instruction::StoreRange { x, y } => {
+                let i = self.index as usize;
+                for (offset, register) in register_range(x, y).into_iter().enumerate() {
+                    self.write_byte(i + offset, self.registers[register]);
+                }
+            }
+            Instruction::RestoreRange { x, y } => {
+                let i = self.index as usize;
+                for (offset, register) in register_range(x, y).into_iter().enumerate() {
+                    self.registers[register] = self.read_byte(i + offset);
+                }
+            }
+            // A 0NNN opcode is its own address
+            Instruction::Sys(opcode) | Instruction::Unknown(opcode) => self.unknown_opcode(opcode),
+        }
+    }
+}
+
+// The registers 5XY2 and 5XY3 go through, VX first and counting down when VY is lower
+fn register_range(x: u8, y: u8) -> Vec<usize> {
+    let (x, y) = (x as usize, y as usize);
+    if x <= y {
+        (x..=y).collect()
+    } else {
+        (y..=x).rev().collect()
+    }
+}
+
+// io errors alone don't say which file they were about
+fn file_error(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+}
+
+// The hundreds, tens and ones digits of `value`, what FX33 stores at I
+pub fn bcd(value: u8) -> [u8; 3] {
+    [value / 100, value / 10 % 10, value % 10]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycle_budget_carries_overshoot_into_the_next_frame() {
+        use timing::{CycleTable, OpcodeClass};
+
+        let mut emu = Chip8::new();
+        // Four LD Vx, byte and a jump back to the start, 4 cycles each
+        let program = [0x60, 1, 0x60, 2, 0x60, 3, 0x60, 4, 0x12, 0x00];
+        emu.memory[START_ADDRESS..START_ADDRESS + program.len()].copy_from_slice(&program);
+        let mut table = CycleTable::flat();
+        table.set_cost(OpcodeClass::LoadImmediate, 4);
+        table.set_cost(OpcodeClass::Jump, 4);
+        table.frame_budget = Some(10);
+        emu.cycle_table = table;
+
+        // 10 cycles run three 4-cycle instructions, the 2 overshot come off the next frame
+        assert_eq!(emu.run_cycles(1), 3);
+        assert_eq!(emu.run_cycles(1), 2);
+        assert_eq!(emu.pc, 0x200);
+        assert_eq!(emu.run_cycles(1), 3);
+
+        // Without a frame budget the caller's ticks are the budget, less the overshoot
+        emu.cycle_table = CycleTable::flat();
+        assert_eq!(emu.run_cycles(7), 5);
+        assert_eq!(emu.run_cycles(7), 7);
+    }
+
+    #[test]
+    fn schip_hires_draws_16x16_sprites_and_scrolls() {
+        let mut emu = Chip8::new();
+        emu.execute(decode(0x00FF));
+        assert_eq!((emu.display_width(), emu.display_height()), (128, 64));
+
+        // DXY0 reads 32 bytes, two per row: only the top row and the last row's left half
+        emu.memory[0x300..0x302].copy_from_slice(&[0xFF, 0xFF]);
+        emu.memory[0x31E] = 0x80;
+        emu.index = 0x300;
+        emu.execute(decode(0xD000));
+        let lit = |emu: &Chip8, x: usize, y: usize| emu.get_display()[x + y * 128];
+        assert!(lit(&emu, 0, 0) && lit(&emu, 15, 0) && !lit(&emu, 16, 0));
+        assert!(lit(&emu, 0, 15) && !lit(&emu, 1, 15));
+        assert_eq!(emu.registers[0xF], 0);
+
+        emu.execute(decode(0x00FB));
+        assert!(!lit(&emu, 0, 0) && lit(&emu, 4, 0) && lit(&emu, 19, 0));
+        emu.execute(decode(0x00C2));
+        assert!(!lit(&emu, 4, 0) && lit(&emu, 4, 2));
+        emu.execute(decode(0x00FC));
+        assert!(lit(&emu, 0, 2) && lit(&emu, 15, 2) && !lit(&emu, 16, 2));
+
+        // Leaving high resolution clears the screen, 00FD stays put
+        emu.execute(decode(0x00FE));
+        assert_eq!(emu.display_width(), CHIP8_WIDTH);
+        assert!(!emu.get_display().contains(&true));
+        emu.pc = 0x210;
+        emu.execute(decode(0x00FD));
+        assert_eq!(emu.pc, 0x20E);
+    }
+
+    #[test]
+    fn every_quirk_flag_changes_its_instruction() {
+        let emu_with = |quirks: Quirks| {
+            let mut emu = Chip8::new();
+            emu.quirks = quirks;
+            emu
+        };
+        let on = |quirk: fn(&mut Quirks)| {
+            let mut quirks = Quirks::default();
+            quirk(&mut quirks);
+            emu_with(quirks)
+        };
+        let off = || emu_with(Quirks::default());
+
+        // 8XY6 shifts VY or VX
+        for (mut emu, shifted) in [(on(|q| q.shift_uses_vy = true), 1), (off(), 0)] {
+            emu.registers[1] = 0b11;
+            emu.execute(decode(0x8016));
+            assert_eq!(emu.registers[0], shifted);
+        }
+
+        // FX55 moves I past the stored registers or leaves it
+        for (mut emu, index) in [
+            (on(|q| q.load_store_increments_index = true), 0x303),
+            (off(), 0x300),
+        ] {
+            emu.index = 0x300;
+            emu.execute(decode(0xF255));
+            assert_eq!(emu.index, index);
+        }
+
+        // BNNN adds VX or V0
+        for (mut emu, pc) in [(on(|q| q.jump_uses_vx = true), 0x254), (off(), 0x244)] {
+            emu.registers[0] = 0x10;
+            emu.registers[2] = 0x20;
+            emu.execute(decode(0xB234));
+            assert_eq!(emu.pc, pc);
+        }
+
+        // 8XY1 clears VF or leaves it
+        for (mut emu, vf) in [(on(|q| q.logic_resets_vf = true), 0), (off(), 5)] {
+            emu.registers[0xF] = 5;
+            emu.execute(decode(0x8011));
+            assert_eq!(emu.registers[0xF], vf);
+        }
+
+        // A sprite at the right edge is cut off or wraps to the left one
+        for (mut emu, wrapped) in [(on(|q| q.clip_sprites = true), false), (off(), true)] {
+            emu.memory[0x300] = 0xFF;
+            emu.index = 0x300;
+            emu.registers[0] = 60;
+            emu.execute(decode(0xD011));
+            assert!(emu.get_display()[63]);
+            assert_eq!(emu.get_display()[0], wrapped);
+        }
+
+        // DRW ends the frame or the frame carries on
+        for (mut emu, waits) in [(on(|q| q.display_wait = true), true), (off(), false)] {
+            let program = [0xD0, 0x01, 0xD0, 0x01, 0x12, 0x04];
+            emu.memory[START_ADDRESS..START_ADDRESS + program.len()].copy_from_slice(&program);
+            assert_eq!(emu.run_cycles(10) == 1, waits);
+        }
+
+        // FX0A finishes on the press or only once the key is released
+        for (mut emu, waits) in [(on(|q| q.wait_key_release = true), true), (off(), false)] {
+            emu.pc = 0x202;
+            emu.keypad[5] = true;
+            emu.execute(decode(0xF00A));
+            assert_eq!(emu.pc == 0x200, waits);
+            if waits {
+                emu.pc = 0x202;
+                emu.keypad[5] = false;
+                emu.execute(decode(0xF00A));
+                assert_eq!(emu.pc, 0x202);
+            }
+            assert_eq!(emu.registers[0], 5);
+        }
+    }
+
+    #[test]
+    fn bcd_edges() {
+        assert_eq!(bcd(0), [0, 0, 0]);
+        assert_eq!(bcd(9), [0, 0, 9]);
+        assert_eq!(bcd(10), [0, 1, 0]);
+        assert_eq!(bcd(99), [0, 9, 9]);
+        assert_eq!(bcd(100), [1, 0, 0]);
+        assert_eq!(bcd(255), [2, 5, 5]);
+    }
+
+    #[test]
+    fn bcd_round_trips_every_value() {
+        for value in 0..=u8::MAX {
+            let [hundreds, tens, ones] = bcd(value);
+            assert!(tens < 10 && ones < 10, "{} gave {:?}", value, bcd(value));
+            assert_eq!(
+                hundreds as u16 * 100 + tens as u16 * 10 + ones as u16,
+                value as u16
+            );
+        }
+    }
+
+    #[test]
+    fn fx55_wraps_past_end_of_memory() {
+        let mut emu = Chip8::new();
+        emu.registers[..3].copy_from_slice(&[1, 2, 3]);
+        emu.index = 0xFFE;
+        emu.execute(decode(0xF255));
+        assert_eq!(emu.memory[0xFFE..], [1, 2]);
+        assert_eq!(emu.memory[0], 3);
+        assert!(emu.halted.is_none());
+    }
+
+    #[test]
+    fn memory_fault_halts_on_the_instruction() {
+        let mut emu = Chip8::new();
+        emu.on_memory_fault = OnMemoryFault::Fault;
+        emu.memory[0x200..0x202].copy_from_slice(&[0xF0, 0x65]);
+        emu.index = 0x1000;
+        emu.step();
+        assert!(matches!(
+            emu.halted,
+            Some(Chip8Error::MemoryFault {
+                address: 0x1000,
+                pc: 0x200
+            })
+        ));
+        assert_eq!(emu.pc, 0x200);
+    }
+
+    #[test]
+    fn fetch_wraps_pc_at_end_of_memory() {
+        let mut emu = Chip8::new();
+        emu.pc = 0xFFF;
+        emu.memory[0xFFF] = 0x12;
+        emu.memory[0] = 0x34;
+        assert_eq!(emu.fetch(), 0x1234);
+        assert_eq!(emu.pc, 0x1001);
+        emu.on_memory_fault = OnMemoryFault::Fault;
+        emu.fetch();
+        assert!(matches!(
+            emu.halted,
+            Some(Chip8Error::MemoryFault { pc: 0x1001, .. })
+        ));
+    }
+
+    #[test]
+    fn ret_on_empty_stack_halts() {
+        let mut emu = Chip8::new();
+        emu.memory[0x200..0x202].copy_from_slice(&[0x00, 0xEE]);
+        emu.step();
+        assert!(matches!(
+            emu.halted,
+            Some(Chip8Error::StackUnderflow { pc: 0x200 })
+        ));
+        assert_eq!((emu.pc, emu.sp), (0x200, 0));
+    }
+
+    #[test]
+    fn call_past_stack_depth_halts_or_grows() {
+        // CALL 0x200, calling itself forever
+        let mut emu = Chip8::with_stack_depth(2);
+        emu.memory[0x200..0x202].copy_from_slice(&[0x22, 0x00]);
+        for _ in 0..3 {
+            emu.step();
+        }
+        assert!(matches!(
+            emu.halted,
+            Some(Chip8Error::StackOverflow { depth: 2, .. })
+        ));
+
+        let mut emu = Chip8::with_stack_depth(2);
+        emu.grow_stack = true;
+        emu.memory[0x200..0x202].copy_from_slice(&[0x22, 0x00]);
+        for _ in 0..3 {
+            emu.step();
         }
+        assert!(emu.halted.is_none());
+        assert_eq!((emu.sp, emu.stack.len()), (3, 3));
+    }
+
+    #[test]
+    fn video_generation_only_moves_when_the_screen_does() {
+        let mut emu = Chip8::new();
+        let start = emu.video_generation();
+        emu.execute(decode(0x7005));
+        assert_eq!(emu.video_generation(), start);
+        // DRW V0, V0, 5 with I on the font's "0"
+        emu.index = FONTSET_START_ADDRESS as u32;
+        emu.execute(decode(0xD005));
+        assert_ne!(emu.video_generation(), start);
+    }
+
+    #[test]
+    fn display_rows_follow_the_screen_width() {
+        let mut emu = Chip8::new();
+        emu.video.flip(0, 0);
+        emu.video.flip(63, 1);
+        assert_eq!(emu.display_rows()[..2], [1 << 63, 1]);
+
+        // In high resolution row 1 starts 128 pixels in, not 64
+        emu.execute(decode(0x00FF));
+        emu.video.flip(0, 1);
+        emu.video.flip(64, 0);
+        assert_eq!(emu.display_rows()[..2], [0, 1 << 63]);
+        assert_eq!(emu.display_rows_wide()[0], 1 << 63);
+    }
+
+    #[test]
+    fn fx33_stores_digits_at_index() {
+        let mut emu = Chip8::new();
+        emu.registers[0xA] = 255;
+        emu.index = 0x300;
+        emu.execute(decode(0xFA33));
+        assert_eq!(&emu.memory[0x300..0x303], &[2, 5, 5]);
+    }
+
+    #[test]
+    fn xo_chip_planes_draw_and_clear_on_their_own() {
+        let mut emu = Chip8::new();
+        // One row for the first plane, then one for the second
+        emu.memory[0x300..0x302].copy_from_slice(&[0x80, 0xC0]);
+        emu.index = 0x300;
+        emu.execute(decode(0xF301));
+        emu.execute(decode(0xD001));
+        assert_eq!(emu.display_planes()[..3], [3, 2, 0]);
+        assert_eq!(emu.registers[0xF], 0);
+
+        // A saved state brings the second plane back
+        let state = emu.save_state();
+        emu.execute(decode(0xF201));
+        emu.execute(decode(0x00E0));
+        assert_eq!(emu.display_planes()[..3], [1, 0, 0]);
+        emu.load_state(&state);
+        assert_eq!(emu.display_planes()[..3], [3, 2, 0]);
+        assert_eq!(decode(0xF301).to_string(), "PLANE 3");
+    }
+
+    #[test]
+    fn xo_chip_long_index_register_ranges_and_sound() {
+        let mut emu = Chip8::new();
+        // SE V0, 0 skips all of I := long 0x1234, then the second one runs
+        emu.load_rom_bytes(&[0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0xF0, 0x00, 0x0E, 0x00])
+            .unwrap();
+        emu.run_cycles(1);
+        assert_eq!(emu.pc, 0x206);
+        emu.run_cycles(1);
+        assert_eq!((emu.pc, emu.index), (0x20A, 0xE00));
+
+        // V1-V3 in order, then back into V3-V1 backwards, I stays put
+        emu.registers[1..4].copy_from_slice(&[1, 2, 3]);
+        emu.execute(decode(0x5132));
+        assert_eq!(emu.memory[0xE00..0xE03], [1, 2, 3]);
+        assert_eq!(emu.index, 0xE00);
+        emu.execute(decode(0x5313));
+        assert_eq!(emu.registers[1..4], [3, 2, 1]);
+
+        emu.execute(decode(0xF002));
+        assert_eq!(emu.audio_pattern.unwrap()[..3], [1, 2, 3]);
+        emu.execute(decode(0xF13A));
+        assert_eq!(emu.pitch, 3);
+
+        emu.video.flip(0, 3);
+        emu.execute(decode(0x00D2));
+        assert!(emu.get_display()[CHIP8_WIDTH]);
+    }
+
+    #[test]
+    fn subn_wraps_and_sets_vf_after_the_result() {
+        let mut emu = Chip8::new();
+        // VY < VX borrows instead of panicking
+        emu.registers[0] = 5;
+        emu.registers[1] = 3;
+        emu.execute(decode(0x8017));
+        assert_eq!((emu.registers[0], emu.registers[0xF]), (0xFE, 0));
+
+        // Equal values don't borrow
+        emu.registers[0] = 3;
+        emu.execute(decode(0x8017));
+        assert_eq!((emu.registers[0], emu.registers[0xF]), (0, 1));
+
+        // With VF as VX the flag wins
+        emu.registers[0xF] = 1;
+        emu.registers[1] = 4;
+        emu.execute(decode(0x8F17));
+        assert_eq!(emu.registers[0xF], 1);
+    }
+
+    #[test]
+    fn key_skips_only_look_at_the_low_nibble() {
+        let mut emu = Chip8::new();
+        emu.chip8x = true;
+        emu.keypad[3] = true;
+        emu.keypad2[3] = true;
+        emu.registers[0] = 0xF3;
+        for opcode in [0xE09E, 0xE0F2] {
+            emu.pc = 0x200;
+            emu.execute(decode_chip8x(opcode));
+            assert_eq!(emu.pc, 0x202, "{:04x}", opcode);
+        }
+        for opcode in [0xE0A1, 0xE0F5] {
+            emu.pc = 0x200;
+            emu.execute(decode_chip8x(opcode));
+            assert_eq!(emu.pc, 0x200, "{:04x}", opcode);
+        }
+    }
+
+    #[test]
+    fn restart_keeps_the_rom() {
+        let mut emu = Chip8::new();
+        // V0 = 0x42, then overwrite the first instruction
+        emu.load_rom_bytes(&[0x60, 0x42, 0xA2, 0x00, 0xF0, 0x55])
+            .unwrap();
+        let hash = emu.rom_hash.clone();
+        emu.run_cycles(3);
+        assert_eq!(emu.memory[0x200], 0x42);
+
+        emu.restart();
+        assert_eq!(emu.pc, START_ADDRESS as u16);
+        assert_eq!(emu.registers[0], 0);
+        assert_eq!(&emu.memory[0x200..0x202], &[0x60, 0x42]);
+        assert_eq!(emu.rom_hash, hash);
+    }
+
+    #[test]
+    fn megachip_shows_palette_sprites_on_cls() {
+        let mut emu = Chip8::new();
+        emu.execute(decode_megachip(0x0011));
+        assert_eq!((emu.display_width(), emu.display_height()), (256, 192));
+
+        // Two ARGB colours for palette entries 1 and 2, then a 2x1 sprite using both
+        emu.memory[0x300..0x308].copy_from_slice(&[0xFF, 0x10, 0x20, 0x30, 0xFF, 0x40, 0x50, 0x60]);
+        emu.memory[0x308..0x30A].copy_from_slice(&[1, 2]);
+        emu.index = 0x300;
+        emu.execute(decode_megachip(0x0202));
+        emu.execute(decode_megachip(0x0302));
+        emu.execute(decode_megachip(0x0401));
+        emu.index = 0x308;
+        emu.registers[0] = 10;
+        emu.registers[1] = 5;
+        emu.execute(decode(0xD010));
+
+        let at = 10 + 5 * 256;
+        assert_eq!(emu.megachip.frame()[at], [0, 0, 0]);
+        emu.execute(decode(0x00E0));
+        assert_eq!(
+            emu.megachip.frame()[at..at + 2],
+            [[0x10, 0x20, 0x30], [0x40, 0x50, 0x60]]
+        );
+        assert!(emu.get_display()[at]);
+    }
+
+    #[test]
+    fn megachip_rom_grows_memory_for_ldhi() {
+        let mut rom = vec![0; 0x3000];
+        // MEGAON, SE V0, 0 skipping all of an LDHI and then LDHI I, 0x123456
+        rom[..14].copy_from_slice(&[
+            0x00, 0x11, 0x30, 0x00, 0x01, 0x00, 0x30, 0x00, 0x01, 0x12, 0x34, 0x56, 0x00, 0x00,
+        ]);
+        let mut emu = Chip8::new();
+        emu.load_rom_bytes(&rom).unwrap();
+        assert_eq!(emu.memory.len(), 0x4000);
+
+        emu.run_cycles(2);
+        assert_eq!(emu.pc, 0x208);
+        emu.run_cycles(1);
+        assert_eq!((emu.pc, emu.index), (0x20C, 0x123456));
+    }
+
+    #[test]
+    fn megachip_opcodes_are_unknown_in_other_roms() {
+        let mut emu = Chip8::new();
+        emu.on_unknown_opcode = OnUnknownOpcode::Halt;
+        // SE V0, 0 skips two bytes of 0x01xx data, then a stray MEGAON
+        emu.load_rom_bytes(&[0x30, 0x00, 0x01, 0x00, 0x00, 0x11])
+            .unwrap();
+        emu.run_cycles(1);
+        assert_eq!(emu.pc, 0x204);
+        emu.run_cycles(1);
+        assert!(matches!(emu.halted, Some(Chip8Error::InvalidOpcode(_))));
+        assert!(!emu.megachip.enabled);
+    }
+
+    #[test]
+    fn logged_opcodes_are_kept_for_the_frontend() {
+        let mut emu = Chip8::new();
+        emu.on_unknown_opcode = OnUnknownOpcode::Log;
+        emu.load_rom_bytes(&[0xFF, 0xFF, 0x60, 0x01]).unwrap();
+        emu.run_cycles(2);
+        assert_eq!(emu.registers[0], 1);
+        assert_eq!(
+            emu.skipped_opcodes,
+            [UnknownOpcode {
+                opcode: 0xFFFF,
+                pc: 0x200
+            }]
+        );
+    }
+
+    #[test]
+    fn chip8x_colours_zones_and_reads_second_keypad() {
+        let mut emu = Chip8::new();
+        emu.chip8x = true;
+        emu.video.pixels_mut()[0] = true;
+        emu.video.pixels_mut()[8] = true;
+        // V0 = 8, V1 = 0 and V2 = 4, green for the zone right of the top left one
+        emu.registers[..3].copy_from_slice(&[8, 0, 4]);
+        emu.execute(decode_chip8x(0xB021));
+
+        let colors = emu.display_colors().unwrap();
+        assert_eq!((colors[0], colors[8]), ([0xFF, 0, 0], [0, 0xFF, 0]));
+        assert_eq!(colors[1], [0, 0, 0x80]);
+
+        emu.keypad2[4] = true;
+        emu.execute(decode_chip8x(0xE2F2));
+        assert_eq!(emu.pc, 0x202);
+        assert_eq!(decode_chip8x(0x5121).to_string(), "ADDN V1, V2");
+    }
+
+    #[test]
+    fn eti660_roms_start_at_0x600_on_a_taller_screen() {
+        let mut emu = Chip8::with_start_address(0x600);
+        emu.set_geometry(Geometry::Eti660);
+        // LD V1, 40 then DRW V0, V1, 1 with I on the font's top row of 0
+        emu.load_rom_bytes(&[0x61, 40, 0xD0, 0x11]).unwrap();
+        assert_eq!(emu.memory[0x600], 0x61);
+        emu.index = FONTSET_START_ADDRESS as u32;
+
+        emu.run_cycles(2);
+        assert_eq!(emu.pc, 0x604);
+        assert_eq!(emu.display_height(), 48);
+        assert!(emu.get_display()[40 * CHIP8_WIDTH]);
+    }
+
+    #[test]
+    fn second_player_shares_the_keypad_outside_chip8x() {
+        let mut emu = Chip8::new();
+        emu.keypress2(5, true);
+        assert!(emu.keypad[5]);
+        emu.chip8x = true;
+        emu.keypress2(6, true);
+        assert!(emu.keypad2[6] && !emu.keypad[6]);
+    }
+
+    #[test]
+    fn a_one_frame_sound_timer_still_beeps() {
+        let mut emu = Chip8::new();
+        emu.stimer = 1;
+        emu.tick_timers();
+        emu.tick_timers();
+
+        let mut out = [0.0; 20];
+        emu.render_audio(&mut out, 600);
+        assert!(out[..10].iter().all(|&s| s != 0.0));
+        assert!(out[10..].iter().all(|&s| s == 0.0));
     }
 }
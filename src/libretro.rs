@@ -0,0 +1,524 @@
+// A libretro core, so RetroArch and other libretro frontends can run CHIP-8 games with their
+// own shaders, netplay, rewind and save states. Build it with
+//
+//     cargo build --release --no-default-features --features libretro
+//
+// and load target/release/libchip8_emu.so (chip8_emu.dll, libchip8_emu.dylib) as a core.
+//
+// The frontend owns the timing and calls `retro_run` once per 60 Hz frame. The joypad is
+// mapped like the SDL gamepad, and a keyboard works with the default 1234/QWER/ASDF/ZXCV
+// layout. Save states are the JSON `SaveState`, zero padded to SERIALIZED_SIZE because
+// libretro wants the same size every time.
+//
+// Only the parts of libretro.h used here are declared below.
+
+use std::ffi::{CStr, CString, c_char, c_uint, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+use std::sync::Mutex;
+
+use crate::crash_dump::panic_message;
+use crate::error::Chip8Error;
+use crate::keymap::{self, KeyMap};
+use crate::keypad::KEY_COUNT;
+use crate::megachip::{MEGA_HEIGHT, MEGA_WIDTH};
+use crate::palette::{Palette, Rgb};
+use crate::rom::RomFormat;
+use crate::rom_database::RomInfo;
+use crate::state::SaveState;
+use crate::unknown_opcode::OnUnknownOpcode;
+use crate::{CHIP8_HEIGHT, CHIP8_WIDTH, Chip8};
+
+const API_VERSION: c_uint = 1;
+
+const DEVICE_JOYPAD: c_uint = 1;
+const DEVICE_KEYBOARD: c_uint = 3;
+
+const ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+const ENVIRONMENT_GET_LOG_INTERFACE: c_uint = 27;
+const LOG_INFO: c_uint = 1;
+const LOG_ERROR: c_uint = 3;
+const PIXEL_FORMAT_XRGB8888: c_uint = 1;
+
+const REGION_NTSC: c_uint = 0;
+const MEMORY_SYSTEM_RAM: c_uint = 2;
+
+// RETRO_DEVICE_ID_JOYPAD_* and the keypad key each one presses
+const JOYPAD_MAPPING: [(c_uint, usize); 8] = [
+    (4, 0x2), // Up
+    (5, 0x8), // Down
+    (6, 0x4), // Left
+    (7, 0x6), // Right
+    (0, 0x5), // B, the bottom face button
+    (8, 0x0), // A, right
+    (1, 0xA), // Y, left
+    (9, 0xB), // X, top
+];
+
+const SAMPLE_RATE: u32 = 44100;
+const SAMPLES_PER_FRAME: usize = SAMPLE_RATE as usize / 60;
+const DEFAULT_TICKS_PER_FRAME: usize = 10;
+// Comfortably more than the largest state, a hires screen and a deep stack included
+const SERIALIZED_SIZE: usize = 32 * 1024;
+
+#[repr(C)]
+pub struct SystemInfo {
+    library_name: *const c_char,
+    library_version: *const c_char,
+    valid_extensions: *const c_char,
+    need_fullpath: bool,
+    block_extract: bool,
+}
+
+#[repr(C)]
+pub struct GameGeometry {
+    base_width: c_uint,
+    base_height: c_uint,
+    max_width: c_uint,
+    max_height: c_uint,
+    aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct SystemTiming {
+    fps: f64,
+    sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct SystemAvInfo {
+    geometry: GameGeometry,
+    timing: SystemTiming,
+}
+
+#[repr(C)]
+pub struct GameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+type EnvironmentFn = unsafe extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+type VideoRefreshFn =
+    unsafe extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+type AudioSampleFn = unsafe extern "C" fn(left: i16, right: i16);
+type AudioSampleBatchFn = unsafe extern "C" fn(data: *const i16, frames: usize) -> usize;
+type InputPollFn = unsafe extern "C" fn();
+type InputStateFn =
+    unsafe extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+type LogFn = unsafe extern "C" fn(level: c_uint, format: *const c_char, ...);
+
+#[repr(C)]
+struct LogCallback {
+    log: Option<LogFn>,
+}
+
+// Set by the frontend before and after `retro_init`, so they live apart from the core
+#[derive(Clone, Copy)]
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+    // Where messages go, the frontend's log rather than a terminal it may not have
+    log: Option<LogFn>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+    log: None,
+});
+
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+struct Core {
+    emu: Box<Chip8>,
+    rom: Vec<u8>,
+    format: RomFormat,
+    ticks_per_frame: usize,
+    palette: Palette,
+    keyboard: Vec<(c_uint, usize)>,
+    // Stops running once the ROM halts, the last frame stays up
+    halted: bool,
+    frame: Vec<u32>,
+    samples: Vec<f32>,
+    audio: Vec<i16>,
+}
+
+// Messages are dropped when the frontend has no log
+fn log(level: c_uint, message: &str) {
+    let Some(log) = CALLBACKS.lock().unwrap().log else {
+        return;
+    };
+    let Ok(message) = CString::new(message) else {
+        return;
+    };
+    // Safety: a printf-style callback, given a format with one string and that string
+    unsafe { log(level, c"%s\n".as_ptr(), message.as_ptr()) };
+}
+
+fn xrgb([r, g, b]: Rgb) -> u32 {
+    (r as u32) << 16 | (g as u32) << 8 | b as u32
+}
+
+// RETROK_* codes are ASCII for letters and digits, other keymap keys aren't reachable
+fn keyboard_mapping(keymap: &KeyMap) -> Vec<(c_uint, usize)> {
+    keymap::bindings(&keymap.keys)
+        .filter_map(|(key, name)| match name.as_bytes() {
+            [c] if c.is_ascii_alphanumeric() => Some((c.to_ascii_lowercase() as c_uint, key)),
+            _ => None,
+        })
+        .collect()
+}
+
+impl Core {
+    fn new() -> Self {
+        let mut emu = Box::new(Chip8::new());
+        // A panic can't unwind into the frontend, halt on bad opcodes instead
+        emu.on_unknown_opcode = OnUnknownOpcode::Halt;
+        // The frontend rewinds through save states
+        emu.rewind = None;
+        let keyboard = keyboard_mapping(&KeyMap::default());
+
+        Self {
+            emu,
+            rom: Vec::new(),
+            format: RomFormat::Binary,
+            ticks_per_frame: DEFAULT_TICKS_PER_FRAME,
+            palette: Palette::default(),
+            keyboard,
+            halted: false,
+            frame: Vec::with_capacity(MEGA_WIDTH * MEGA_HEIGHT),
+            samples: vec![0.0; SAMPLES_PER_FRAME],
+            audio: vec![0; SAMPLES_PER_FRAME * 2],
+        }
+    }
+
+    fn load(&mut self) -> bool {
+        self.emu.reset();
+        self.halted = false;
+        match self.emu.load_rom_bytes_with_format(&self.rom, self.format) {
+            Ok(report) => {
+                log(LOG_INFO, &report.to_string());
+                self.ticks_per_frame = report.tickrate().unwrap_or(DEFAULT_TICKS_PER_FRAME);
+                self.palette = report
+                    .rom_info
+                    .and_then(RomInfo::palette)
+                    .unwrap_or_default();
+                true
+            }
+            Err(e) => {
+                log(LOG_ERROR, &format!("Failed to load ROM: {}", e));
+                false
+            }
+        }
+    }
+
+    fn read_keypad(&self, input_state: InputStateFn) -> [bool; KEY_COUNT] {
+        let mut keys = [false; KEY_COUNT];
+        // Safety: the frontend's input callback, called as libretro.h describes
+        let pressed = |device, id| unsafe { input_state(0, device, 0, id) != 0 };
+        for &(id, key) in &JOYPAD_MAPPING {
+            keys[key] |= pressed(DEVICE_JOYPAD, id);
+        }
+        for &(id, key) in &self.keyboard {
+            keys[key] |= pressed(DEVICE_KEYBOARD, id);
+        }
+        keys
+    }
+
+    fn run(&mut self, callbacks: &Callbacks) {
+        if !self.halted {
+            if let Some(input_state) = callbacks.input_state {
+                self.emu.keypad = self.read_keypad(input_state);
+            }
+            // A panic mustn't unwind into the frontend, it halts the core like a bad opcode
+            let ticks = self.ticks_per_frame;
+            if let Err(payload) =
+                panic::catch_unwind(AssertUnwindSafe(|| self.emu.run_cycles(ticks)))
+            {
+                self.emu.halted = Some(Chip8Error::Crashed(panic_message(&*payload).to_string()));
+            }
+            if let Some(error) = self.emu.halted.take() {
+                log(LOG_ERROR, &format!("Emulation halted: {}", error));
+                self.halted = true;
+            } else {
+                self.emu.tick_timers();
+            }
+        }
+
+        self.frame.clear();
+        if let Some(colors) = self.emu.display_colors() {
+            self.frame.extend(colors.iter().map(|&pixel| xrgb(pixel)));
+        } else {
+            self.frame.extend(
+                self.emu
+                    .display_planes()
+                    .into_iter()
+                    .map(|planes| xrgb(self.palette.color(planes))),
+            );
+        }
+        if let Some(video_refresh) = callbacks.video_refresh {
+            let width = self.emu.display_width();
+            let height = self.emu.display_height();
+            // Safety: `frame` holds `height` rows of `width` XRGB8888 pixels
+            unsafe {
+                video_refresh(
+                    self.frame.as_ptr().cast(),
+                    width as c_uint,
+                    height as c_uint,
+                    width * size_of::<u32>(),
+                )
+            };
+        }
+
+        // A halted core ticks no frames, so this runs out into silence
+        self.emu.render_audio(&mut self.samples, SAMPLE_RATE);
+        for (stereo, &sample) in self.audio.chunks_exact_mut(2).zip(&self.samples) {
+            stereo.fill((sample * i16::MAX as f32) as i16);
+        }
+        if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+            // Safety: `audio` holds SAMPLES_PER_FRAME interleaved stereo frames
+            unsafe { audio_sample_batch(self.audio.as_ptr(), SAMPLES_PER_FRAME) };
+        }
+    }
+}
+
+fn with_core<T>(default: T, f: impl FnOnce(&mut Core) -> T) -> T {
+    match CORE.lock().unwrap().as_mut() {
+        Some(core) => f(core),
+        None => default,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_api_version() -> c_uint {
+    API_VERSION
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    let mut log = LogCallback { log: None };
+    // Safety: GET_LOG_INTERFACE fills in a retro_log_callback
+    let has_log = unsafe { callback(ENVIRONMENT_GET_LOG_INTERFACE, (&raw mut log).cast()) };
+
+    let mut callbacks = CALLBACKS.lock().unwrap();
+    callbacks.environment = Some(callback);
+    callbacks.log = log.log.filter(|_| has_log);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    CALLBACKS.lock().unwrap().video_refresh = Some(callback);
+}
+
+// Sound goes through the batch callback
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    CALLBACKS.lock().unwrap().audio_sample_batch = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    CALLBACKS.lock().unwrap().input_poll = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    CALLBACKS.lock().unwrap().input_state = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_init() {
+    *CORE.lock().unwrap() = Some(Core::new());
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_deinit() {
+    *CORE.lock().unwrap() = None;
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    let system_info = SystemInfo {
+        library_name: c"CHIP-8 (chip8_emu)".as_ptr(),
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast(),
+        valid_extensions: c"ch8|c8|sc8|xo8|hex|gif".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+    // Safety: the frontend passes a struct to fill in
+    unsafe { info.write(system_info) };
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    let av_info = SystemAvInfo {
+        geometry: GameGeometry {
+            base_width: CHIP8_WIDTH as c_uint,
+            base_height: CHIP8_HEIGHT as c_uint,
+            max_width: MEGA_WIDTH as c_uint,
+            max_height: MEGA_HEIGHT as c_uint,
+            aspect_ratio: 2.0,
+        },
+        timing: SystemTiming {
+            fps: 60.0,
+            sample_rate: SAMPLE_RATE as f64,
+        },
+    };
+    // Safety: the frontend passes a struct to fill in
+    unsafe { info.write(av_info) };
+}
+
+// There is only the one kind of controller
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_reset() {
+    with_core((), |core| {
+        core.load();
+    });
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_run() {
+    let callbacks = *CALLBACKS.lock().unwrap();
+    if let Some(input_poll) = callbacks.input_poll {
+        // Safety: the frontend's input callback, called as libretro.h describes
+        unsafe { input_poll() };
+    }
+    with_core((), |core| core.run(&callbacks));
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_serialize_size() -> usize {
+    SERIALIZED_SIZE
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    with_core(false, |core| {
+        if !core.emu.supports_save_states() {
+            return false;
+        }
+        let json = serde_json::to_vec(&core.emu.save_state().to_json()).unwrap_or_default();
+        if json.is_empty() || json.len() > size {
+            return false;
+        }
+        // Safety: the frontend hands over a buffer of `size` bytes
+        let out = unsafe { slice::from_raw_parts_mut(data.cast::<u8>(), size) };
+        out[..json.len()].copy_from_slice(&json);
+        out[json.len()..].fill(0);
+        true
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    // Safety: the frontend hands over `size` bytes it got from `retro_serialize`
+    let data = unsafe { slice::from_raw_parts(data.cast::<u8>(), size) };
+    let json = &data[..data.iter().position(|&byte| byte == 0).unwrap_or(size)];
+    let Ok(state) = serde_json::from_slice::<serde_json::Value>(json)
+        .map_err(|e| log(LOG_ERROR, &format!("Bad save state: {}", e)))
+    else {
+        return false;
+    };
+    with_core(false, |core| match SaveState::from_json(&state) {
+        Ok(state) => {
+            core.emu.load_state(&state);
+            core.halted = false;
+            true
+        }
+        Err(e) => {
+            log(LOG_ERROR, &format!("Bad save state: {}", e));
+            false
+        }
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    // Safety: the frontend passes the game it loaded, or null when there is none
+    let Some(game) = (unsafe { game.as_ref() }) else {
+        return false;
+    };
+    if game.data.is_null() {
+        return false;
+    }
+
+    let environment = CALLBACKS.lock().unwrap().environment;
+    if let Some(environment) = environment {
+        let mut format = PIXEL_FORMAT_XRGB8888;
+        // Safety: SET_PIXEL_FORMAT takes a pointer to the format
+        if !unsafe { environment(ENVIRONMENT_SET_PIXEL_FORMAT, (&raw mut format).cast()) } {
+            log(LOG_ERROR, "Frontend doesn't support XRGB8888");
+            return false;
+        }
+    }
+
+    with_core(false, |core| {
+        // Safety: `data` points at `size` bytes and `path` is a C string when given
+        core.rom = unsafe { slice::from_raw_parts(game.data.cast::<u8>(), game.size) }.to_vec();
+        core.format = if game.path.is_null() {
+            RomFormat::Binary
+        } else {
+            RomFormat::from_path(&unsafe { CStr::from_ptr(game.path) }.to_string_lossy())
+        };
+        core.load()
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const GameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_unload_game() {
+    with_core((), |core| {
+        core.emu.reset();
+        core.rom.clear();
+    });
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_region() -> c_uint {
+    REGION_NTSC
+}
+
+// The 4 KiB of CHIP-8 memory, for cheats and achievements
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+    with_core(std::ptr::null_mut(), |core| match id {
+        MEMORY_SYSTEM_RAM => core.emu.memory.as_mut_ptr().cast(),
+        _ => std::ptr::null_mut(),
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> usize {
+    with_core(0, |core| match id {
+        MEMORY_SYSTEM_RAM => core.emu.memory.len(),
+        _ => 0,
+    })
+}
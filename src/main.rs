@@ -1,7 +1,1053 @@
-use chip8_emu::Chip8;
+use chip8_emu::batch;
+use chip8_emu::bench;
+use chip8_emu::cfg;
+use chip8_emu::chrome_trace::TraceRecorder;
+use chip8_emu::compare::{self, Profile};
+use chip8_emu::debugger::WatchKind;
+use chip8_emu::disassembler;
+use chip8_emu::drivers::display_driver::{DisplayOptions, MAX_SCALE, SCALE_FACTOR};
+use chip8_emu::drivers::shm_driver::SharedFramebuffer;
+use chip8_emu::drivers::vnc_driver::{self, VncServer};
+use chip8_emu::error::Chip8Error;
+use chip8_emu::event_log::EventLog;
+use chip8_emu::frontend;
+use chip8_emu::geometry::{self, Geometry};
+use chip8_emu::gif_recorder::{DEFAULT_GIF_SCALE, GifRecorder};
+use chip8_emu::instruction_trace::InstructionTrace;
+use chip8_emu::keymap::{self, KeyMap};
+use chip8_emu::kiosk::{DEFAULT_IDLE_TIMEOUT, DEFAULT_SECONDS_PER_ROM, DemoInput, Kiosk};
+use chip8_emu::memory_fault::OnMemoryFault;
+use chip8_emu::metrics::Metrics;
+use chip8_emu::movie::{Movie, MovieSession};
+use chip8_emu::pacing::FRAMES_PER_SECOND;
+use chip8_emu::palette::{self, Palette, Rgb};
+use chip8_emu::persistent_ram::{self, PersistentRam};
+use chip8_emu::playlist::Playlist;
+use chip8_emu::quirks::{self, Quirks};
+use chip8_emu::recent::{self, RecentRoms};
+use chip8_emu::rewind::Rewind;
+use chip8_emu::rom::RomFormat;
+use chip8_emu::rom_quirks::{self, RomQuirks};
+use chip8_emu::run_loop::{self, FrontendOptions};
+use chip8_emu::settings::{self, Settings};
+use chip8_emu::slow_draw::{self, SlowDraw};
+use chip8_emu::soak;
+use chip8_emu::state::SaveState;
+use chip8_emu::state_diff;
+use chip8_emu::test_suite;
+use chip8_emu::timing::{self, CycleTable};
+use chip8_emu::unknown_opcode::OnUnknownOpcode;
+use chip8_emu::watchdog::{self, Watchdog};
+use chip8_emu::{Chip8, START_ADDRESS};
+use clap::{Args, Parser, Subcommand};
+use std::fmt::Display;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const DEFAULT_TICKS_PER_FRAME: usize = 10;
+
+// `chip8_emu game.ch8` is short for `chip8_emu run game.ch8`
+#[derive(Parser)]
+#[command(
+    version,
+    about = "A CHIP-8 emulator",
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    run: RunArgs,
+
+    /// Write machine-readable session events to FILE, `-` for stdout
+    #[arg(long, global = true, value_name = "FILE", num_args = 0..=1, default_missing_value = "-")]
+    json_log: Option<String>,
+
+    /// Serve Prometheus metrics on http://ADDR/metrics
+    #[arg(long, global = true, value_name = "ADDR", num_args = 0..=1, default_missing_value = "127.0.0.1:9188")]
+    metrics: Option<String>,
+
+    /// Instructions executed per frame, 10 unless the ROM database or a cartridge says otherwise
+    #[arg(long, global = true)]
+    ticks: Option<usize>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Play ROMs in a window
+    Run(RunArgs),
+    /// Run without a window and serve the screen to VNC clients
+    Vnc {
+        #[arg(default_value = "roms/Pong.ch8")]
+        rom: String,
+        #[arg(long, default_value = vnc_driver::DEFAULT_ADDR)]
+        addr: String,
+        #[command(flatten)]
+        emu: EmuArgs,
+    },
+    /// Print an annotated listing of a ROM
+    Disasm {
+        rom: String,
+        /// List the subroutines each routine calls instead
+        #[arg(long)]
+        calls: bool,
+        /// bin, hex or octo, guessed from the extension by default
+        #[arg(long)]
+        format: Option<RomFormat>,
+        /// Where the ROM is loaded, 0x200 by default
+        #[arg(long, value_name = "ADDR", value_parser = hex_address)]
+        start_address: Option<u16>,
+    },
+    /// Write a ROM's control-flow graph as Graphviz DOT
+    Cfg {
+        rom: String,
+        #[arg(short, value_name = "FILE")]
+        output: Option<String>,
+        /// Refine the graph with the addresses executed during N frames
+        #[arg(long = "run", value_name = "N", default_value_t = 0)]
+        frames: u64,
+        /// bin, hex or octo, guessed from the extension by default
+        #[arg(long)]
+        format: Option<RomFormat>,
+        /// Where the ROM is loaded, 0x200 by default
+        #[arg(long, value_name = "ADDR", value_parser = hex_address)]
+        start_address: Option<u16>,
+    },
+    /// Run every ROM in a directory headlessly with random input and report failures
+    Soak {
+        dir: String,
+        #[arg(long, default_value_t = 30)]
+        seconds: u64,
+    },
+    /// Run a ROM under two timing or quirk profiles in lock-step and report where they diverge
+    Compare {
+        rom: String,
+        #[arg(value_parser = timing_profile)]
+        a: Profile,
+        #[arg(value_parser = timing_profile)]
+        b: Profile,
+        #[arg(long, default_value_t = 3600)]
+        frames: u64,
+    },
+    /// Print everything that differs between two save states
+    Statediff { a: PathBuf, b: PathBuf },
+    /// Run every ROM in a directory in parallel and write a compatibility report
+    Batch {
+        dir: String,
+        #[arg(long, default_value_t = batch::DEFAULT_FRAMES)]
+        frames: u64,
+        /// Worker threads, one per CPU by default
+        #[arg(long)]
+        jobs: Option<usize>,
+        /// Report file, CSV unless it ends in .json
+        #[arg(short, value_name = "FILE")]
+        output: Option<String>,
+    },
+    /// Run a ROM without a window as fast as possible and report its speed and frame times
+    Bench {
+        rom: String,
+        #[arg(long, default_value_t = bench::DEFAULT_FRAMES)]
+        frames: u64,
+    },
+    /// Run Timendus' CHIP-8 test suite from a directory and check each screen against expected.sha1
+    Test {
+        dir: PathBuf,
+        #[arg(long, default_value_t = test_suite::DEFAULT_FRAMES)]
+        frames: u64,
+        /// Record the screens as the expected ones instead of checking them
+        #[arg(long)]
+        bless: bool,
+    },
+}
+
+#[derive(Args)]
+struct RunArgs {
+    /// ROMs to play, or a .playlist file. PageUp/PageDown switches between them, without any
+    /// the window waits for a ROM to be dropped on it
+    roms: Vec<String>,
+
+    /// Cycle through every ROM in DIR with random demo input
+    #[arg(long, value_name = "DIR", conflicts_with = "roms")]
+    kiosk: Option<String>,
+
+    /// How long each ROM runs in kiosk mode
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_SECONDS_PER_ROM, requires = "kiosk")]
+    kiosk_seconds: u64,
+
+    /// How long the keypad has to be left alone before kiosk mode takes over again
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_IDLE_TIMEOUT, requires = "kiosk")]
+    kiosk_idle: u64,
+
+    /// Demo input in kiosk mode: random, none, or movie to replay the movie next to each ROM
+    /// (pong.movie for pong.ch8, recorded with --record)
+    #[arg(long, value_name = "INPUT", default_value = "random", value_parser = demo_input, requires = "kiosk")]
+    kiosk_input: DemoInput,
+
+    /// Start on a built-in CHIP-8 program for picking a ROM from the playlist
+    #[arg(long)]
+    boot_menu: bool,
+
+    /// Keep running while the window is in the background instead of pausing
+    #[arg(long)]
+    no_focus_pause: bool,
+
+    /// Reload the ROM whenever its file changes, for testing a ROM while writing it
+    #[arg(long)]
+    watch_rom: bool,
+
+    /// Window pixels per CHIP-8 pixel, Ctrl+Plus and Ctrl+Minus change it while playing
+    #[arg(long, default_value_t = SCALE_FACTOR, value_parser = clap::value_parser!(u32).range(1..=MAX_SCALE as i64))]
+    scale: u32,
+
+    /// Only scale by whole multiples when the window is resized
+    #[arg(long)]
+    integer_scale: bool,
+
+    /// Start in fullscreen, F11 or Alt+Enter toggles it
+    #[arg(long)]
+    fullscreen: bool,
+
+    /// Screen colours: classic, green, amber, high-contrast or hex colours like #101010,#e0e0e0
+    #[arg(long, value_parser = screen_palette)]
+    palette: Option<Palette>,
+
+    /// Start with the debug overlay shown, F1 toggles it
+    #[arg(long)]
+    overlay: bool,
+
+    /// Record the screen to an animated GIF from the start, Shift+F12 toggles it while playing
+    #[arg(long, value_name = "FILE")]
+    record_gif: Option<PathBuf>,
+
+    /// Time frames by the display's vsync, for less tearing on 60 Hz displays
+    #[arg(long)]
+    vsync: bool,
+
+    /// Instructions executed per second, overrides --ticks (600 is 10 a frame)
+    #[arg(long, value_name = "HZ", value_parser = clap::value_parser!(u32).range(1..))]
+    cpu_hz: Option<u32>,
+
+    /// Fade pixels out instead of switching them off against flicker, keeping DECAY (0.0-0.9) of
+    /// their brightness each frame
+    #[arg(long, value_name = "DECAY", num_args = 0..=1, default_missing_value = "0.5")]
+    phosphor: Option<f32>,
+
+    /// Leave a one pixel gap between big CHIP-8 pixels, in this colour (black if left out)
+    #[arg(long, value_name = "COLOR", num_args = 0..=1, default_missing_value = "#000000", value_parser = grid_color)]
+    grid: Option<Rgb>,
+
+    /// Light up a corner of the screen while the sound timer runs, M mutes the sound
+    #[arg(long)]
+    sound_indicator: bool,
+
+    /// Mirror the screen horizontally
+    #[arg(long)]
+    flip_x: bool,
+
+    /// Mirror the screen vertically
+    #[arg(long)]
+    flip_y: bool,
+
+    /// Where to play: sdl for a window or terminal for half-block characters in this terminal
+    #[cfg(feature = "terminal")]
+    #[arg(long, default_value = "sdl", value_parser = backend)]
+    backend: Backend,
+
+    #[command(flatten)]
+    emu: EmuArgs,
+}
+
+// Options shared by every way of running a ROM
+#[derive(Args)]
+struct EmuArgs {
+    /// Interpreter quirks to follow: default, vip, chip48, schip-modern or xo-chip (or the
+    /// older chip8, schip and xochip)
+    #[arg(long, value_parser = quirks_preset)]
+    quirks: Option<Quirks>,
+
+    /// Quirk presets per ROM hash, defaults to quirks.toml in the config directory
+    #[arg(long, value_name = "FILE", conflicts_with = "quirks")]
+    quirks_file: Option<PathBuf>,
+
+    /// Instruction cycle-cost table: flat, vip or hp48
+    #[arg(long, value_parser = cycle_table)]
+    timing: Option<CycleTable>,
+
+    /// Load the cycle-cost table from a TOML file instead
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["timing", "vip"])]
+    cycle_table: Option<PathBuf>,
+
+    /// Original COSMAC VIP timing: VIP cycle costs and DXYN waiting for the next frame
+    #[arg(long, conflicts_with = "timing")]
+    vip: bool,
+
+    /// Run a CHIP-8X ROM, with colour and a second keypad on the numpad
+    #[arg(long)]
+    chip8x: bool,
+
+    /// Load and start ROMs at this address instead of 0x200, ETI-660 ROMs start at 0x600
+    #[arg(long, value_name = "ADDR", value_parser = hex_address)]
+    start_address: Option<u16>,
+
+    /// Low resolution screen size: 64x32, or 64x48 and 64x64 for the ETI-660
+    #[arg(long, value_parser = geometry)]
+    geometry: Option<Geometry>,
+
+    /// What to do on an opcode that isn't an instruction: panic, skip, halt or log
+    #[arg(long, value_name = "POLICY", value_parser = unknown_opcode_policy)]
+    on_unknown: Option<OnUnknownOpcode>,
+
+    /// How many return addresses fit on the stack, 12 on the original VIP interpreter
+    #[arg(long, value_name = "DEPTH", value_parser = clap::value_parser!(u8).range(1..))]
+    stack_depth: Option<u8>,
+
+    /// Make the stack deeper when a ROM calls past its depth instead of halting
+    #[arg(long)]
+    grow_stack: bool,
+
+    /// What to do when an instruction reads or writes past 0xFFF: wrap or fault
+    #[arg(long, value_name = "POLICY", value_parser = memory_fault_policy)]
+    on_memory_fault: Option<OnMemoryFault>,
+
+    /// ROM format (bin, hex or octo), guessed from the extension by default
+    #[arg(long)]
+    format: Option<RomFormat>,
+
+    /// Seed for the random number instruction, makes runs repeatable
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Record the keypad every frame to a movie file, written on exit
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+
+    /// Play a recorded movie instead of reading the keyboard
+    #[arg(long, value_name = "FILE", value_parser = movie_file, conflicts_with = "record")]
+    play: Option<Movie>,
+
+    /// Set a debugger breakpoint at a hex address, can be given several times
+    #[arg(long = "break", value_name = "ADDR", value_parser = hex_address)]
+    breakpoints: Vec<u16>,
+
+    /// Pause when an instruction touches a hex address, optionally only on :r reads or :w writes
+    #[arg(long = "watch", value_name = "ADDR[:r|w]", value_parser = watchpoint)]
+    watchpoints: Vec<(u16, WatchKind)>,
+
+    /// Keep a byte of memory at a value, like 0x2f0=3 for infinite lives, can be given several
+    /// times
+    #[arg(long = "freeze", value_name = "ADDR=VALUE", value_parser = frozen_byte)]
+    frozen: Vec<(u16, u8)>,
+
+    /// Pause and print where the ROM is stuck after this many instructions without drawing,
+    /// timer activity or key reads
+    #[arg(long, value_name = "INSTRUCTIONS", num_args = 0..=1)]
+    watchdog: Option<Option<u64>>,
+
+    /// Halt on writes below 0x200 so ROMs that overwrite the fonts get caught
+    #[arg(long)]
+    protect_fonts: bool,
+
+    /// Keyboard bindings, defaults to keymap.toml in the config directory
+    #[arg(long, value_name = "FILE")]
+    keymap: Option<PathBuf>,
+
+    /// Bind a gamepad button (SDL names like a, b, dpup) to a keypad key, e.g. a=5
+    #[arg(long, value_name = "BUTTON=KEY", value_parser = pad_binding)]
+    pad: Vec<(String, usize)>,
+
+    /// Buzzer pitch
+    #[arg(long, value_name = "HZ")]
+    beep: Option<f32>,
+
+    /// Buzzer loudness, 0.0-1.0
+    #[arg(long)]
+    volume: Option<f32>,
+
+    #[arg(long)]
+    mute: bool,
+
+    /// Gamepad rumble strength for beeps, 0.0-1.0
+    #[arg(long, value_name = "INTENSITY")]
+    rumble: Option<f32>,
+
+    #[arg(long, conflicts_with = "rumble")]
+    no_rumble: bool,
+
+    /// How far Backspace can roll back
+    #[arg(long, value_name = "SECONDS")]
+    rewind: Option<usize>,
+
+    #[arg(long, conflicts_with = "rewind")]
+    no_rewind: bool,
+
+    /// Animate every sprite draw to debug collisions
+    #[arg(long, value_name = "FRAMES_PER_ROW", num_args = 0..=1)]
+    slow_draw: Option<Option<u32>>,
+
+    /// Keep memory (from the start address on by default) between sessions per ROM
+    #[arg(long, value_name = "START-END", num_args = 0..=1, value_parser = ram_range)]
+    persist_ram: Option<Option<Range<usize>>>,
+
+    /// Publish every frame into a shared memory file
+    #[arg(long, value_name = "NAME", num_args = 0..=1, default_missing_value = "chip8-framebuffer")]
+    shm: Option<String>,
+
+    /// Log every executed instruction with the registers it changed to FILE
+    #[arg(long, value_name = "FILE")]
+    trace: Option<PathBuf>,
+
+    /// Record a trace-event profile, open it in Perfetto or chrome://tracing
+    #[arg(long, value_name = "FILE", num_args = 0..=1, default_missing_value = "chip8-trace.json")]
+    chrome_trace: Option<String>,
+
+    /// Show registers, stack and memory in a debugger in this terminal while the game runs
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    tui: bool,
+
+    /// Load a driver plugin, can be given several times
+    #[cfg(feature = "plugins")]
+    #[arg(long = "plugin", value_name = "PATH")]
+    plugins: Vec<String>,
+}
+
+impl EmuArgs {
+    fn apply(self, emu: &mut Chip8, options: &mut FrontendOptions) -> io::Result<()> {
+        if let Some(quirks) = self.quirks {
+            emu.quirks = quirks;
+            emu.rom_database.quirks = false;
+        } else {
+            let quirks_path = self
+                .quirks_file
+                .or_else(|| Some(rom_quirks::default_path()).filter(|path| path.exists()));
+            if let Some(path) = quirks_path {
+                match RomQuirks::read(&path) {
+                    Ok(rom_quirks) => emu.rom_quirks = rom_quirks,
+                    Err(e) => eprintln!("Failed to load quirks file {}: {}", path.display(), e),
+                }
+            }
+        }
+        if let Some(table) = self.timing {
+            emu.cycle_table = table;
+        }
+        if let Some(path) = &self.cycle_table {
+            emu.cycle_table = context(CycleTable::read(path), path.display())?;
+        }
+        if self.vip {
+            emu.cycle_table = CycleTable::vip();
+            emu.quirks.display_wait = true;
+        }
+        emu.chip8x = self.chip8x;
+        if let Some(address) = self.start_address {
+            emu.start_address = address as usize;
+            emu.pc = address;
+        }
+        if let Some(geometry) = self.geometry {
+            emu.set_geometry(geometry);
+        }
+        if let Some(policy) = self.on_unknown {
+            emu.on_unknown_opcode = policy;
+        }
+        if let Some(depth) = self.stack_depth {
+            emu.set_stack_depth(depth as usize);
+        }
+        emu.grow_stack = self.grow_stack;
+        if let Some(policy) = self.on_memory_fault {
+            emu.on_memory_fault = policy;
+        }
+        emu.rom_format = self.format;
+        if let Some(seed) = self.seed {
+            emu.seed_rng(seed);
+        }
+        for addr in self.breakpoints {
+            emu.debug.add_breakpoint(addr);
+        }
+        for (addr, kind) in self.watchpoints {
+            emu.debug.add_watchpoint(addr, kind);
+        }
+        for (addr, value) in self.frozen {
+            emu.cheats.freeze(addr as usize, value);
+        }
+        if let Some(limit) = self.watchdog {
+            emu.watchdog = Some(Watchdog::new(limit.unwrap_or(watchdog::DEFAULT_LIMIT)));
+        }
+        emu.protect_interpreter_area = self.protect_fonts;
+
+        // Only complain about a missing keymap when it was asked for
+        let keymap_path = self
+            .keymap
+            .or_else(|| Some(keymap::default_path()).filter(|path| path.exists()));
+        if let Some(path) = keymap_path {
+            match KeyMap::read(&path) {
+                Ok(keymap) => options.keymap = keymap,
+                Err(e) => eprintln!("Failed to load keymap {}: {}", path.display(), e),
+            }
+        }
+        options.gamepad_remap.extend(self.pad);
+
+        if let Some(frequency) = self.beep {
+            emu.buzzer.frequency = frequency;
+        }
+        if let Some(volume) = self.volume {
+            emu.buzzer.volume = volume.clamp(0.0, 1.0);
+        }
+        if self.mute {
+            options.sound = false;
+        }
+        if let Some(intensity) = self.rumble {
+            options.rumble_intensity = Some(intensity);
+        }
+        if self.no_rumble {
+            options.rumble_intensity = None;
+        }
+        if let Some(seconds) = self.rewind {
+            emu.rewind = Some(Rewind::new(seconds * 60));
+        }
+        if self.no_rewind {
+            emu.rewind = None;
+        }
+
+        // Rewinding would leave the recorded frames out of step with the emulator
+        if let Some(path) = self.record {
+            let seed = self.seed.unwrap_or_else(rand::random);
+            emu.movie = Some(MovieSession::record(emu, path, seed));
+            emu.rewind = None;
+        }
+        if let Some(movie) = self.play {
+            emu.movie = Some(MovieSession::play(emu, movie));
+            emu.rewind = None;
+        }
+
+        if let Some(frames_per_row) = self.slow_draw {
+            emu.slow_draw = Some(SlowDraw::new(
+                frames_per_row.unwrap_or(slow_draw::DEFAULT_FRAMES_PER_ROW),
+            ));
+        }
+        if let Some(range) = self.persist_ram {
+            let mut ram = PersistentRam::default();
+            if let Some(range) = range {
+                ram.range = range;
+            } else {
+                ram.range.start = emu.start_address;
+            }
+            emu.persistent_ram = Some(ram);
+        }
+
+        if let Some(name) = self.shm {
+            let shm = context(SharedFramebuffer::create(&name), &name)?;
+            println!("Publishing frames to {}", shm.path().display());
+            emu.shared_framebuffer = Some(shm);
+        }
+        if let Some(path) = self.trace {
+            emu.instruction_trace = Some(context(InstructionTrace::create(&path), path.display())?);
+        }
+        if let Some(path) = self.chrome_trace {
+            emu.tracer = Some(TraceRecorder::new(path));
+        }
+
+        #[cfg(feature = "tui")]
+        if self.tui {
+            options.tui_debugger = true;
+        }
+
+        #[cfg(feature = "plugins")]
+        for path in self.plugins {
+            match chip8_emu::plugin::Plugin::load(&path) {
+                Ok(plugin) => {
+                    println!("Loaded plugin {}", plugin.name());
+                    options.plugins.push(plugin);
+                }
+                Err(e) => eprintln!("Failed to load plugin {}: {}", path, e),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn geometry(name: &str) -> Result<Geometry, String> {
+    Geometry::from_name(name)
+        .ok_or_else(|| format!("expected one of {}", geometry::GEOMETRIES.join(", ")))
+}
+
+fn quirks_preset(name: &str) -> Result<Quirks, String> {
+    Quirks::preset(name).ok_or_else(|| {
+        format!(
+            "expected one of {} (or {})",
+            quirks::PRESETS.join(", "),
+            quirks::ALIASES.join(", ")
+        )
+    })
+}
+
+#[cfg(feature = "terminal")]
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Sdl,
+    Terminal,
+}
+
+#[cfg(feature = "terminal")]
+fn backend(name: &str) -> Result<Backend, String> {
+    match name {
+        "sdl" => Ok(Backend::Sdl),
+        "terminal" => Ok(Backend::Terminal),
+        _ => Err("expected sdl or terminal".to_string()),
+    }
+}
+
+fn screen_palette(text: &str) -> Result<Palette, String> {
+    Palette::parse(text).ok_or_else(|| {
+        format!(
+            "expected one of {} or hex colours like #101010,#e0e0e0",
+            palette::PRESETS.join(", ")
+        )
+    })
+}
+
+fn grid_color(text: &str) -> Result<Rgb, String> {
+    palette::parse_hex_color(text).ok_or_else(|| "expected a hex colour like #202020".to_string())
+}
+
+fn movie_file(path: &str) -> Result<Movie, String> {
+    Movie::read(path).map_err(|e| e.to_string())
+}
+
+fn cycle_table(name: &str) -> Result<CycleTable, String> {
+    CycleTable::preset(name)
+        .ok_or_else(|| format!("expected one of {}", timing::PRESETS.join(", ")))
+}
+
+fn timing_profile(name: &str) -> Result<Profile, String> {
+    Profile::named(name).ok_or_else(|| {
+        format!(
+            "expected a cycle table ({}) or a quirk preset ({})",
+            timing::PRESETS.join(", "),
+            quirks::PRESETS.join(", ")
+        )
+    })
+}
+
+fn memory_fault_policy(name: &str) -> Result<OnMemoryFault, String> {
+    OnMemoryFault::parse(name).ok_or_else(|| "expected wrap or fault".to_string())
+}
+
+fn unknown_opcode_policy(name: &str) -> Result<OnUnknownOpcode, String> {
+    OnUnknownOpcode::parse(name).ok_or_else(|| "expected panic, skip, halt or log".to_string())
+}
+
+fn demo_input(name: &str) -> Result<DemoInput, String> {
+    DemoInput::parse(name).ok_or_else(|| "expected random, movie or none".to_string())
+}
+
+fn hex_address(value: &str) -> Result<u16, String> {
+    let hex = value.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(hex, 16).map_err(|_| "expected a hex address like 0x2a4".to_string())
+}
+
+fn frozen_byte(value: &str) -> Result<(u16, u8), String> {
+    let binding = value.split_once('=').and_then(|(addr, byte)| {
+        let byte = match byte.strip_prefix("0x") {
+            Some(hex) => u8::from_str_radix(hex, 16).ok()?,
+            None => byte.parse().ok()?,
+        };
+        Some((hex_address(addr).ok()?, byte))
+    });
+    binding.ok_or_else(|| "expected a hex address and a byte like 0x2f0=3".to_string())
+}
+
+fn watchpoint(value: &str) -> Result<(u16, WatchKind), String> {
+    let (addr, kind) = match value.split_once(':') {
+        Some((addr, "r")) => (addr, WatchKind::Read),
+        Some((addr, "w")) => (addr, WatchKind::Write),
+        Some(_) => return Err("expected :r or :w after the address".to_string()),
+        None => (value, WatchKind::Access),
+    };
+    Ok((hex_address(addr)?, kind))
+}
+
+fn pad_binding(value: &str) -> Result<(String, usize), String> {
+    let binding = value.split_once('=').and_then(|(button, key)| {
+        let key = usize::from_str_radix(key, 16)
+            .ok()
+            .filter(|&key| key < 16)?;
+        Some((button.to_string(), key))
+    });
+    binding.ok_or_else(|| "expected a binding like a=5".to_string())
+}
+
+fn ram_range(value: &str) -> Result<Range<usize>, String> {
+    persistent_ram::parse_range(value)
+        .ok_or_else(|| "expected a range like 0x300-0x3ff".to_string())
+}
+
+// io errors alone don't say which file or address they were about
+fn context<T>(result: io::Result<T>, what: impl Display) -> io::Result<T> {
+    result.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", what, e)))
+}
+
+fn read_rom(rom: &str, format: Option<RomFormat>) -> io::Result<Vec<u8>> {
+    let data = context(std::fs::read(rom), rom)?;
+    let decoded = format
+        .unwrap_or_else(|| RomFormat::from_path(rom))
+        .decode(data);
+    Ok(context(decoded, rom)?.data)
+}
 
 fn main() {
+    if let Err(e) = try_main() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn try_main() -> Result<(), Chip8Error> {
+    let cli = Cli::parse();
+    let ticks = cli.ticks.unwrap_or(DEFAULT_TICKS_PER_FRAME);
     let mut emu = Chip8::new();
+    emu.rom_database.tickrate = cli.ticks.is_none();
+
+    #[cfg(feature = "midi")]
+    let _midi = chip8_emu::drivers::midi_driver::connect(
+        emu.key_injector(),
+        chip8_emu::drivers::midi_driver::DEFAULT_BASE_NOTE,
+    )
+    .map_err(|e| eprintln!("MIDI input disabled: {}", e))
+    .ok();
+
+    if let Some(target) = &cli.json_log {
+        emu.event_log = Some(context(EventLog::open(target), target)?);
+    }
+    if let Some(addr) = &cli.metrics {
+        let metrics = Arc::new(Metrics::default());
+        context(metrics.serve(addr), addr)?;
+        emu.metrics = Some(metrics);
+    }
+
+    match cli.command.unwrap_or(Command::Run(cli.run)) {
+        Command::Run(args) => run(emu, args, ticks)?,
+        Command::Vnc {
+            rom,
+            addr,
+            emu: emu_args,
+        } => {
+            // Only the emulator settings, there is no window for the rest
+            emu_args.apply(&mut emu, &mut FrontendOptions::default())?;
+            let vnc = context(
+                VncServer::start(&addr, emu.key_injector(), emu.metrics.clone()),
+                &addr,
+            )?;
+            println!("Serving VNC on {}", addr);
+
+            let report = emu.load_rom(&rom)?;
+            eprintln!("{}: {}", rom, report);
+            run_loop::restore_persistent_ram(&mut emu);
+            let result = emu.run_headless(ticks, |emu, notice| {
+                if let Some(notice) = notice {
+                    eprintln!("{}", notice);
+                }
+                run_loop::report_skipped_opcodes(emu);
+                // Nothing can step a stuck ROM along without a window, report it once and leave
+                // it paused
+                if emu.watchdog_tripped() {
+                    eprintln!("{}", watchdog::diagnostics(emu));
+                    eprintln!("Emulation paused");
+                    emu.feed_watchdog();
+                }
+                vnc.publish(emu.get_display(), emu.display_width(), emu.display_height());
+                true
+            });
+            if let Err(e) = &result {
+                run_loop::write_halt_dump(&emu, e);
+            }
+            run_loop::finish_session(&mut emu);
+            if let Err(e) = result {
+                eprintln!("Emulation halted: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Disasm {
+            rom,
+            calls,
+            format,
+            start_address,
+        } => {
+            let data = read_rom(&rom, format)?;
+            let start = start_address.unwrap_or(START_ADDRESS as u16);
+            if calls {
+                for (entry, callees) in disassembler::call_graph(&data, start) {
+                    let callees: Vec<String> = callees
+                        .iter()
+                        .map(|addr| format!("{:#05x}", addr))
+                        .collect();
+                    println!("{:#05x}: {}", entry, callees.join(", "));
+                }
+            } else {
+                print!("{}", disassembler::listing(&data, start));
+            }
+        }
+        Command::Cfg {
+            rom,
+            output,
+            frames,
+            format,
+            start_address,
+        } => {
+            let start = start_address.unwrap_or(START_ADDRESS as u16);
+            let execution = if frames > 0 {
+                let recorded = cfg::record_execution(&rom, format, start, frames, ticks);
+                context(recorded, &rom)?
+            } else {
+                Default::default()
+            };
+            if let Some(reason) = &execution.crash {
+                eprintln!("Recording stopped early: {}", reason);
+            }
+            let data = read_rom(&rom, format)?;
+            let graph = cfg::build(&data, start, &execution.executed);
+            let dot = cfg::to_dot(&graph);
+
+            match output {
+                Some(path) => context(std::fs::write(&path, dot), &path)?,
+                None => print!("{}", dot),
+            }
+        }
+        Command::Soak { dir, seconds } => {
+            let mut failures = 0;
+            for (rom, outcome) in context(soak::soak_dir(&dir, seconds, ticks), &dir)? {
+                if !outcome.is_ok() {
+                    failures += 1;
+                }
+                println!("{}: {}", rom, outcome);
+            }
+
+            std::process::exit(if failures > 0 { 1 } else { 0 });
+        }
+        Command::Compare { rom, a, b, frames } => {
+            match context(compare::lockstep(&rom, &a, &b, frames, ticks), &rom)? {
+                None => println!("No divergence in {} frames", frames),
+                Some(divergence) => {
+                    println!("Diverged at frame {}:", divergence.frame);
+                    for difference in divergence.differences {
+                        println!("  {}", difference);
+                    }
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Statediff { a, b } => {
+            let a = context(SaveState::read(&a), a.display())?;
+            let b = context(SaveState::read(&b), b.display())?;
+            let diff = state_diff::diff(&a, &b);
+            print!("{}", state_diff::format_diff(&a, &b, &diff));
+            std::process::exit(if diff.is_empty() { 0 } else { 1 });
+        }
+        Command::Batch {
+            dir,
+            frames,
+            jobs,
+            output,
+        } => {
+            let jobs =
+                jobs.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+            let results = context(batch::run_dir(&dir, frames, ticks, jobs), &dir)?;
+            let report = match &output {
+                Some(path) if path.ends_with(".json") => {
+                    serde_json::to_string_pretty(&batch::to_json(&results))
+                        .map_err(io::Error::from)?
+                }
+                _ => batch::to_csv(&results),
+            };
+            match output {
+                Some(path) => context(std::fs::write(&path, report), &path)?,
+                None => print!("{}", report),
+            }
+
+            let failures = results
+                .iter()
+                .filter(|result| result.outcome.is_failure())
+                .count();
+            eprintln!("{} ROMs, {} failed", results.len(), failures);
+            std::process::exit(if failures > 0 { 1 } else { 0 });
+        }
+        Command::Bench { rom, frames } => match bench::run(&rom, frames, ticks) {
+            Ok(report) => println!("{}", report),
+            Err(e) => {
+                eprintln!("Emulation halted: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Command::Test { dir, frames, bless } => {
+            let results = context(test_suite::run_dir(&dir, frames, ticks), dir.display())?;
+            for (file, outcome) in &results {
+                println!("{}: {}", file, outcome);
+            }
+
+            if bless {
+                let hashes: Vec<_> = results
+                    .iter()
+                    .filter_map(|(file, outcome)| Some((*file, outcome.hash()?.to_string())))
+                    .collect();
+                let path = dir.join(test_suite::EXPECTED_FILE);
+                context(test_suite::write_expected(&path, &hashes), path.display())?;
+                eprintln!("Wrote {} hashes to {}", hashes.len(), path.display());
+                return Ok(());
+            }
+
+            let failures = results
+                .iter()
+                .filter(|(_, outcome)| outcome.is_failure())
+                .count();
+            std::process::exit(if failures > 0 { 1 } else { 0 });
+        }
+    }
+    Ok(())
+}
+
+fn run(mut emu: Chip8, args: RunArgs, ticks: usize) -> Result<(), Chip8Error> {
+    let mut options = FrontendOptions::default();
+    args.emu.apply(&mut emu, &mut options)?;
+    let cpu_hz = match args.cpu_hz {
+        Some(hz) => {
+            emu.rom_database.tickrate = false;
+            hz
+        }
+        None => ticks as u32 * FRAMES_PER_SECOND as u32,
+    };
+    options.boot_menu = args.boot_menu;
+    options.pause_unfocused = !args.no_focus_pause;
+    options.watch_rom = args.watch_rom;
+
+    let mut playlist = match (&args.kiosk, args.roms.as_slice()) {
+        (Some(dir), _) => {
+            options.kiosk = Some(Kiosk::new(
+                args.kiosk_seconds,
+                args.kiosk_idle,
+                args.kiosk_input,
+            ));
+            context(Playlist::from_dir(dir), dir)?
+        }
+        (None, [file]) if Playlist::is_playlist_file(file) => {
+            context(Playlist::from_file(file), file)?
+        }
+        (None, roms) => Playlist::new(roms.to_vec()),
+    };
+
+    // Kiosk mode cycles through a directory, none of it was picked by anyone
+    if options.kiosk.is_none() {
+        match RecentRoms::load(recent::default_path()) {
+            Ok(recent) => options.recent_roms = Some(recent),
+            Err(e) => eprintln!("Failed to read recent ROMs: {}", e),
+        }
+    }
+    // Without ROMs, offer the recent ones that are still there in the boot menu
+    if playlist.is_empty()
+        && let Some(recent) = &options.recent_roms
+    {
+        let roms: Vec<String> = recent
+            .roms()
+            .iter()
+            .filter(|rom| Path::new(rom).is_file())
+            .cloned()
+            .collect();
+        if !roms.is_empty() {
+            playlist = Playlist::new(roms);
+            options.boot_menu = true;
+        }
+    }
+
+    emu.rom_database.palette = args.palette.is_none();
+    let settings = Settings::load(&settings::default_path()).unwrap_or_else(|e| {
+        eprintln!("Failed to read settings: {}", e);
+        Settings::default()
+    });
+    let display_options = DisplayOptions {
+        flip_x: args.flip_x,
+        flip_y: args.flip_y,
+        scale: args.scale,
+        integer_scale: args.integer_scale,
+        fullscreen: args.fullscreen,
+        palette: args.palette.unwrap_or_default(),
+        overlay: args.overlay,
+        // Always lit without sound, so the buzzer can still be seen
+        sound_indicator: args.sound_indicator || !options.sound,
+        vsync: args.vsync,
+        phosphor: args.phosphor,
+        grid: args.grid,
+        crt: settings.crt,
+        geometry: emu.geometry(),
+    };
+    if let Some(path) = args.record_gif {
+        let screen = emu.geometry().size();
+        match GifRecorder::create(&path, &display_options.palette, screen, DEFAULT_GIF_SCALE) {
+            Ok(recorder) => options.gif_recorder = Some(recorder),
+            Err(e) => eprintln!("Failed to start GIF recording {}: {}", path.display(), e),
+        }
+    }
+
+    #[cfg(feature = "terminal")]
+    if args.backend == Backend::Terminal {
+        if playlist.is_empty() {
+            eprintln!("No ROM to play, the terminal can't take dropped files");
+            std::process::exit(2);
+        }
+
+        // Both would draw into this terminal
+        #[cfg(feature = "tui")]
+        if options.tui_debugger {
+            eprintln!("Terminal debugger disabled, it needs the SDL backend");
+            options.tui_debugger = false;
+        }
+
+        let result = chip8_emu::drivers::terminal_driver::run_playlist(
+            &mut emu,
+            &mut options,
+            &mut playlist,
+            cpu_hz,
+            display_options.palette,
+        );
+        if let Err(e) = result {
+            eprintln!("Emulation halted: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Err(e) = frontend::run_playlist(
+        &mut emu,
+        &mut options,
+        &mut playlist,
+        cpu_hz,
+        display_options,
+    ) {
+        eprintln!("Emulation halted: {}", e);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    emu.run("roms/Pong.ch8", 10);
+    #[test]
+    fn quirk_presets_parse_by_their_older_names() {
+        let args = [
+            "chip8_emu",
+            "run",
+            "game.ch8",
+            "--ticks",
+            "12",
+            "--quirks",
+            "schip",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        let Some(Command::Run(run)) = cli.command else {
+            panic!("expected the run subcommand");
+        };
+        assert_eq!(run.emu.quirks, Quirks::preset("schip-modern"));
+        assert_eq!(cli.ticks, Some(12));
+    }
 }
@@ -0,0 +1,183 @@
+// MegaChip, the extension that turns the screen into 256x192 pixels in 256 colours. A ROM
+// switches it on with MEGAON, loads colours with LDPAL and blits sprites of palette indices from
+// anywhere in up to 16 MiB of memory, which LDHI points I at. Sprites are drawn into a back
+// buffer that only reaches the screen on the next CLS.
+
+use crate::palette::Rgb;
+
+pub const MEGA_WIDTH: usize = 256;
+pub const MEGA_HEIGHT: usize = 192;
+// LDHI takes 24-bit addresses
+pub const MEGA_MEMORY_SIZE: usize = 1 << 24;
+// MegaChip ROMs switch the mode on first thing
+pub const MEGAON: u16 = 0x0011;
+
+pub fn is_megachip_rom(rom: &[u8]) -> bool {
+    rom.get(..2) == Some(&MEGAON.to_be_bytes())
+}
+
+// How BMODE mixes a sprite's colours with what is already on the screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    // The sprite at 25%, 50% or 75% opacity
+    Quarter,
+    Half,
+    ThreeQuarters,
+    Add,
+    Multiply,
+}
+
+impl BlendMode {
+    // BMODE's operand, None for numbers without a mode
+    pub fn from_operand(n: u8) -> Option<Self> {
+        match n {
+            0 => Some(Self::Normal),
+            1 => Some(Self::Quarter),
+            2 => Some(Self::Half),
+            3 => Some(Self::ThreeQuarters),
+            4 => Some(Self::Add),
+            5 => Some(Self::Multiply),
+            _ => None,
+        }
+    }
+
+    fn apply(self, sprite: Rgb, screen: Rgb) -> Rgb {
+        let mix = |opacity: u16| {
+            [0, 1, 2].map(|c| {
+                ((sprite[c] as u16 * opacity + screen[c] as u16 * (4 - opacity)) / 4) as u8
+            })
+        };
+        match self {
+            Self::Normal => sprite,
+            Self::Quarter => mix(1),
+            Self::Half => mix(2),
+            Self::ThreeQuarters => mix(3),
+            Self::Add => [0, 1, 2].map(|c| sprite[c].saturating_add(screen[c])),
+            Self::Multiply => [0, 1, 2].map(|c| (sprite[c] as u16 * screen[c] as u16 / 255) as u8),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MegaChip {
+    // MEGAON and MEGAOFF switch between this and the CHIP-8 screen, the settings below stay
+    pub enabled: bool,
+    // Index 0 is transparent, LDPAL fills the entries from 1 up
+    palette: [Rgb; 256],
+    // SPRW and SPRH, 0 stands for 256
+    pub sprite_width: u8,
+    pub sprite_height: u8,
+    pub blend: BlendMode,
+    // Drawing over a pixel of this palette index sets VF
+    pub collision_index: u8,
+    // What sprites are drawn into and the palette index that put each pixel there, empty
+    // until the mode is first switched on
+    buffer: Vec<Rgb>,
+    indices: Vec<u8>,
+    // The buffer as the last CLS showed it
+    frame: Vec<Rgb>,
+}
+
+impl Default for MegaChip {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            palette: [[0; 3]; 256],
+            sprite_width: 0,
+            sprite_height: 0,
+            blend: BlendMode::Normal,
+            collision_index: 0,
+            buffer: Vec::new(),
+            indices: Vec::new(),
+            frame: Vec::new(),
+        }
+    }
+}
+
+impl MegaChip {
+    // Switches the mode on or off with a black screen
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        let size = if enabled { MEGA_WIDTH * MEGA_HEIGHT } else { 0 };
+        for pixels in [&mut self.buffer, &mut self.frame] {
+            pixels.clear();
+            pixels.resize(size, [0; 3]);
+        }
+        self.indices.clear();
+        self.indices.resize(size, 0);
+    }
+
+    // LDPAL, `colors` holds 4 bytes per entry: alpha, red, green and blue. Alpha is ignored, like
+    // the screen alpha of ALPHA.
+    pub fn load_palette(&mut self, colors: &[u8]) {
+        for (entry, argb) in self.palette[1..].iter_mut().zip(colors.chunks_exact(4)) {
+            *entry = [argb[1], argb[2], argb[3]];
+        }
+    }
+
+    pub fn sprite_size(&self) -> (usize, usize) {
+        let size = |n: u8| if n == 0 { 256 } else { n as usize };
+        (size(self.sprite_width), size(self.sprite_height))
+    }
+
+    // Blits `sprite`, one palette index per pixel and `sprite_size` of them, into the back
+    // buffer. Index 0 is left out and so is whatever falls off the screen. True when it drew over
+    // a pixel of `collision_index`.
+    pub fn draw(&mut self, x: usize, y: usize, sprite: &[u8]) -> bool {
+        let (width, _) = self.sprite_size();
+        let mut collided = false;
+        for (row, indices) in sprite.chunks(width).enumerate() {
+            for (column, &index) in indices.iter().enumerate() {
+                let (screen_x, screen_y) = (x + column, y + row);
+                if index == 0 || screen_x >= MEGA_WIDTH || screen_y >= MEGA_HEIGHT {
+                    continue;
+                }
+
+                let i = screen_x + screen_y * MEGA_WIDTH;
+                collided |= self.indices[i] != 0 && self.indices[i] == self.collision_index;
+                self.indices[i] = index;
+                self.buffer[i] = self
+                    .blend
+                    .apply(self.palette[index as usize], self.buffer[i]);
+            }
+        }
+        collided
+    }
+
+    // CLS, shows the back buffer and clears it for the next frame. `lit` gets every pixel a
+    // sprite drew, for frontends that only show two colours.
+    pub fn show(&mut self, lit: &mut [bool]) {
+        self.frame.copy_from_slice(&self.buffer);
+        for (lit, &index) in lit.iter_mut().zip(&self.indices) {
+            *lit = index != 0;
+        }
+        self.buffer.fill([0; 3]);
+        self.indices.fill(0);
+    }
+
+    // Moves the back buffer by dx/dy pixels, whatever scrolls in is blank
+    pub fn scroll(&mut self, dx: isize, dy: isize) {
+        let (buffer, indices) = (self.buffer.clone(), self.indices.clone());
+        for y in 0..MEGA_HEIGHT as isize {
+            for x in 0..MEGA_WIDTH as isize {
+                let (src_x, src_y) = (x - dx, y - dy);
+                let i = (x + y * MEGA_WIDTH as isize) as usize;
+                if (0..MEGA_WIDTH as isize).contains(&src_x)
+                    && (0..MEGA_HEIGHT as isize).contains(&src_y)
+                {
+                    let src = (src_x + src_y * MEGA_WIDTH as isize) as usize;
+                    (self.buffer[i], self.indices[i]) = (buffer[src], indices[src]);
+                } else {
+                    (self.buffer[i], self.indices[i]) = ([0; 3], 0);
+                }
+            }
+        }
+    }
+
+    // The colours on screen, `MEGA_WIDTH` per row
+    pub fn frame(&self) -> &[Rgb] {
+        &self.frame
+    }
+}
@@ -0,0 +1,20 @@
+// What an instruction touching memory past 0xFFF does, like a DXYN or FX55 with I near the
+// end or a jump that runs PC off the end. Every access goes through `Chip8::memory_address`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnMemoryFault {
+    // Go around to the start of memory like the address lines of a 4K machine
+    #[default]
+    Wrap,
+    // Stop executing and report the address through `Chip8::halted`
+    Fault,
+}
+
+impl OnMemoryFault {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "wrap" => Some(Self::Wrap),
+            "fault" => Some(Self::Fault),
+            _ => None,
+        }
+    }
+}
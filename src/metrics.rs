@@ -0,0 +1,101 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_REQUEST_LINE: u64 = 1024;
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub frames: AtomicU64,
+    pub instructions: AtomicU64,
+    pub dropped_frames: AtomicU64,
+    pub connected_clients: AtomicU64,
+    pub errors: AtomicU64,
+}
+
+impl Metrics {
+    pub fn render(&self) -> String {
+        let metrics = [
+            (
+                "chip8_frames_total",
+                "counter",
+                "Frames emulated",
+                &self.frames,
+            ),
+            (
+                "chip8_instructions_total",
+                "counter",
+                "Instructions executed",
+                &self.instructions,
+            ),
+            (
+                "chip8_dropped_frames_total",
+                "counter",
+                "Frames that overran the frame budget",
+                &self.dropped_frames,
+            ),
+            (
+                "chip8_connected_clients",
+                "gauge",
+                "Clients currently connected to a streaming backend",
+                &self.connected_clients,
+            ),
+            (
+                "chip8_errors_total",
+                "counter",
+                "Emulation errors",
+                &self.errors,
+            ),
+        ];
+
+        let mut out = String::new();
+        for (name, kind, help, value) in metrics {
+            out.push_str(&format!(
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {}\n",
+                value.load(Ordering::Relaxed)
+            ));
+        }
+        out
+    }
+
+    // Serves the text exposition format on `GET /metrics` from a background thread
+    pub fn serve(self: &Arc<Self>, addr: &str) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        let metrics = Arc::clone(self);
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = metrics.respond(stream);
+            }
+        });
+
+        Ok(())
+    }
+
+    // Requests are answered one at a time, so a client that connects and never sends a line
+    // only holds up the scrapers behind it until the timeout
+    fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+        let mut request_line = String::new();
+        BufReader::new((&stream).take(MAX_REQUEST_LINE)).read_line(&mut request_line)?;
+
+        let (status, body) = if request_line.starts_with("GET /metrics ") {
+            ("200 OK", self.render())
+        } else {
+            ("404 Not Found", "not found\n".to_string())
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+}
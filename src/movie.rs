@@ -0,0 +1,174 @@
+// Keypad input recorded frame by frame, together with the RND seed this replays a session
+// exactly. Movies are JSON with the input run-length encoded, each run is the keys held as a
+// bitmask (bit n for key n) and how many frames they stayed that way.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Chip8;
+use crate::keypad::KEY_COUNT;
+
+pub const MOVIE_VERSION: u64 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Movie {
+    pub version: u64,
+    pub seed: u64,
+    pub rom_hash: Option<String>,
+    runs: Vec<(u16, u32)>,
+}
+
+impl Movie {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            version: MOVIE_VERSION,
+            seed,
+            rom_hash: None,
+            runs: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, keypad: &[bool; KEY_COUNT]) {
+        let keys = pack(keypad);
+        match self.runs.last_mut() {
+            Some((held, frames)) if *held == keys => *frames += 1,
+            _ => self.runs.push((keys, 1)),
+        }
+    }
+
+    // The keys held during `frame`, None past the end of the movie
+    pub fn keys(&self, frame: u64) -> Option<[bool; KEY_COUNT]> {
+        let mut start = 0;
+        for &(keys, frames) in &self.runs {
+            if frame < start + frames as u64 {
+                return Some(unpack(keys));
+            }
+            start += frames as u64;
+        }
+        None
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.runs.iter().map(|&(_, frames)| frames as u64).sum()
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let movie: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if movie.version != MOVIE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported movie version {}", movie.version),
+            ));
+        }
+        Ok(movie)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)
+    }
+}
+
+fn pack(keypad: &[bool; KEY_COUNT]) -> u16 {
+    keypad
+        .iter()
+        .enumerate()
+        .filter(|&(_, &down)| down)
+        .fold(0, |keys, (key, _)| keys | 1 << key)
+}
+
+fn unpack(keys: u16) -> [bool; KEY_COUNT] {
+    std::array::from_fn(|key| keys & 1 << key != 0)
+}
+
+pub enum MovieSession {
+    Recording { movie: Movie, path: PathBuf },
+    Playing { movie: Movie, frame: u64 },
+}
+
+impl MovieSession {
+    // Seeds the RND source so the recording can be replayed
+    pub fn record(emu: &mut Chip8, path: impl Into<PathBuf>, seed: u64) -> Self {
+        emu.seed_rng(seed);
+        MovieSession::Recording {
+            movie: Movie::new(seed),
+            path: path.into(),
+        }
+    }
+
+    pub fn play(emu: &mut Chip8, movie: Movie) -> Self {
+        emu.seed_rng(movie.seed);
+        MovieSession::Playing { movie, frame: 0 }
+    }
+}
+
+// What happened to a playing movie that the player should hear about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovieNotice {
+    // Recorded with a different ROM, it plays anyway
+    OtherRom,
+    // Out of input after this many frames, the player has the keypad again
+    Finished(u64),
+}
+
+impl fmt::Display for MovieNotice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OtherRom => write!(
+                f,
+                "Movie was recorded with a different ROM, playing it anyway"
+            ),
+            Self::Finished(frames) => write!(f, "Movie finished after {} frames", frames),
+        }
+    }
+}
+
+impl Chip8 {
+    // Call once per emulated frame before running it. Records the keypad, or replaces it with
+    // the recorded keys and hands control back to the player when the movie ends.
+    pub fn movie_frame(&mut self) -> Option<MovieNotice> {
+        match &mut self.movie {
+            Some(MovieSession::Recording { movie, .. }) => {
+                if movie.rom_hash.is_none() {
+                    movie.rom_hash = self.rom_hash.clone();
+                }
+                movie.record(&self.keypad);
+                None
+            }
+            Some(MovieSession::Playing { movie, frame }) => {
+                let other_rom =
+                    *frame == 0 && movie.rom_hash.is_some() && movie.rom_hash != self.rom_hash;
+                match movie.keys(*frame) {
+                    Some(keys) => {
+                        self.keypad = keys;
+                        *frame += 1;
+                        other_rom.then_some(MovieNotice::OtherRom)
+                    }
+                    None => {
+                        let frames = *frame;
+                        self.clear_keys();
+                        self.movie = None;
+                        Some(MovieNotice::Finished(frames))
+                    }
+                }
+            }
+            None => None,
+        }
+    }
+
+    // Writes a recording out and returns where it went, None when nothing is being recorded
+    pub fn save_movie(&self) -> io::Result<Option<PathBuf>> {
+        match &self.movie {
+            Some(MovieSession::Recording { movie, path }) => {
+                movie
+                    .write(path)
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+                Ok(Some(path.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+}
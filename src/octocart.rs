@@ -0,0 +1,140 @@
+// Octo "cartridges" are GIFs whose pixel colour indices carry a payload in their low two
+// bits: four pixels per byte, most significant bits first, across all frames. The first
+// four bytes are the big-endian payload length, followed by a JSON object holding the
+// program and the settings it was written for.
+
+use std::io;
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CartOptions {
+    pub tickrate: Option<u64>,
+    pub fill_color: Option<String>,
+    pub background_color: Option<String>,
+    pub buzz_color: Option<String>,
+    pub shift_quirks: bool,
+    pub load_store_quirks: bool,
+    pub jump_quirks: bool,
+    pub logic_quirks: bool,
+    pub clip_quirks: bool,
+    pub vblank_quirks: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OctoCart {
+    pub program: String,
+    pub options: CartOptions,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+pub fn parse_cartridge(data: &[u8]) -> io::Result<OctoCart> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options
+        .read_info(data)
+        .map_err(|e| invalid(&e.to_string()))?;
+
+    let mut bytes = Vec::new();
+    let mut current = 0u8;
+    let mut bits = 0;
+
+    while let Some(frame) = decoder
+        .read_next_frame()
+        .map_err(|e| invalid(&e.to_string()))?
+    {
+        for &index in frame.buffer.iter() {
+            current = (current << 2) | (index & 3);
+            bits += 2;
+            if bits == 8 {
+                bytes.push(current);
+                current = 0;
+                bits = 0;
+            }
+        }
+    }
+
+    if bytes.len() < 4 {
+        return Err(invalid("GIF does not contain an Octo cartridge payload"));
+    }
+
+    let size = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    let payload = bytes
+        .get(4..4 + size)
+        .ok_or_else(|| invalid("Octo cartridge payload is truncated"))?;
+
+    let json: Value = serde_json::from_slice(payload)
+        .map_err(|_| invalid("Octo cartridge payload is not JSON"))?;
+
+    let program = json["program"]
+        .as_str()
+        .ok_or_else(|| invalid("Octo cartridge has no program"))?
+        .to_string();
+
+    let opts = &json["options"];
+    let color = |key: &str| opts[key].as_str().map(str::to_string);
+    let flag = |key: &str| opts[key].as_bool().unwrap_or(false);
+
+    Ok(OctoCart {
+        program,
+        options: CartOptions {
+            tickrate: opts["tickrate"].as_u64(),
+            fill_color: color("fillColor"),
+            background_color: color("backgroundColor"),
+            buzz_color: color("buzzColor"),
+            shift_quirks: flag("shiftQuirks"),
+            load_store_quirks: flag("loadStoreQuirks"),
+            jump_quirks: flag("jumpQuirks"),
+            logic_quirks: flag("logicQuirks"),
+            clip_quirks: flag("clipQuirks"),
+            vblank_quirks: flag("vBlankQuirks"),
+        },
+    })
+}
+
+// Carts store Octo source code. Without a full Octo assembler only programs made of plain
+// byte literals (as produced by exporting a ROM as source) can be turned back into a ROM.
+pub fn assemble_literals(program: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+
+    for line in program.lines() {
+        let code = line.split('#').next().unwrap_or("");
+
+        for token in code.split_whitespace() {
+            let value = if let Some(hex) = token.strip_prefix("0x") {
+                u8::from_str_radix(hex, 16)
+            } else if let Some(bin) = token.strip_prefix("0b") {
+                u8::from_str_radix(bin, 2)
+            } else {
+                token.parse()
+            };
+
+            bytes.push(value.map_err(|_| {
+                invalid(&format!(
+                    "cartridge program uses Octo syntax ('{}'), assemble it with Octo and load the .ch8 instead",
+                    token
+                ))
+            })?);
+        }
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literals_assemble_in_any_base() {
+        let program = "0x60 0b00000101 # V0 = 5\n  18 255\n";
+        assert_eq!(assemble_literals(program).unwrap(), [0x60, 0x05, 18, 255]);
+
+        // Anything but byte literals needs Octo itself
+        assert!(assemble_literals(": main\nclear").is_err());
+        assert!(assemble_literals("256").is_err());
+    }
+}
@@ -0,0 +1,215 @@
+// Decides whether the window frontend runs the next frame and how long that frame lasts:
+// 60 frames a second normally, nothing while paused apart from single-frame advances and as
+// fast as possible while fast-forwarding.
+//
+// Frames are timed against a running deadline rather than from when each one started, so
+// oversleeping one frame shortens the next and the rate doesn't drift. Sleeps stop short of
+// the deadline and spin for the rest, since sleeping is only accurate to about 15 ms on
+// Windows. `CpuClock` spreads an instruction rate in Hz over those frames, the timers and the
+// screen stay at 60 Hz, and `RateMeter` measures what was actually reached.
+
+use std::time::{Duration, Instant};
+use std::{hint, thread};
+
+pub const FRAMES_PER_SECOND: f64 = 60.0;
+// 10 instructions a frame
+pub const DEFAULT_CPU_HZ: u32 = 600;
+// Frames a late frontend may be behind before it gives up catching up
+const MAX_LAG_FRAMES: u32 = 3;
+// How long before a deadline sleeping stops and spinning takes over
+const SPIN_MARGIN: Duration = Duration::from_millis(1);
+
+pub struct Pacer {
+    frame_duration: Duration,
+    paused: bool,
+    fast_forward: bool,
+    // Frames requested with frame advance while paused
+    pending_frames: usize,
+    // When the current frame should end, None to start over from the next frame's start
+    deadline: Option<Instant>,
+    // Presenting a frame already waited for the display, see `set_vsync`
+    vsync: bool,
+}
+
+impl Default for Pacer {
+    fn default() -> Self {
+        Self::new(FRAMES_PER_SECOND)
+    }
+}
+
+impl Pacer {
+    pub fn new(frames_per_second: f64) -> Self {
+        Self {
+            frame_duration: Duration::from_secs_f64(1.0 / frames_per_second),
+            paused: false,
+            fast_forward: false,
+            pending_frames: 0,
+            deadline: None,
+            vsync: false,
+        }
+    }
+
+    // For displays that block on vsync, which keeps better time than sleeping, `wait` then
+    // only reports missed refreshes
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.vsync = vsync;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.pending_frames = 0;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.paused {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Lets one more frame through while paused, ignored otherwise
+    pub fn advance_frame(&mut self) {
+        if self.paused {
+            self.pending_frames += 1;
+        }
+    }
+
+    pub fn set_fast_forward(&mut self, fast_forward: bool) {
+        self.fast_forward = fast_forward;
+    }
+
+    pub fn is_fast_forwarding(&self) -> bool {
+        self.fast_forward && !self.paused
+    }
+
+    // Whether the emulator should run this frame, uses up a frame advance when paused
+    pub fn next_frame(&mut self) -> bool {
+        if !self.paused {
+            return true;
+        }
+        if self.pending_frames > 0 {
+            self.pending_frames -= 1;
+            return true;
+        }
+        false
+    }
+
+    // Sleeps until the frame that started at `frame_start` is due to end. False when the frame
+    // already took longer than it should have.
+    pub fn wait(&mut self, frame_start: Instant) -> bool {
+        if self.is_fast_forwarding() {
+            self.deadline = None;
+            return true;
+        }
+        // Late when a refresh was missed
+        if self.vsync {
+            return frame_start.elapsed() < self.frame_duration * 2;
+        }
+
+        let deadline = self.deadline.unwrap_or(frame_start) + self.frame_duration;
+        let now = Instant::now();
+        if now < deadline {
+            sleep_until(deadline);
+            self.deadline = Some(deadline);
+            true
+        } else {
+            // After a stall like loading a ROM, carry on from now instead of rushing frames
+            let lagging = now - deadline > self.frame_duration * MAX_LAG_FRAMES;
+            self.deadline = if lagging { None } else { Some(deadline) };
+            false
+        }
+    }
+}
+
+fn sleep_until(deadline: Instant) {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining > SPIN_MARGIN {
+        thread::sleep(remaining - SPIN_MARGIN);
+    }
+    while Instant::now() < deadline {
+        hint::spin_loop();
+    }
+}
+
+// How many instructions each 60 Hz frame runs for a rate in Hz. The remainder carries over,
+// so 700 Hz runs 11, 12, 12, 11, ... instructions and exactly 700 every second.
+#[derive(Debug, Clone, Copy)]
+pub struct CpuClock {
+    hz: u32,
+    // Instructions owed, in 1/60ths
+    credit: u32,
+}
+
+impl CpuClock {
+    pub fn new(hz: u32) -> Self {
+        Self { hz, credit: 0 }
+    }
+
+    pub fn hz(&self) -> u32 {
+        self.hz
+    }
+
+    pub fn set_hz(&mut self, hz: u32) {
+        self.hz = hz;
+    }
+
+    pub fn next_frame(&mut self) -> usize {
+        let frames_per_second = FRAMES_PER_SECOND as u32;
+        self.credit += self.hz;
+        let instructions = self.credit / frames_per_second;
+        self.credit %= frames_per_second;
+        instructions as usize
+    }
+}
+
+// What a second of running actually got through, which fast-forward, slow machines and
+// pauses make differ from what was asked for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rates {
+    pub fps: f64,
+    pub ips: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct RateMeter {
+    started: Option<Instant>,
+    frames: u32,
+    instructions: u64,
+}
+
+impl RateMeter {
+    // Counts a frame, returns the rates over the last second once one has passed
+    pub fn frame(&mut self) -> Option<Rates> {
+        let now = Instant::now();
+        let started = *self.started.get_or_insert(now);
+        self.frames += 1;
+
+        let elapsed = now - started;
+        if elapsed < Duration::from_secs(1) {
+            return None;
+        }
+        let seconds = elapsed.as_secs_f64();
+        let rates = Rates {
+            fps: self.frames as f64 / seconds,
+            ips: self.instructions as f64 / seconds,
+        };
+        *self = Self {
+            started: Some(now),
+            ..Self::default()
+        };
+        Some(rates)
+    }
+
+    pub fn add_instructions(&mut self, instructions: u64) {
+        self.instructions += instructions;
+    }
+}
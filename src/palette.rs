@@ -0,0 +1,121 @@
+// Screen colours. `--palette` takes a preset name or comma separated hex colours:
+// background and foreground, optionally followed by the XO-CHIP second plane and the colour
+// of pixels lit on both planes.
+
+pub type Rgb = [u8; 3];
+
+pub const PRESETS: [&str; 4] = ["classic", "green", "amber", "high-contrast"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub background: Rgb,
+    pub foreground: Rgb,
+    pub plane2: Rgb,
+    pub both_planes: Rgb,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+impl Palette {
+    // White on black
+    pub fn classic() -> Self {
+        Self {
+            background: [0x00, 0x00, 0x00],
+            foreground: [0xFF, 0xFF, 0xFF],
+            plane2: [0xAA, 0xAA, 0xAA],
+            both_planes: [0x55, 0x55, 0x55],
+        }
+    }
+
+    // Green phosphor monitor
+    pub fn green() -> Self {
+        Self {
+            background: [0x0A, 0x1A, 0x0F],
+            foreground: [0x33, 0xFF, 0x66],
+            plane2: [0x1E, 0x99, 0x3D],
+            both_planes: [0x99, 0xFF, 0xB3],
+        }
+    }
+
+    // Amber phosphor monitor
+    pub fn amber() -> Self {
+        Self {
+            background: [0x1A, 0x10, 0x00],
+            foreground: [0xFF, 0xB0, 0x00],
+            plane2: [0x99, 0x66, 0x00],
+            both_planes: [0xFF, 0xD8, 0x80],
+        }
+    }
+
+    // Yellow on black with a strongly separated second plane
+    pub fn high_contrast() -> Self {
+        Self {
+            background: [0x00, 0x00, 0x00],
+            foreground: [0xFF, 0xFF, 0x00],
+            plane2: [0x00, 0xFF, 0xFF],
+            both_planes: [0xFF, 0xFF, 0xFF],
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Self::classic()),
+            "green" => Some(Self::green()),
+            "amber" => Some(Self::amber()),
+            "high-contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+
+    // The colour of a pixel lit on the planes in `planes`, see `Chip8::display_planes`
+    pub fn color(&self, planes: u8) -> Rgb {
+        match planes & 3 {
+            0 => self.background,
+            1 => self.foreground,
+            2 => self.plane2,
+            _ => self.both_planes,
+        }
+    }
+
+    // A preset name or 2-4 hex colours like `#101010,#e0e0e0`. Missing plane colours are
+    // taken from the classic palette.
+    pub fn parse(text: &str) -> Option<Self> {
+        if let Some(palette) = Self::preset(text) {
+            return Some(palette);
+        }
+
+        let colors = text
+            .split(',')
+            .map(parse_hex_color)
+            .collect::<Option<Vec<_>>>()?;
+        let mut palette = Self::classic();
+        match colors.as_slice() {
+            [background, foreground, rest @ ..] if rest.len() <= 2 => {
+                palette.background = *background;
+                palette.foreground = *foreground;
+                if let Some(&plane2) = rest.first() {
+                    palette.plane2 = plane2;
+                }
+                if let Some(&both_planes) = rest.get(1) {
+                    palette.both_planes = both_planes;
+                }
+                Some(palette)
+            }
+            _ => None,
+        }
+    }
+}
+
+// `#rrggbb` or `rrggbb`
+pub fn parse_hex_color(text: &str) -> Option<Rgb> {
+    let hex = text.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
@@ -0,0 +1,110 @@
+// Keeps a slice of memory on disk between sessions, keyed by ROM hash, so games that store
+// high scores or progress at fixed addresses remember them.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::START_ADDRESS;
+
+pub struct PersistentRam {
+    pub range: Range<usize>,
+    pub dir: PathBuf,
+}
+
+impl Default for PersistentRam {
+    fn default() -> Self {
+        Self::new(START_ADDRESS..4096, default_dir())
+    }
+}
+
+impl PersistentRam {
+    pub fn new(range: Range<usize>, dir: PathBuf) -> Self {
+        Self { range, dir }
+    }
+
+    // The range is part of the file name so changing it never restores mismatched data
+    pub fn path(&self, rom_hash: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{}-{:03x}-{:03x}.ram",
+            rom_hash, self.range.start, self.range.end
+        ))
+    }
+
+    // Returns false when there was nothing saved for this ROM yet
+    pub fn restore(&self, rom_hash: &str, memory: &mut [u8]) -> io::Result<bool> {
+        let data = match fs::read(self.path(rom_hash)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        let Some(target) = memory.get_mut(self.range.clone()) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("range {:#05x?} is outside of memory", self.range),
+            ));
+        };
+        if data.len() != target.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "saved RAM is {} bytes, expected {}",
+                    data.len(),
+                    target.len()
+                ),
+            ));
+        }
+
+        target.copy_from_slice(&data);
+        Ok(true)
+    }
+
+    pub fn save(&self, rom_hash: &str, memory: &[u8]) -> io::Result<PathBuf> {
+        let data = memory.get(self.range.clone()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("range {:#05x?} is outside of memory", self.range),
+            )
+        })?;
+
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(rom_hash);
+        fs::write(&path, data)?;
+        Ok(path)
+    }
+}
+
+// `$XDG_DATA_HOME/chip8-emu`, falling back to `~/.local/share` and then the working directory
+pub fn data_dir() -> PathBuf {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_default();
+
+    data_home.join("chip8-emu")
+}
+
+pub fn default_dir() -> PathBuf {
+    data_dir().join("ram")
+}
+
+// Parses an inclusive `start-end` range like `0x300-0x3ff`
+pub fn parse_range(text: &str) -> Option<Range<usize>> {
+    let (start, end) = text.split_once('-')?;
+    let parse = |value: &str| {
+        let value = value.trim();
+        match value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))
+        {
+            Some(hex) => usize::from_str_radix(hex, 16).ok(),
+            None => value.parse().ok(),
+        }
+    };
+
+    let (start, end) = (parse(start)?, parse(end)?);
+    (start <= end && end < 4096).then_some(start..end + 1)
+}
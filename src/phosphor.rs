@@ -0,0 +1,65 @@
+// Pixel persistence against XOR flicker. A pixel that goes dark fades out over a few frames
+// instead of vanishing at once, like the phosphor of an old CRT, so sprites that games erase
+// and draw again every frame stay visible instead of blinking.
+
+// Past this fading would take long enough to smear moving sprites into trails
+pub const MAX_DECAY: f32 = 0.9;
+// Dimmer than this counts as dark
+const CUTOFF: f32 = 1.0 / 64.0;
+
+#[derive(Debug, Clone)]
+pub struct Phosphor {
+    // How much of its brightness a dark pixel keeps each frame
+    decay: f32,
+    brightness: Vec<f32>,
+}
+
+impl Phosphor {
+    pub fn new(decay: f32) -> Self {
+        Self {
+            decay: decay.clamp(0.0, MAX_DECAY),
+            brightness: Vec::new(),
+        }
+    }
+
+    // Ages the glow by a frame and lights every pixel that is on, true while some are still
+    // fading. Switching resolution starts over.
+    pub fn update(&mut self, pixels: &[bool]) -> bool {
+        if self.brightness.len() != pixels.len() {
+            self.brightness = vec![0.0; pixels.len()];
+        }
+
+        let mut fading = false;
+        for (glow, &lit) in self.brightness.iter_mut().zip(pixels) {
+            *glow = if lit {
+                1.0
+            } else if *glow * self.decay < CUTOFF {
+                0.0
+            } else {
+                fading = true;
+                *glow * self.decay
+            };
+        }
+        fading
+    }
+
+    // 0.0 for dark to 1.0 for lit, one per pixel of the last update
+    pub fn brightness(&self) -> &[f32] {
+        &self.brightness
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phosphor_fades_dark_pixels_out() {
+        let mut phosphor = Phosphor::new(0.5);
+        assert!(!phosphor.update(&[true, false]));
+        assert!(phosphor.update(&[false, false]));
+        assert_eq!(phosphor.brightness(), &[0.5, 0.0]);
+        while phosphor.update(&[false, false]) {}
+        assert_eq!(phosphor.brightness(), &[0.0, 0.0]);
+    }
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    roms: Vec<String>,
+    current: usize,
+}
+
+impl Playlist {
+    pub fn new(roms: Vec<String>) -> Self {
+        Self { roms, current: 0 }
+    }
+
+    // One ROM path per line, blank lines and lines starting with '#' are skipped.
+    // Relative paths are resolved against the playlist's directory.
+    pub fn from_file(filename: &str) -> io::Result<Self> {
+        let text = fs::read_to_string(filename)?;
+        let base = Path::new(filename).parent().unwrap_or(Path::new(""));
+
+        let roms = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| base.join(line).to_string_lossy().into_owned())
+            .collect();
+
+        Ok(Self::new(roms))
+    }
+
+    pub fn from_dir(dir: &str) -> io::Result<Self> {
+        let mut roms: Vec<String> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("ch8") | Some("c8") | Some("hex") | Some("gif")
+                )
+            })
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        roms.sort();
+
+        Ok(Self::new(roms))
+    }
+
+    pub fn is_playlist_file(filename: &str) -> bool {
+        matches!(
+            Path::new(filename).extension().and_then(|ext| ext.to_str()),
+            Some("playlist") | Some("m3u")
+        )
+    }
+
+    pub fn roms(&self) -> &[String] {
+        &self.roms
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+
+    pub fn position(&self) -> usize {
+        self.current
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.roms.get(self.current).map(String::as_str)
+    }
+
+    pub fn advance(&mut self) -> Option<&str> {
+        if !self.roms.is_empty() {
+            self.current = (self.current + 1) % self.roms.len();
+        }
+        self.current()
+    }
+
+    pub fn go_back(&mut self) -> Option<&str> {
+        if !self.roms.is_empty() {
+            self.current = (self.current + self.roms.len() - 1) % self.roms.len();
+        }
+        self.current()
+    }
+
+    // Makes `rom` the current entry, adding it at the end if it isn't in the list yet
+    pub fn open(&mut self, rom: &str) {
+        match self.roms.iter().position(|entry| entry == rom) {
+            Some(index) => self.current = index,
+            None => {
+                self.roms.push(rom.to_string());
+                self.current = self.roms.len() - 1;
+            }
+        }
+    }
+
+    pub fn select(&mut self, index: usize) -> Option<&str> {
+        if index < self.roms.len() {
+            self.current = index;
+        }
+        self.current()
+    }
+}
@@ -0,0 +1,128 @@
+// Runtime-loaded driver plugins. A plugin is a shared library exporting
+//
+//   const Chip8PluginDescriptor *chip8_plugin_descriptor(void);
+//
+// which returns a static descriptor. Any callback may be null if the plugin doesn't
+// provide that kind of driver. `state` is whatever `init` returned.
+//
+//   typedef struct {
+//       uint32_t abi_version;                 // must be CHIP8_PLUGIN_ABI_VERSION (1)
+//       const char *name;
+//       void *(*init)(void);
+//       void (*present)(void *state, const uint8_t *pixels, uint32_t width, uint32_t height);
+//       void (*audio)(void *state, bool sound_active);
+//       void (*poll_input)(void *state, bool keys[16]);
+//       void (*shutdown)(void *state);
+//   } Chip8PluginDescriptor;
+//
+// `pixels` holds one byte per pixel (0 = off, 1 = on). `poll_input` receives the current
+// keypad state and may change any entry.
+
+use std::error::Error;
+use std::ffi::{CStr, c_char, c_void};
+use std::ptr;
+
+use libloading::{Library, Symbol};
+
+use crate::keypad::KEY_COUNT;
+
+pub const ABI_VERSION: u32 = 1;
+
+#[repr(C)]
+pub struct PluginDescriptor {
+    pub abi_version: u32,
+    pub name: *const c_char,
+    pub init: Option<extern "C" fn() -> *mut c_void>,
+    pub present: Option<extern "C" fn(*mut c_void, *const u8, u32, u32)>,
+    pub audio: Option<extern "C" fn(*mut c_void, bool)>,
+    pub poll_input: Option<extern "C" fn(*mut c_void, *mut bool)>,
+    pub shutdown: Option<extern "C" fn(*mut c_void)>,
+}
+
+pub struct Plugin {
+    name: String,
+    descriptor: &'static PluginDescriptor,
+    state: *mut c_void,
+    pixels: Vec<u8>,
+    // Declared last so the library is unloaded after everything that points into it
+    _library: Library,
+}
+
+impl Plugin {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        // Safety: loading a library runs its initialisers, plugins are trusted like any
+        // other code the user chooses to run
+        let library = unsafe { Library::new(path)? };
+
+        let descriptor: &'static PluginDescriptor = unsafe {
+            let entry: Symbol<extern "C" fn() -> *const PluginDescriptor> =
+                library.get(b"chip8_plugin_descriptor")?;
+            entry()
+                .as_ref()
+                .ok_or("plugin returned a null descriptor")?
+        };
+
+        if descriptor.abi_version != ABI_VERSION {
+            return Err(format!(
+                "plugin ABI version {} is not supported (expected {})",
+                descriptor.abi_version, ABI_VERSION
+            )
+            .into());
+        }
+
+        let name = if descriptor.name.is_null() {
+            path.to_string()
+        } else {
+            unsafe { CStr::from_ptr(descriptor.name) }
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        let state = descriptor.init.map_or(ptr::null_mut(), |init| init());
+
+        Ok(Self {
+            name,
+            descriptor,
+            state,
+            pixels: Vec::new(),
+            _library: library,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn present(&mut self, video: &[bool], width: usize, height: usize) {
+        if let Some(present) = self.descriptor.present {
+            self.pixels.clear();
+            self.pixels.extend(video.iter().map(|&pixel| pixel as u8));
+            present(
+                self.state,
+                self.pixels.as_ptr(),
+                width as u32,
+                height as u32,
+            );
+        }
+    }
+
+    pub fn audio(&mut self, sound_active: bool) {
+        if let Some(audio) = self.descriptor.audio {
+            audio(self.state, sound_active);
+        }
+    }
+
+    pub fn poll_input(&mut self, keypad: &mut [bool; KEY_COUNT]) {
+        if let Some(poll_input) = self.descriptor.poll_input {
+            poll_input(self.state, keypad.as_mut_ptr());
+        }
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.descriptor.shutdown {
+            shutdown(self.state);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::octocart::CartOptions;
+
+// Names `Quirks::preset` knows, and the older spellings of vip, schip-modern and xo-chip
+pub const PRESETS: [&str; 5] = ["default", "vip", "chip48", "schip-modern", "xo-chip"];
+pub const ALIASES: [&str; 3] = ["chip8", "schip", "xochip"];
+
+// Behaviours that differ between CHIP-8 interpreters. Every flag off is what this emulator
+// always did, so `Quirks::default()` keeps existing ROMs running unchanged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quirks {
+    // 8XY6/8XYE shift VY into VX instead of shifting VX in place
+    pub shift_uses_vy: bool,
+    // FX55/FX65 leave I pointing past the last register
+    pub load_store_increments_index: bool,
+    // BNNN becomes BXNN, jumping to XNN + VX
+    pub jump_uses_vx: bool,
+    // 8XY1/8XY2/8XY3 clear VF
+    pub logic_resets_vf: bool,
+    // Sprites are cut off at the screen edge instead of wrapping around
+    pub clip_sprites: bool,
+    // DRW waits for the next frame before the program continues
+    pub display_wait: bool,
+    // FX0A only returns once the pressed key is let go again
+    pub wait_key_release: bool,
+}
+
+impl Quirks {
+    // The original COSMAC VIP interpreter
+    pub fn chip8() -> Self {
+        Self {
+            shift_uses_vy: true,
+            load_store_increments_index: true,
+            jump_uses_vx: false,
+            logic_resets_vf: true,
+            clip_sprites: true,
+            display_wait: true,
+            wait_key_release: true,
+        }
+    }
+
+    // CHIP-48 on the HP48, the interpreter SUPER-CHIP grew out of. It moved I by X instead
+    // of X + 1 on FX55/FX65, the closest flag setting is leaving I alone.
+    pub fn chip48() -> Self {
+        Self {
+            shift_uses_vy: false,
+            load_store_increments_index: false,
+            jump_uses_vx: true,
+            logic_resets_vf: false,
+            clip_sprites: true,
+            display_wait: false,
+            wait_key_release: false,
+        }
+    }
+
+    // SUPER-CHIP 1.1 on the HP48, as modern ROMs written for it expect
+    pub fn superchip() -> Self {
+        Self {
+            shift_uses_vy: false,
+            load_store_increments_index: false,
+            jump_uses_vx: true,
+            logic_resets_vf: false,
+            clip_sprites: true,
+            display_wait: false,
+            wait_key_release: false,
+        }
+    }
+
+    // XO-CHIP as implemented by Octo
+    pub fn xochip() -> Self {
+        Self {
+            shift_uses_vy: true,
+            load_store_increments_index: true,
+            jump_uses_vx: false,
+            logic_resets_vf: false,
+            clip_sprites: false,
+            display_wait: false,
+            wait_key_release: true,
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "vip" | "chip8" => Some(Self::chip8()),
+            "chip48" => Some(Self::chip48()),
+            "schip-modern" | "schip" => Some(Self::superchip()),
+            "xo-chip" | "xochip" => Some(Self::xochip()),
+            _ => None,
+        }
+    }
+
+    // Octo names its flags after the deviation from the original interpreter, so the shift
+    // and load/store flags are inverted compared to ours
+    pub fn from_cart(options: &CartOptions) -> Self {
+        Self {
+            shift_uses_vy: !options.shift_quirks,
+            load_store_increments_index: !options.load_store_quirks,
+            jump_uses_vx: options.jump_quirks,
+            logic_resets_vf: options.logic_quirks,
+            clip_sprites: options.clip_quirks,
+            display_wait: options.vblank_quirks,
+            // Octo always waits for the release
+            wait_key_release: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_preset_and_alias_is_known() {
+        for name in PRESETS.iter().chain(&ALIASES) {
+            assert!(Quirks::preset(name).is_some(), "{}", name);
+        }
+        assert_eq!(Quirks::preset("schip"), Quirks::preset("schip-modern"));
+        assert_eq!(Quirks::preset("chip8"), Quirks::preset("vip"));
+        assert_eq!(Quirks::preset("xochip"), Quirks::preset("xo-chip"));
+    }
+}
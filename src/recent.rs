@@ -0,0 +1,63 @@
+// The ROMs played last, newest first, kept in a plain text file with one path per line. Started
+// without ROMs, the window offers them in the boot menu.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::keymap;
+
+pub const MAX_RECENT: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct RecentRoms {
+    path: PathBuf,
+    roms: Vec<String>,
+}
+
+impl RecentRoms {
+    // A missing file is an empty list
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let roms = match fs::read_to_string(&path) {
+            Ok(text) => text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .take(MAX_RECENT)
+                .map(str::to_string)
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self { path, roms })
+    }
+
+    pub fn roms(&self) -> &[String] {
+        &self.roms
+    }
+
+    // Moves `rom` to the front and saves the list. Paths are made absolute so the list still
+    // works from another directory.
+    pub fn add(&mut self, rom: &str) -> io::Result<()> {
+        let rom = fs::canonicalize(rom)?.to_string_lossy().into_owned();
+        self.roms.retain(|entry| *entry != rom);
+        self.roms.insert(0, rom);
+        self.roms.truncate(MAX_RECENT);
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text: String = self.roms.iter().map(|rom| format!("{}\n", rom)).collect();
+        fs::write(&self.path, text)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+// recent.txt in the same directory as keymap.toml
+pub fn default_path() -> PathBuf {
+    keymap::default_path().with_file_name("recent.txt")
+}
@@ -0,0 +1,62 @@
+// Keeps the last few seconds of emulation as save states so time can be rolled back frame
+// by frame while the rewind key is held.
+
+use std::collections::VecDeque;
+
+use crate::Chip8;
+use crate::state::SaveState;
+
+pub const DEFAULT_SECONDS: usize = 30;
+
+pub struct Rewind {
+    capacity: usize,
+    states: VecDeque<SaveState>,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self::new(DEFAULT_SECONDS * 60)
+    }
+}
+
+impl Rewind {
+    // `capacity` is in frames, the oldest state is dropped once it is reached
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            states: VecDeque::new(),
+        }
+    }
+
+    // MegaChip ROMs are left out, see `Chip8::supports_save_states`
+    pub fn record(&mut self, emu: &Chip8) {
+        if !emu.supports_save_states() {
+            return;
+        }
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back(emu.save_state());
+    }
+
+    // Restores the newest state and forgets it, false once the buffer is used up.
+    // Keys keep their current state so nothing is stuck down after letting go.
+    pub fn step_back(&mut self, emu: &mut Chip8) -> bool {
+        let Some(state) = self.states.pop_back() else {
+            return false;
+        };
+
+        let keypad = emu.keypad;
+        emu.load_state(&state);
+        emu.keypad = keypad;
+        true
+    }
+
+    pub fn frames(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+}
@@ -0,0 +1,251 @@
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::megachip;
+use crate::octocart::{self, CartOptions};
+use crate::rom_database::RomInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomFormat {
+    Binary,
+    Hex,
+    OctoCart,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedRom {
+    pub data: Vec<u8>,
+    pub cart_options: Option<CartOptions>,
+}
+
+impl RomFormat {
+    pub fn from_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("hex") | Some("txt") => RomFormat::Hex,
+            Some("gif") => RomFormat::OctoCart,
+            _ => RomFormat::Binary,
+        }
+    }
+
+    pub fn decode(self, data: Vec<u8>) -> io::Result<DecodedRom> {
+        match self {
+            RomFormat::Binary => Ok(DecodedRom {
+                data,
+                cart_options: None,
+            }),
+            RomFormat::Hex => {
+                let text = String::from_utf8(data).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "hex ROM is not text")
+                })?;
+                Ok(DecodedRom {
+                    data: parse_hex(&text)?,
+                    cart_options: None,
+                })
+            }
+            RomFormat::OctoCart => {
+                let cart = octocart::parse_cartridge(&data)?;
+                Ok(DecodedRom {
+                    data: octocart::assemble_literals(&cart.program)?,
+                    cart_options: Some(cart.options),
+                })
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for RomFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bin" | "binary" | "ch8" => Ok(RomFormat::Binary),
+            "hex" | "text" => Ok(RomFormat::Hex),
+            "octo" | "gif" | "cart" => Ok(RomFormat::OctoCart),
+            _ => Err(format!("unknown ROM format '{}'", s)),
+        }
+    }
+}
+
+// Parses listings like:
+//   0200: 6A 02 6B 0C  ; set up paddle
+//   # comments and blank lines are ignored
+// Address labels ending in ':' and '0x' prefixes are skipped, everything else must be hex digits.
+pub fn parse_hex(text: &str) -> io::Result<Vec<u8>> {
+    let mut digits = Vec::new();
+
+    for (line_number, line) in text.lines().enumerate() {
+        let code = line
+            .split(['#', ';'])
+            .next()
+            .unwrap_or("")
+            .split("//")
+            .next()
+            .unwrap_or("");
+
+        for token in code.split_whitespace() {
+            if token.ends_with(':') {
+                continue;
+            }
+
+            let token = token
+                .strip_prefix("0x")
+                .or_else(|| token.strip_prefix("0X"))
+                .unwrap_or(token);
+
+            for c in token.chars() {
+                let digit = c.to_digit(16).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid hex digit '{}' on line {}", c, line_number + 1),
+                    )
+                })?;
+                digits.push(digit as u8);
+            }
+        }
+    }
+
+    if digits.len() % 2 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "hex ROM has an odd number of digits",
+        ));
+    }
+
+    Ok(digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair[1])
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantHint {
+    SuperChip,
+    XoChip,
+    MegaChip,
+}
+
+impl std::fmt::Display for VariantHint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            VariantHint::SuperChip => write!(f, "SUPER-CHIP"),
+            VariantHint::XoChip => write!(f, "XO-CHIP"),
+            VariantHint::MegaChip => write!(f, "MegaChip"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadReport {
+    pub bytes_loaded: usize,
+    pub load_range: Range<usize>,
+    pub overlaps_font: bool,
+    pub reaches_memory_limit: bool,
+    pub variant_hints: Vec<VariantHint>,
+    pub cart_options: Option<CartOptions>,
+    // Preset picked for this ROM in the per-ROM quirks file
+    pub quirks_preset: Option<String>,
+    // What the built-in ROM database knows about it
+    pub rom_info: Option<&'static RomInfo>,
+}
+
+impl std::fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Loaded {} bytes at {:#05x}..{:#05x}",
+            self.bytes_loaded, self.load_range.start, self.load_range.end
+        )?;
+        if let Some(info) = self.rom_info {
+            write!(f, ", recognised as {}", info.title)?;
+        }
+        if self.overlaps_font {
+            write!(f, ", overlaps the font area")?;
+        }
+        if self.reaches_memory_limit {
+            write!(f, ", reaches the end of memory")?;
+        }
+        for hint in &self.variant_hints {
+            write!(f, ", looks like {}", hint)?;
+        }
+        if let Some(tickrate) = self.cart_options.as_ref().and_then(|o| o.tickrate) {
+            write!(f, ", cartridge recommends {} ticks per frame", tickrate)?;
+        }
+        if let Some(preset) = &self.quirks_preset {
+            write!(f, ", using {} quirks", preset)?;
+        }
+        Ok(())
+    }
+}
+
+impl LoadReport {
+    // Instructions per frame the cartridge or the ROM database asks for
+    pub fn tickrate(&self) -> Option<usize> {
+        self.cart_options
+            .as_ref()
+            .and_then(|options| options.tickrate)
+            .map(|tickrate| tickrate as usize)
+            .or_else(|| self.rom_info.and_then(|info| info.tickrate))
+    }
+}
+
+// Scans the ROM for opcodes that only exist in the extended instruction sets.
+// This is only a hint, data bytes can look like anything.
+pub fn detect_variants(rom: &[u8]) -> Vec<VariantHint> {
+    let mut hints = Vec::new();
+    let mut schip = false;
+    let mut xochip = false;
+
+    for pair in rom.chunks_exact(2) {
+        let opcode = u16::from_be_bytes([pair[0], pair[1]]);
+
+        match opcode {
+            0x00FB..=0x00FF => schip = true,
+            op if op & 0xFFF0 == 0x00C0 => schip = true,
+            op if op & 0xF0FF == 0xF030 || op & 0xF0FF == 0xF075 || op & 0xF0FF == 0xF085 => {
+                schip = true
+            }
+            0xF000 | 0xF002 => xochip = true,
+            op if op & 0xFFF0 == 0x00D0 => xochip = true,
+            op if op & 0xF00F == 0x5002 || op & 0xF00F == 0x5003 => xochip = true,
+            op if op & 0xF0FF == 0xF001 || op & 0xF0FF == 0xF03A => xochip = true,
+            _ => (),
+        }
+    }
+
+    if schip {
+        hints.push(VariantHint::SuperChip);
+    }
+    if xochip {
+        hints.push(VariantHint::XoChip);
+    }
+    if megachip::is_megachip_rom(rom) {
+        hints.push(VariantHint::MegaChip);
+    }
+
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_roms_skip_labels_comments_and_prefixes() {
+        let text =
+            "start:\n  00E0 0x6105 # clear, V1 = 5\n; whole line comment\n12 00 // jump back\n";
+        assert_eq!(
+            parse_hex(text).unwrap(),
+            [0x00, 0xE0, 0x61, 0x05, 0x12, 0x00]
+        );
+
+        assert!(parse_hex("00E").is_err());
+        let error = parse_hex("00E0\n60zz").unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+    }
+}
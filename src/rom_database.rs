@@ -0,0 +1,75 @@
+// Known ROMs looked up by SHA-1 when they load, so they get a proper window title and the
+// quirks, speed and colours they were written for. The database is rom_database.json built
+// into the binary, in the shape of the community CHIP-8 database trimmed down to what is
+// used here:
+//
+//     {
+//         "<sha1>": {
+//             "title": "Some Game",
+//             "quirks": "schip-modern",
+//             "tickrate": 30,
+//             "palette": "#000000,#ffcc00"
+//         }
+//     }
+//
+// Everything but the title is optional. Unknown ROMs keep the defaults, and anything given
+// on the command line wins over the database.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::palette::Palette;
+use crate::quirks::Quirks;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RomInfo {
+    pub title: String,
+    // A `Quirks::preset` name
+    pub quirks: Option<String>,
+    pub tickrate: Option<usize>,
+    // Anything `--palette` accepts
+    pub palette: Option<String>,
+}
+
+impl RomInfo {
+    pub fn quirks(&self) -> Option<Quirks> {
+        Quirks::preset(self.quirks.as_deref()?)
+    }
+
+    pub fn palette(&self) -> Option<Palette> {
+        Palette::parse(self.palette.as_deref()?)
+    }
+}
+
+// Which database settings get applied, each is switched off when its command line flag is
+// given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UseDatabase {
+    pub quirks: bool,
+    pub tickrate: bool,
+    pub palette: bool,
+}
+
+impl Default for UseDatabase {
+    fn default() -> Self {
+        Self {
+            quirks: true,
+            tickrate: true,
+            palette: true,
+        }
+    }
+}
+
+fn database() -> &'static HashMap<String, RomInfo> {
+    static DATABASE: OnceLock<HashMap<String, RomInfo>> = OnceLock::new();
+    DATABASE.get_or_init(|| {
+        serde_json::from_str(include_str!("rom_database.json"))
+            .expect("rom_database.json is not a valid ROM database")
+    })
+}
+
+pub fn lookup(hash: &str) -> Option<&'static RomInfo> {
+    database().get(hash)
+}
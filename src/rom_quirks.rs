@@ -0,0 +1,67 @@
+// Quirk presets remembered per ROM, so games that need particular quirks get them without
+// passing `--quirks` every time. Read from quirks.toml next to the keymap, keyed by the SHA-1
+// of the ROM (the sha1 column of a `batch` report):
+//
+//     [roms]
+//     "0a1b2c..." = "chip48"
+//     "9f8e7d..." = "schip-modern"
+//
+// `--quirks` on the command line wins over the file.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::keymap;
+use crate::quirks::Quirks;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RomQuirks {
+    presets: HashMap<String, String>,
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+impl RomQuirks {
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let table: toml::Table = text.parse().map_err(|e| invalid(format!("{}", e)))?;
+        let mut rom_quirks = Self::default();
+
+        let Some(roms) = table.get("roms") else {
+            return Ok(rom_quirks);
+        };
+        let roms = roms
+            .as_table()
+            .ok_or_else(|| invalid("'roms' must be a table"))?;
+
+        for (hash, preset) in roms {
+            let preset = preset
+                .as_str()
+                .filter(|name| Quirks::preset(name).is_some())
+                .ok_or_else(|| invalid(format!("ROM {} needs a quirk preset name", hash)))?;
+            rom_quirks
+                .presets
+                .insert(hash.to_lowercase(), preset.to_string());
+        }
+
+        Ok(rom_quirks)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    // The preset name and its quirks for a ROM hash
+    pub fn get(&self, hash: &str) -> Option<(&str, Quirks)> {
+        let name = self.presets.get(hash)?;
+        Some((name, Quirks::preset(name)?))
+    }
+}
+
+// quirks.toml in the same directory as keymap.toml
+pub fn default_path() -> PathBuf {
+    keymap::default_path().with_file_name("quirks.toml")
+}
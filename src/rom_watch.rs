@@ -0,0 +1,51 @@
+// Notices when the running ROM's file changes on disk, so a ROM being worked on in Octo or an
+// assembler reloads as soon as it is saved. Polls the modification time a couple of times a
+// second, which needs nothing from the platform and is cheap next to a frame.
+
+use std::fs;
+use std::time::SystemTime;
+
+// Frames between looks at the file
+const POLL_FRAMES: u32 = 30;
+
+#[derive(Debug, Default)]
+pub struct RomWatcher {
+    path: Option<String>,
+    modified: Option<SystemTime>,
+    frames_left: u32,
+}
+
+impl RomWatcher {
+    // Called every frame with the running ROM's file, returns the file to reload once it
+    // changed. None keeps watching the last one, so a save that fails to load is retried on
+    // the next.
+    pub fn poll(&mut self, rom: Option<&str>) -> Option<String> {
+        if let Some(rom) = rom
+            && self.path.as_deref() != Some(rom)
+        {
+            self.path = Some(rom.to_string());
+            self.modified = modified(rom);
+            self.frames_left = POLL_FRAMES;
+            return None;
+        }
+
+        if self.frames_left > 0 {
+            self.frames_left -= 1;
+            return None;
+        }
+        self.frames_left = POLL_FRAMES;
+
+        let path = self.path.as_deref()?;
+        // Editors that save by replacing the file leave it missing for a moment
+        let modified = modified(path)?;
+        if self.modified == Some(modified) {
+            return None;
+        }
+        self.modified = Some(modified);
+        Some(path.to_string())
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
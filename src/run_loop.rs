@@ -0,0 +1,791 @@
+// The frame loop interactive frontends share: pacing, hotkeys, the debugger, save slots,
+// rewind, the playlist, kiosk mode and crash handling. Where frames are drawn, keys come from
+// and sound goes is up to the drivers it is given.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+use crate::chrome_trace::TraceRecorder;
+use crate::drivers::{AudioDriver, DisplayDriver, InputDriver, InputEvent};
+use crate::error::Chip8Error;
+use crate::gif_recorder::{self, DEFAULT_GIF_SCALE, GifRecorder};
+use crate::keymap::KeyMap;
+use crate::kiosk::{Kiosk, KioskAction};
+use crate::pacing::{CpuClock, FRAMES_PER_SECOND, Pacer, RateMeter};
+use crate::playlist::Playlist;
+#[cfg(feature = "plugins")]
+use crate::plugin::Plugin;
+use crate::recent::RecentRoms;
+use crate::rom_database::RomInfo;
+use crate::rom_watch::RomWatcher;
+use crate::state::{self, SaveState};
+#[cfg(feature = "tui")]
+use crate::tui_debugger::TuiDebugger;
+use crate::turbo::Turbo;
+use crate::{Chip8, boot_menu, crash_dump, debugger, splash, watchdog};
+
+// What the speed hotkeys add or take away, one instruction a frame
+const SPEED_STEP_HZ: u32 = 60;
+pub const DEFAULT_RUMBLE_INTENSITY: f32 = 0.5;
+
+// Everything the frontend does around the emulator that the core in `Chip8` doesn't need
+pub struct FrontendOptions {
+    // Remembers every ROM the window starts, None leaves the list alone
+    pub recent_roms: Option<RecentRoms>,
+    // Start on the built-in ROM picker instead of the first playlist entry, Home returns to it
+    pub boot_menu: bool,
+    // Pause while the window is in the background or minimized
+    pub pause_unfocused: bool,
+    // Reload the ROM whenever its file changes
+    pub watch_rom: bool,
+    // Gamepad rumble strength while the sound timer runs, None disables it
+    pub rumble_intensity: Option<f32>,
+    pub keymap: KeyMap,
+    // (SDL button name, key) pairs overriding the frontend's default gamepad mapping
+    pub gamepad_remap: Vec<(String, usize)>,
+    // Play the buzzer on the default audio device, see `Chip8::buzzer` for pitch and volume
+    pub sound: bool,
+    // Show the terminal debugger alongside the SDL window
+    #[cfg(feature = "tui")]
+    pub tui_debugger: bool,
+    #[cfg(feature = "plugins")]
+    pub plugins: Vec<Plugin>,
+    pub gif_recorder: Option<GifRecorder>,
+    // Attract mode cycling through the playlist on its own, see `Kiosk`
+    pub kiosk: Option<Kiosk>,
+}
+
+impl Default for FrontendOptions {
+    fn default() -> Self {
+        Self {
+            recent_roms: None,
+            boot_menu: false,
+            pause_unfocused: true,
+            watch_rom: false,
+            rumble_intensity: Some(DEFAULT_RUMBLE_INTENSITY),
+            keymap: KeyMap::default(),
+            gamepad_remap: Vec::new(),
+            sound: true,
+            #[cfg(feature = "tui")]
+            tui_debugger: false,
+            #[cfg(feature = "plugins")]
+            plugins: Vec::new(),
+            gif_recorder: None,
+            kiosk: None,
+        }
+    }
+}
+
+impl FrontendOptions {
+    // The menu isn't a loaded ROM, so it is the only time there is no ROM hash
+    pub fn in_boot_menu(&self, emu: &Chip8) -> bool {
+        self.boot_menu && emu.rom_hash.is_none()
+    }
+
+    // Call once per emulated frame, stops recording if the file can't be written
+    pub fn capture_gif_frame(&mut self, emu: &Chip8) {
+        if let Some(recorder) = &mut self.gif_recorder
+            && let Err(e) = recorder.capture(emu.video.pixels(), emu.video.width())
+        {
+            eprintln!("GIF recording stopped: {}", e);
+            self.gif_recorder = None;
+        }
+    }
+
+    pub fn finish_gif(&mut self) {
+        if let Some(recorder) = self.gif_recorder.take() {
+            let path = recorder.path().to_path_buf();
+            match recorder.finish() {
+                Ok(path) => eprintln!("GIF written to {}", path.display()),
+                Err(e) => eprintln!("Failed to write GIF {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+pub fn run<D: DisplayDriver, I: InputDriver, A: AudioDriver>(
+    emu: &mut Chip8,
+    options: &mut FrontendOptions,
+    playlist: &mut Playlist,
+    cpu_hz: u32,
+    display: &mut D,
+    input: &mut I,
+    audio: &mut A,
+) -> Result<(), Chip8Error> {
+    #[cfg(feature = "tui")]
+    let mut tui = if options.tui_debugger {
+        TuiDebugger::new()
+            .map_err(|e| eprintln!("Terminal debugger disabled: {}", e))
+            .ok()
+    } else {
+        None
+    };
+
+    // `cpu_hz` stays what ROMs the database has no speed for go back to
+    let mut clock = CpuClock::new(cpu_hz);
+
+    if playlist.is_empty() {
+        show_splash(emu, display);
+        if let Some(rom) = input.pick_rom() {
+            open_rom(emu, options, playlist, &rom, display, cpu_hz, &mut clock)?;
+        }
+    } else if options.boot_menu {
+        show_boot_menu(emu, playlist, display);
+    } else if options.kiosk.is_some() {
+        start_kiosk_rom(emu, options, playlist, display, cpu_hz, &mut clock);
+    } else {
+        let rom = playlist.current().expect("playlist is empty").to_string();
+        switch_rom(emu, options, &rom, display, cpu_hz, &mut clock)?;
+    }
+
+    let mut pacer = Pacer::default();
+    pacer.set_vsync(display.paces_frames());
+    let mut rate_meter = RateMeter::default();
+    let mut events = Vec::new();
+    let mut frame: u64 = 0;
+    let mut slot = 0;
+    let mut rewinding = false;
+    // Paused by losing focus rather than by the player, so focus coming back resumes
+    let mut unfocused = false;
+    let mut watcher = options.watch_rom.then(RomWatcher::default);
+    let mut turbo = Turbo::new(options.keymap.turbo_rate);
+
+    loop {
+        let frame_start = std::time::Instant::now();
+        let frame_trace_start = emu.tracer.as_ref().map(TraceRecorder::now);
+        if let Some(rates) = rate_meter.frame() {
+            display.show_speed(clock.hz(), rates);
+        }
+
+        input.poll(&mut events);
+        for event in events.drain(..) {
+            match event {
+                InputEvent::Key { key, pressed } | InputEvent::SecondKey { key, pressed } => {
+                    if let Some(kiosk) = &mut options.kiosk {
+                        if pressed && kiosk.is_attracting() {
+                            emu.clear_keys();
+                        }
+                        kiosk.user_activity();
+                    }
+                    if let InputEvent::SecondKey { .. } = event {
+                        emu.keypress2(key, pressed);
+                    } else {
+                        emu.keypress(key, pressed);
+                    }
+                }
+                InputEvent::Turbo { key, held } => turbo.hold(emu, key, held),
+                InputEvent::Quit => {
+                    if let Some(log) = &mut emu.event_log {
+                        log.halt("quit", frame);
+                    }
+                    options.finish_gif();
+                    finish_session(emu);
+                    return Ok(());
+                }
+                InputEvent::NextRom | InputEvent::PreviousRom if playlist.len() > 1 => {
+                    let rom = if event == InputEvent::NextRom {
+                        playlist.advance()
+                    } else {
+                        playlist.go_back()
+                    };
+                    let rom = rom.unwrap_or_default().to_string();
+                    switch_rom(emu, options, &rom, display, cpu_hz, &mut clock)?;
+                    pacer.resume();
+                }
+                InputEvent::BootMenu if options.boot_menu => {
+                    show_boot_menu(emu, playlist, display);
+                    pacer.resume();
+                }
+                InputEvent::OpenRom(rom) => {
+                    open_rom(emu, options, playlist, &rom, display, cpu_hz, &mut clock)?;
+                    pacer.resume();
+                }
+                InputEvent::PickRom => {
+                    if let Some(rom) = input.pick_rom() {
+                        open_rom(emu, options, playlist, &rom, display, cpu_hz, &mut clock)?;
+                        pacer.resume();
+                    }
+                }
+                // The splash screen has no ROM to start over
+                InputEvent::Reset | InputEvent::ReloadRom if playlist.is_empty() => {}
+                InputEvent::Reset | InputEvent::ReloadRom if options.in_boot_menu(emu) => {
+                    show_boot_menu(emu, playlist, display);
+                    pacer.resume();
+                }
+                InputEvent::Reset => {
+                    // Persistent RAM survives the reset button like it would on a cartridge
+                    save_persistent_ram(emu);
+                    emu.restart();
+                    restore_persistent_ram(emu);
+                    notify(display, "Reset");
+                    pacer.resume();
+                }
+                InputEvent::ReloadRom => {
+                    if let Some(rom) = emu.rom_path.clone() {
+                        reload_rom(emu, options, &rom, display, cpu_hz, &mut clock, &mut pacer);
+                    }
+                }
+                InputEvent::Rewind(held) => rewinding = held,
+                InputEvent::SaveState => save_slot(emu, slot, display),
+                InputEvent::NextSlot => {
+                    slot = (slot + 1) % state::SLOT_COUNT;
+                    notify(display, &format!("Save slot {}", slot));
+                }
+                InputEvent::LoadState => {
+                    load_slot(emu, slot, display);
+                    pacer.resume();
+                }
+                InputEvent::ToggleBreakpoint => {
+                    let pc = emu.pc;
+                    if emu.debug.toggle_breakpoint(pc) {
+                        eprintln!("Breakpoint set at {:#05x}", pc);
+                    } else {
+                        eprintln!("Breakpoint cleared at {:#05x}", pc);
+                    }
+                }
+                InputEvent::ToggleDebugPause => {
+                    emu.debug.toggle_pause();
+                    if emu.debug.is_paused() {
+                        eprintln!("Paused at {}", debugger::describe(emu));
+                    } else {
+                        eprintln!("Resumed");
+                    }
+                }
+                InputEvent::TogglePause => {
+                    pacer.toggle_pause();
+                    unfocused = false;
+                    notify(
+                        display,
+                        if pacer.is_paused() {
+                            "Paused"
+                        } else {
+                            "Resumed"
+                        },
+                    );
+                }
+                InputEvent::AdvanceFrame if pacer.is_paused() => pacer.advance_frame(),
+                InputEvent::Focus(false) => {
+                    // Keys let go in another window never come back up here
+                    emu.clear_keys();
+                    rewinding = false;
+                    if options.pause_unfocused && options.kiosk.is_none() && !pacer.is_paused() {
+                        pacer.pause();
+                        unfocused = true;
+                        notify(display, "Paused in the background");
+                    }
+                }
+                InputEvent::Focus(true) if unfocused => {
+                    pacer.resume();
+                    unfocused = false;
+                    notify(display, "Resumed");
+                }
+                InputEvent::FastForward(held) => {
+                    pacer.set_fast_forward(held);
+                    display.handle(emu, event);
+                }
+                InputEvent::SpeedUp => {
+                    clock.set_hz(clock.hz() + SPEED_STEP_HZ);
+                    notify(display, &format!("Speed: {} Hz", clock.hz()));
+                }
+                InputEvent::SlowDown => {
+                    let hz = clock.hz().saturating_sub(SPEED_STEP_HZ).max(SPEED_STEP_HZ);
+                    clock.set_hz(hz);
+                    notify(display, &format!("Speed: {} Hz", clock.hz()));
+                }
+                InputEvent::ToggleMute => {
+                    emu.buzzer.muted = !emu.buzzer.muted;
+                    notify(
+                        display,
+                        if emu.buzzer.muted {
+                            "Sound muted"
+                        } else {
+                            "Sound on"
+                        },
+                    );
+                }
+                InputEvent::Step(count) => emu.debug.step(count),
+                InputEvent::ToggleGifRecording => toggle_gif_recording(emu, options, display),
+                event => display.handle(emu, event),
+            }
+        }
+
+        if let Some(watcher) = &mut watcher
+            && !options.in_boot_menu(emu)
+            && let Some(rom) = watcher.poll(emu.rom_path.as_deref())
+        {
+            reload_rom(emu, options, &rom, display, cpu_hz, &mut clock, &mut pacer);
+        }
+
+        emu.process_injected_keys();
+
+        #[cfg(feature = "tui")]
+        if let Some(debugger) = &mut tui
+            && let Err(e) = debugger.update(emu)
+        {
+            tui = None;
+            eprintln!("Terminal debugger closed: {}", e);
+        }
+
+        #[cfg(feature = "plugins")]
+        for plugin in &mut options.plugins {
+            plugin.poll_input(&mut emu.keypad);
+        }
+
+        let kiosk_action = options
+            .kiosk
+            .as_mut()
+            .map(|kiosk| kiosk.update(&mut emu.keypad));
+        if kiosk_action == Some(KioskAction::NextRom) {
+            playlist.advance();
+            start_kiosk_rom(emu, options, playlist, display, cpu_hz, &mut clock);
+            pacer.resume();
+        }
+
+        if let Some(slow_draw) = &mut emu.slow_draw
+            && slow_draw.is_animating()
+        {
+            slow_draw.advance();
+            display.draw(emu)?;
+            pacer.wait(frame_start);
+            continue;
+        }
+
+        // Rewinding runs the recorded frames backwards at normal speed
+        if rewinding && let Some(mut rewind) = emu.rewind.take() {
+            rewind.step_back(emu);
+            emu.rewind = Some(rewind);
+            pacer.resume();
+            display.draw(emu)?;
+            pacer.wait(frame_start);
+            continue;
+        }
+
+        // The debugger's pause wins, a frame advance stays queued until it resumes
+        if emu.debug.is_paused() || !pacer.next_frame() {
+            if emu.run_pending_steps() > 0 {
+                if let Some(hit) = emu.debug.take_watch_hit() {
+                    eprintln!("{}", hit);
+                }
+                eprintln!("{}", debugger::describe(emu));
+            }
+            input.rumble(false);
+            audio.update(&mut emu.buzzer);
+            display.draw(emu)?;
+            pacer.wait(frame_start);
+            continue;
+        }
+
+        turbo.frame(emu);
+        if let Some(notice) = emu.movie_frame() {
+            notify(display, &notice.to_string());
+        }
+        emu.cheats.apply(&mut emu.memory);
+        let batch_start = emu.tracer.as_ref().map(TraceRecorder::now);
+        let instructions = clock.next_frame();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| emu.run_cycles(instructions)));
+
+        let executed = match result {
+            Ok(executed) => executed,
+            // A kiosk can't stop for one broken ROM, the crash halts it like any other error
+            Err(payload) if options.kiosk.is_some() => {
+                let reason = crash_dump::panic_message(payload.as_ref());
+                emu.halted = Some(Chip8Error::Crashed(reason.to_string()));
+                0
+            }
+            Err(payload) => {
+                let reason = crash_dump::panic_message(payload.as_ref());
+                let pc = emu.pc;
+                if let Some(log) = &mut emu.event_log {
+                    log.error(reason, pc);
+                    log.halt("crash", frame);
+                }
+                if let Some(metrics) = &emu.metrics {
+                    metrics.errors.fetch_add(1, Ordering::Relaxed);
+                }
+
+                options.finish_gif();
+                save_movie(emu);
+                write_trace(emu);
+                match crash_dump::write_crash_dump(emu, reason) {
+                    Ok(path) => {
+                        eprintln!("Emulation crashed, dump written to {}", path.display())
+                    }
+                    Err(e) => eprintln!("Emulation crashed, failed to write dump: {}", e),
+                }
+
+                panic::resume_unwind(payload);
+            }
+        };
+        if let Some(tracer) = &mut emu.tracer
+            && let Some(start) = batch_start
+        {
+            tracer.instruction_batch(executed, start);
+        }
+        report_skipped_opcodes(emu);
+        if let Some(hit) = emu.debug.take_watch_hit() {
+            eprintln!("{}, paused at {}", hit, debugger::describe(emu));
+        } else if emu.debug.is_paused() {
+            eprintln!("Breakpoint hit at {}", debugger::describe(emu));
+        }
+        if let Some(error) = emu.halted.take() {
+            if let Some(log) = &mut emu.event_log {
+                log.error(&error.to_string(), emu.pc);
+                let reason = match error {
+                    Chip8Error::StackOverflow { .. } => "stack-overflow",
+                    Chip8Error::StackUnderflow { .. } => "stack-underflow",
+                    Chip8Error::ProtectedWrite { .. } => "protected-write",
+                    Chip8Error::MemoryFault { .. } => "memory-fault",
+                    Chip8Error::InvalidOpcode(_) => "unknown-opcode",
+                    Chip8Error::RomTooLarge { .. } => "rom-too-large",
+                    Chip8Error::Crashed(_) => "crash",
+                    Chip8Error::Sdl(_) | Chip8Error::Io(_) => "io",
+                };
+                log.halt(reason, frame);
+            }
+            write_halt_dump(emu, &error);
+            if options.kiosk.is_none() {
+                options.finish_gif();
+                finish_session(emu);
+                return Err(error);
+            }
+            eprintln!("{}, skipping to the next ROM", error);
+            playlist.advance();
+            start_kiosk_rom(emu, options, playlist, display, cpu_hz, &mut clock);
+            pacer.wait(frame_start);
+            continue;
+        }
+
+        if options.in_boot_menu(emu)
+            && let Some(choice) = boot_menu::selection(emu)
+        {
+            let rom = playlist.select(choice).unwrap_or_default().to_string();
+            switch_rom(emu, options, &rom, display, cpu_hz, &mut clock)?;
+        }
+
+        emu.tick_timers();
+        options.capture_gif_frame(emu);
+        if let Some(mut rewind) = emu.rewind.take() {
+            rewind.record(emu);
+            emu.rewind = Some(rewind);
+        }
+        let sound_active = emu.sound_active();
+        input.rumble(sound_active);
+        audio.update(&mut emu.buzzer);
+        display.draw(emu)?;
+        let (width, height) = (emu.display_width(), emu.display_height());
+        let video = emu.video.pixels();
+        if let Some(shm) = &mut emu.shared_framebuffer {
+            shm.publish(video, width, height);
+        }
+        #[cfg(feature = "plugins")]
+        for plugin in &mut options.plugins {
+            plugin.present(video, width, height);
+            plugin.audio(sound_active);
+        }
+
+        frame += 1;
+        let pc = emu.pc;
+        if let Some(log) = &mut emu.event_log {
+            log.frame(frame, pc);
+        }
+        rate_meter.add_instructions(executed);
+        if let Some(metrics) = &emu.metrics {
+            metrics.frames.fetch_add(1, Ordering::Relaxed);
+            metrics.instructions.fetch_add(executed, Ordering::Relaxed);
+        }
+        if let Some(tracer) = &mut emu.tracer
+            && let Some(start) = frame_trace_start
+        {
+            tracer.frame(frame, start);
+        }
+
+        if emu.watchdog_tripped() {
+            eprintln!("{}", watchdog::diagnostics(emu));
+            notify(display, "Emulation paused");
+            if let Some(log) = &mut emu.event_log {
+                log.halt("watchdog", frame);
+            }
+            pacer.pause();
+        }
+
+        if !pacer.wait(frame_start)
+            && let Some(metrics) = &emu.metrics
+        {
+            metrics.dropped_frames.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn show_splash(emu: &mut Chip8, display: &mut impl DisplayDriver) {
+    splash::load(emu);
+    display.set_title("Drop a ROM on the window");
+}
+
+fn show_boot_menu(emu: &mut Chip8, playlist: &Playlist, display: &mut impl DisplayDriver) {
+    save_persistent_ram(emu);
+    emu.show_boot_menu(playlist);
+    display.set_title("Boot Menu");
+}
+
+fn switch_rom(
+    emu: &mut Chip8,
+    options: &mut FrontendOptions,
+    rom: &str,
+    display: &mut impl DisplayDriver,
+    default_hz: u32,
+    clock: &mut CpuClock,
+) -> Result<(), Chip8Error> {
+    // Save the outgoing ROM's RAM before loading clears it
+    save_persistent_ram(emu);
+    let report = emu.switch_rom(rom)?;
+    eprintln!("{}: {}", rom, report);
+    restore_persistent_ram(emu);
+    if let Some(kiosk) = &mut options.kiosk {
+        kiosk.rom_switched();
+        match kiosk.load_movie(rom) {
+            Ok(Some(seed)) => emu.seed_rng(seed),
+            Ok(None) => (),
+            Err(e) => eprintln!("Failed to read demo movie for {}: {}", rom, e),
+        }
+    }
+    if let Some(recent) = &mut options.recent_roms
+        && let Err(e) = recent.add(rom)
+    {
+        eprintln!("Failed to update {}: {}", recent.path().display(), e);
+    }
+
+    if emu.rom_database.tickrate {
+        let hz = report
+            .tickrate()
+            .map_or(default_hz, |ticks| ticks as u32 * FRAMES_PER_SECOND as u32);
+        clock.set_hz(hz);
+    }
+    if emu.rom_database.palette {
+        display.set_rom_palette(report.rom_info.and_then(RomInfo::palette));
+    }
+
+    let name = match report.rom_info {
+        Some(info) => info.title.clone(),
+        None => Path::new(rom)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    display.set_title(&name);
+    Ok(())
+}
+
+// Starts the playlist's current ROM, or the first one after it that loads. A kiosk keeps going
+// past ROMs that don't, and with none left waits on the splash screen for the next turn.
+fn start_kiosk_rom(
+    emu: &mut Chip8,
+    options: &mut FrontendOptions,
+    playlist: &mut Playlist,
+    display: &mut impl DisplayDriver,
+    default_hz: u32,
+    clock: &mut CpuClock,
+) {
+    for _ in 0..playlist.len() {
+        let rom = playlist.current().unwrap_or_default().to_string();
+        match switch_rom(emu, options, &rom, display, default_hz, clock) {
+            Ok(()) => return,
+            Err(e) => eprintln!("Failed to load {}: {}, skipping it", rom, e),
+        }
+        playlist.advance();
+    }
+
+    show_splash(emu, display);
+    if let Some(kiosk) = &mut options.kiosk {
+        kiosk.rom_switched();
+    }
+}
+
+// Starts a ROM from outside the playlist, which then gets it as its current entry
+fn open_rom(
+    emu: &mut Chip8,
+    options: &mut FrontendOptions,
+    playlist: &mut Playlist,
+    rom: &str,
+    display: &mut impl DisplayDriver,
+    default_hz: u32,
+    clock: &mut CpuClock,
+) -> Result<(), Chip8Error> {
+    match switch_rom(emu, options, rom, display, default_hz, clock) {
+        Ok(()) => playlist.open(rom),
+        Err(e) => {
+            notify(display, &format!("Failed to load {}: {}", rom, e));
+            // Loading cleared the machine, go back to what it showed before
+            match playlist.current() {
+                Some(current) => {
+                    let current = current.to_string();
+                    switch_rom(emu, options, &current, display, default_hz, clock)?;
+                }
+                None => show_splash(emu, display),
+            }
+        }
+    }
+    Ok(())
+}
+
+// Reads the ROM from disk again. One that doesn't load any more leaves the emulator paused on a
+// blank screen until it is fixed.
+fn reload_rom(
+    emu: &mut Chip8,
+    options: &mut FrontendOptions,
+    rom: &str,
+    display: &mut impl DisplayDriver,
+    default_hz: u32,
+    clock: &mut CpuClock,
+    pacer: &mut Pacer,
+) {
+    match switch_rom(emu, options, rom, display, default_hz, clock) {
+        Ok(()) => {
+            notify(display, "ROM reloaded");
+            pacer.resume();
+        }
+        Err(e) => {
+            notify(display, &format!("Failed to reload ROM: {}", e));
+            pacer.pause();
+        }
+    }
+}
+
+// Saves what the session kept, persistent RAM, a movie being recorded and traces, once
+// emulation stops
+pub fn finish_session(emu: &mut Chip8) {
+    save_persistent_ram(emu);
+    save_movie(emu);
+    write_trace(emu);
+}
+
+pub fn save_persistent_ram(emu: &Chip8) {
+    match emu.save_persistent_ram() {
+        Ok(Some(path)) => eprintln!("Saved RAM to {}", path.display()),
+        Ok(None) => (),
+        Err(e) => eprintln!("Failed to save RAM: {}", e),
+    }
+}
+
+pub fn restore_persistent_ram(emu: &mut Chip8) {
+    match emu.restore_persistent_ram() {
+        Ok(Some(path)) => eprintln!("Restored RAM from {}", path.display()),
+        Ok(None) => (),
+        Err(e) => eprintln!("Failed to restore RAM: {}", e),
+    }
+}
+
+fn save_movie(emu: &Chip8) {
+    match emu.save_movie() {
+        Ok(Some(path)) => eprintln!("Movie written to {}", path.display()),
+        Ok(None) => (),
+        Err(e) => eprintln!("Failed to write movie {}", e),
+    }
+}
+
+fn write_trace(emu: &mut Chip8) {
+    match emu.write_trace() {
+        Ok(()) => {
+            if let Some(tracer) = &emu.tracer {
+                eprintln!("Trace written to {}", tracer.path().display());
+            }
+        }
+        Err(e) => eprintln!("Failed to write trace {}", e),
+    }
+}
+
+// For errors that halt emulation, written before the run returns them
+pub fn write_halt_dump(emu: &Chip8, error: &Chip8Error) {
+    match crash_dump::write_crash_dump(emu, &error.to_string()) {
+        Ok(path) => eprintln!("Emulation halted, dump written to {}", path.display()),
+        Err(e) => eprintln!("Emulation halted, failed to write dump: {}", e),
+    }
+}
+
+pub fn report_skipped_opcodes(emu: &mut Chip8) {
+    for unknown in emu.skipped_opcodes.drain(..) {
+        eprintln!("{}, skipping it", unknown);
+    }
+}
+
+// Prints `message` and puts it on screen
+fn notify(display: &mut impl DisplayDriver, message: &str) {
+    eprintln!("{}", message);
+    display.show_message(message);
+}
+
+fn toggle_gif_recording(
+    emu: &Chip8,
+    options: &mut FrontendOptions,
+    display: &mut impl DisplayDriver,
+) {
+    if options.gif_recorder.is_some() {
+        options.finish_gif();
+        display.show_message("Recording stopped");
+        return;
+    }
+
+    let path = gif_recorder::default_path();
+    let screen = (emu.display_width(), emu.display_height());
+    match GifRecorder::create(&path, &display.palette(), screen, DEFAULT_GIF_SCALE) {
+        Ok(recorder) => {
+            eprintln!("Recording GIF to {}, Shift+F12 stops", path.display());
+            display.show_message("Recording GIF");
+            options.gif_recorder = Some(recorder);
+        }
+        Err(e) => {
+            eprintln!("Failed to start GIF recording {}: {}", path.display(), e);
+            display.show_message("Recording failed");
+        }
+    }
+}
+
+fn save_slot(emu: &mut Chip8, slot: usize, display: &mut impl DisplayDriver) {
+    let Some(hash) = emu.rom_hash.clone() else {
+        notify(display, "No ROM running, nothing to save");
+        return;
+    };
+    if !emu.supports_save_states() {
+        notify(display, "Save states don't work with MegaChip ROMs");
+        return;
+    }
+
+    let path = state::slot_path(&hash, slot);
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| emu.save_state().write(&path));
+    match result {
+        Ok(()) => {
+            eprintln!("Saved slot {} to {}", slot, path.display());
+            display.show_message(&format!("State saved to slot {}", slot));
+            if let Some(log) = &mut emu.event_log {
+                log.state_saved(slot, &path.to_string_lossy());
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to save slot {}: {}", slot, e);
+            display.show_message(&format!("Failed to save slot {}", slot));
+        }
+    }
+}
+
+fn load_slot(emu: &mut Chip8, slot: usize, display: &mut impl DisplayDriver) {
+    let Some(hash) = emu.rom_hash.clone() else {
+        notify(display, "No ROM running, nothing to load");
+        return;
+    };
+
+    let path = state::slot_path(&hash, slot);
+    match SaveState::read(&path) {
+        Ok(state) => {
+            emu.load_state(&state);
+            eprintln!("Loaded slot {} from {}", slot, path.display());
+            display.show_message(&format!("State loaded from slot {}", slot));
+        }
+        Err(e) => {
+            eprintln!("Failed to load slot {}: {}", slot, e);
+            display.show_message(&format!("Failed to load slot {}", slot));
+        }
+    }
+}
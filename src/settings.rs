@@ -0,0 +1,44 @@
+// Preferences the window remembers between runs, in settings.toml next to keymap.toml. Only
+// what can be changed with a hotkey while playing ends up here, everything else is a
+// command-line option.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::keymap;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // The scanline filter, F3 toggles it
+    pub crt: bool,
+}
+
+impl Settings {
+    // A missing file has the defaults
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                toml::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)
+    }
+}
+
+// settings.toml in the same directory as keymap.toml
+pub fn default_path() -> PathBuf {
+    keymap::default_path().with_file_name("settings.toml")
+}
@@ -0,0 +1,93 @@
+// Debug mode that animates DRW row by row so sprite placement and collisions can be watched
+// instead of inferred from VF.
+
+pub const DEFAULT_FRAMES_PER_ROW: u32 = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpriteDraw {
+    pub x: usize,
+    pub y: usize,
+    // 8, or 16 for SUPER-CHIP DXY0 sprites
+    pub width: usize,
+    // Sprite rows left aligned in 16 bits
+    pub rows: Vec<u16>,
+    pub collided: bool,
+    // Pixels past the screen edge are dropped instead of wrapped, see `Quirks::clip_sprites`
+    pub clipped: bool,
+    pub screen_width: usize,
+    pub screen_height: usize,
+}
+
+impl SpriteDraw {
+    // Yields (row, framebuffer index) for every set sprite bit, with the same wrapping as DRW
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.rows.iter().enumerate().flat_map(move |(row, bits)| {
+            (0..self.width)
+                .filter(move |bit| bits & (0x8000 >> bit) != 0)
+                .filter(move |bit| {
+                    !self.clipped
+                        || (self.x + bit < self.screen_width && self.y + row < self.screen_height)
+                })
+                .map(move |bit| {
+                    let x = (self.x + bit) % self.screen_width;
+                    let y = (self.y + row) % self.screen_height;
+                    (row, x + self.screen_width * y)
+                })
+        })
+    }
+}
+
+pub struct SlowDraw {
+    pub frames_per_row: u32,
+    current: Option<SpriteDraw>,
+    frame: u32,
+}
+
+impl Default for SlowDraw {
+    fn default() -> Self {
+        Self::new(DEFAULT_FRAMES_PER_ROW)
+    }
+}
+
+impl SlowDraw {
+    pub fn new(frames_per_row: u32) -> Self {
+        Self {
+            frames_per_row: frames_per_row.max(1),
+            current: None,
+            frame: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, draw: SpriteDraw) {
+        self.current = Some(draw);
+        self.frame = 0;
+    }
+
+    // The draw being animated, the framebuffer already holds its final result
+    pub fn current(&self) -> Option<&SpriteDraw> {
+        self.current.as_ref()
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.current.is_some()
+    }
+
+    // How many sprite rows have been XORed in so far
+    pub fn rows_shown(&self) -> usize {
+        let rows = self.current.as_ref().map_or(0, |draw| draw.rows.len());
+        ((self.frame / self.frames_per_row) as usize + 1).min(rows)
+    }
+
+    // Steps the animation by one frame, holding the finished sprite for one extra row's time
+    pub fn advance(&mut self) {
+        let Some(draw) = &self.current else {
+            return;
+        };
+
+        self.frame += 1;
+        if self.frame >= (draw.rows.len() as u32 + 1) * self.frames_per_row {
+            self.current = None;
+            self.frame = 0;
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::fmt;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+
+use rand::Rng;
+
+use crate::Chip8;
+use crate::crash_dump;
+use crate::keypad::KEY_COUNT;
+use crate::pacing::FRAMES_PER_SECOND;
+use crate::playlist::Playlist;
+use crate::watchdog::{self, Watchdog};
+
+const KEY_HOLD_FRAMES: u64 = 6;
+const WATCHDOG_SECONDS: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SoakOutcome {
+    Ok,
+    LoadError(String),
+    Panic(String),
+    // The emulator stopped itself, e.g. on a stack overflow
+    Halted(String),
+    WatchdogTripped(String),
+}
+
+impl SoakOutcome {
+    pub fn is_ok(&self) -> bool {
+        *self == SoakOutcome::Ok
+    }
+}
+
+impl fmt::Display for SoakOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SoakOutcome::Ok => write!(f, "ok"),
+            SoakOutcome::LoadError(e) => write!(f, "load error: {}", e),
+            SoakOutcome::Panic(e) => write!(f, "panic: {}", e),
+            SoakOutcome::Halted(e) => write!(f, "halted: {}", e),
+            SoakOutcome::WatchdogTripped(e) => write!(f, "watchdog: {}", e),
+        }
+    }
+}
+
+// Runs a ROM headlessly for `seconds` of emulated time while mashing random keys
+pub fn soak_rom(rom: &str, seconds: u64, ticks_per_frame: usize) -> SoakOutcome {
+    let mut emu = Chip8::new();
+    // The default limit is tuned for long sessions, a soak run only lasts a few emulated seconds
+    emu.watchdog = Some(Watchdog::new(
+        WATCHDOG_SECONDS * FRAMES_PER_SECOND as u64 * ticks_per_frame as u64,
+    ));
+
+    if let Err(e) = emu.load_rom(rom) {
+        return SoakOutcome::LoadError(e.to_string());
+    }
+
+    let mut rng = rand::rng();
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        for frame in 0..seconds * FRAMES_PER_SECOND as u64 {
+            if frame % KEY_HOLD_FRAMES == 0 {
+                emu.clear_keys();
+                if rng.random_bool(0.5) {
+                    emu.keypress(rng.random_range(0..KEY_COUNT), true);
+                }
+            }
+
+            emu.run_cycles(ticks_per_frame);
+            if let Some(error) = emu.halted.take() {
+                return SoakOutcome::Halted(error.to_string());
+            }
+            emu.tick_timers();
+
+            if emu.watchdog_tripped() {
+                return SoakOutcome::WatchdogTripped(watchdog::diagnostics(&emu));
+            }
+        }
+        SoakOutcome::Ok
+    }));
+
+    match result {
+        Ok(outcome) => outcome,
+        Err(payload) => SoakOutcome::Panic(crash_dump::panic_message(payload.as_ref()).to_string()),
+    }
+}
+
+pub fn soak_dir(
+    dir: &str,
+    seconds: u64,
+    ticks_per_frame: usize,
+) -> io::Result<Vec<(String, SoakOutcome)>> {
+    let playlist = Playlist::from_dir(dir)?;
+
+    // Panics are expected and reported per ROM, keep the default hook from spamming stderr
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let results = playlist
+        .roms()
+        .iter()
+        .map(|rom| (rom.clone(), soak_rom(rom, seconds, ticks_per_frame)))
+        .collect();
+
+    panic::set_hook(hook);
+
+    Ok(results)
+}
@@ -0,0 +1,57 @@
+// What the window shows when it was opened without a ROM: a small CHIP-8 program that writes
+// "DROP A ROM HERE" in the boot menu's font and waits. Dropping a file on the window replaces it.
+
+use crate::boot_menu::{self, ENTRY_SIZE};
+use crate::{CHIP8_WIDTH, Chip8, START_ADDRESS};
+
+const LINES: [&str; 2] = ["DROP A ROM", "HERE"];
+const FIRST_LINE_Y: u8 = 10;
+const LINE_SPACING: u8 = 7;
+
+// Each line's text columns follow the program, `draw_line` moves I along them
+const TEXT_ADDRESS: usize = 0x222;
+
+#[rustfmt::skip]
+const PROGRAM: [u8; 34] = [
+    0x60, 0x00, // 200: LD V0, x of the first line
+    0x61, 0x00, // 202: LD V1, y of the first line
+    0xA2, 0x22, // 204: LD I, 0x222
+    0x22, 0x10, // 206: CALL 0x210
+    0x60, 0x00, // 208: LD V0, x of the second line
+    0x61, 0x00, // 20A: LD V1, y of the second line
+    0x22, 0x10, // 20C: CALL 0x210
+    // halt:
+    0x12, 0x0E, // 20E: JP 0x20e
+    // draw_line:
+    0x62, 0x07, // 210: LD V2, 7
+    // draw_column:
+    0xD0, 0x15, // 212: DRW V0, V1, 5
+    0x63, 0x05, // 214: LD V3, 5
+    0xF3, 0x1E, // 216: ADD I, V3
+    0x70, 0x08, // 218: ADD V0, 8
+    0x72, 0xFF, // 21A: ADD V2, 0xFF
+    0x32, 0x00, // 21C: SE V2, 0
+    0x12, 0x12, // 21E: JP 0x212
+    0x00, 0xEE, // 220: RET
+];
+
+// Operand bytes of the LD V0 and LD V1 before each CALL
+const LINE_OPERANDS: [(usize, usize); 2] = [(0x201, 0x203), (0x209, 0x20B)];
+
+// Resets the emulator into the splash screen
+pub fn load(emu: &mut Chip8) {
+    emu.reset();
+    // The program is built for 0x200 whatever `start_address` the ROMs use
+    emu.pc = START_ADDRESS as u16;
+    emu.memory[START_ADDRESS..START_ADDRESS + PROGRAM.len()].copy_from_slice(&PROGRAM);
+
+    for (i, (line, (x, y))) in LINES.iter().zip(LINE_OPERANDS).enumerate() {
+        // Four pixels per character with the last one's gap left off
+        let width = line.len() * 4 - 1;
+        emu.memory[x] = ((CHIP8_WIDTH - width) / 2) as u8;
+        emu.memory[y] = FIRST_LINE_Y + i as u8 * LINE_SPACING;
+
+        let start = TEXT_ADDRESS + i * ENTRY_SIZE;
+        emu.memory[start..start + ENTRY_SIZE].copy_from_slice(&boot_menu::render_name(line));
+    }
+}
@@ -0,0 +1,247 @@
+// Snapshot of the machine state, stored as versioned JSON in `.state` files
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::audio::{DEFAULT_PITCH, PATTERN_SIZE};
+use crate::geometry::Geometry;
+use crate::keypad::KEY_COUNT;
+use crate::persistent_ram;
+use crate::{CHIP8_WIDTH, Chip8, HIRES_HEIGHT, HIRES_WIDTH, MEMORY_SIZE};
+
+pub const STATE_VERSION: u64 = 1;
+pub const SLOT_COUNT: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveState {
+    pub rom_hash: Option<String>,
+    pub registers: [u8; 16],
+    #[serde(with = "hex")]
+    pub memory: Vec<u8>,
+    pub index: u32,
+    pub pc: u16,
+    pub stack: Vec<u16>,
+    pub sp: u8,
+    pub dtimer: u8,
+    pub stimer: u8,
+    pub keypad: [bool; KEY_COUNT],
+    // States written before SUPER-CHIP support have neither field
+    #[serde(default)]
+    pub hires: bool,
+    #[serde(default)]
+    pub rpl: [u8; 8],
+    // Only the visible pixels, see `width`
+    #[serde(with = "video_rows")]
+    pub video: Vec<bool>,
+    // XO-CHIP's second plane, left out while nothing is drawn on it
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "video_rows")]
+    pub plane2: Vec<bool>,
+    #[serde(default = "first_plane")]
+    pub planes: u8,
+    // XO-CHIP's F002 sound pattern and FX3A pitch
+    #[serde(default)]
+    pub audio_pattern: Option<[u8; PATTERN_SIZE]>,
+    #[serde(default = "default_pitch")]
+    pub pitch: u8,
+}
+
+fn first_plane() -> u8 {
+    1
+}
+
+fn default_pitch() -> u8 {
+    DEFAULT_PITCH
+}
+
+#[derive(Serialize, Deserialize)]
+struct StateFile<S> {
+    version: u64,
+    #[serde(flatten)]
+    state: S,
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+// Memory is stored as one hex string instead of 4096 numbers
+mod hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let text: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        serializer.serialize_str(&text)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        if !text.len().is_multiple_of(2) {
+            return Err(serde::de::Error::custom("odd number of hex digits"));
+        }
+        // Pairs of bytes, not of chars, so a multi-byte character can't split the slicing
+        text.as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                let digits = String::from_utf8_lossy(pair);
+                u8::from_str_radix(&digits, 16)
+                    .map_err(|_| serde::de::Error::custom(format!("invalid hex byte '{}'", digits)))
+            })
+            .collect()
+    }
+}
+
+// One string of 0s and 1s per display row keeps the file readable
+mod video_rows {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(video: &[bool], serializer: S) -> Result<S::Ok, S::Error> {
+        let width = if video.len() == HIRES_WIDTH * HIRES_HEIGHT {
+            HIRES_WIDTH
+        } else {
+            CHIP8_WIDTH
+        };
+        let rows: Vec<String> = video
+            .chunks(width)
+            .map(|row| row.iter().map(|&on| if on { '1' } else { '0' }).collect())
+            .collect();
+        rows.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<bool>, D::Error> {
+        let rows = Vec::<String>::deserialize(deserializer)?;
+        Ok(rows
+            .iter()
+            .flat_map(|row| row.chars().map(|c| c == '1'))
+            .collect())
+    }
+}
+
+impl SaveState {
+    pub fn capture(emu: &Chip8) -> Self {
+        Self {
+            rom_hash: emu.rom_hash.clone(),
+            registers: emu.registers,
+            memory: emu.memory.to_vec(),
+            index: emu.index,
+            pc: emu.pc,
+            stack: emu.stack.clone(),
+            sp: emu.sp,
+            dtimer: emu.dtimer,
+            stimer: emu.stimer,
+            keypad: emu.keypad,
+            hires: emu.hires,
+            rpl: emu.rpl,
+            video: emu.get_display().to_vec(),
+            plane2: if emu.video.plane(1).contains(&true) {
+                emu.video.plane(1).to_vec()
+            } else {
+                Vec::new()
+            },
+            planes: emu.planes,
+            audio_pattern: emu.audio_pattern,
+            pitch: emu.pitch,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        if self.hires { HIRES_WIDTH } else { CHIP8_WIDTH }
+    }
+
+    // Low resolution screens are as tall as the geometry they were saved with
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HIRES_HEIGHT
+        } else {
+            self.video.len() / CHIP8_WIDTH
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(StateFile {
+            version: STATE_VERSION,
+            state: self,
+        })
+        .expect("save states always serialize")
+    }
+
+    pub fn from_json(value: &Value) -> io::Result<Self> {
+        let version = value
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| invalid("missing 'version'"))?;
+        if version != STATE_VERSION {
+            return Err(invalid(format!("unsupported state version {}", version)));
+        }
+
+        let file: StateFile<Self> = serde_json::from_value(value.clone())?;
+        let state = file.state;
+        let known_size = state.hires || Geometry::from_height(state.height()).is_some();
+        if state.memory.len() != MEMORY_SIZE
+            || state.video.len() != state.width() * state.height()
+            || !(state.plane2.is_empty() || state.plane2.len() == state.video.len())
+            || !known_size
+        {
+            return Err(invalid("memory or video has the wrong size"));
+        }
+        if state.sp as usize > state.stack.len() {
+            return Err(invalid("stack pointer is past the end of the stack"));
+        }
+        Ok(state)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let value: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+        Self::from_json(&value)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_json().to_string())
+    }
+}
+
+// Numbered save slots live next to the persistent RAM, one set per ROM
+pub fn slot_path(rom_hash: &str, slot: usize) -> PathBuf {
+    persistent_ram::data_dir()
+        .join("states")
+        .join(format!("{}-{}.state", rom_hash, slot))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_diff;
+
+    #[test]
+    fn save_states_round_trip_and_diff() {
+        let mut emu = Chip8::new();
+        // V0 = 0xA, point I at its font glyph, draw it
+        emu.load_rom_bytes(&[0x60, 0x0A, 0xF0, 0x29, 0xD0, 0x05])
+            .unwrap();
+        emu.run_cycles(2);
+        let before = emu.save_state();
+
+        let json = before.to_json();
+        assert_eq!(SaveState::from_json(&json).unwrap(), before);
+
+        // A non-ASCII character in the memory string is an error, not a panic
+        let mut broken = json.clone();
+        broken["memory"] = Value::String("é0".repeat(MEMORY_SIZE / 2));
+        assert!(SaveState::from_json(&broken).is_err());
+
+        emu.run_cycles(1);
+        let after = emu.save_state();
+        let changes = state_diff::diff(&before, &after);
+        let names: Vec<_> = changes.fields.iter().map(|f| f.name.as_str()).collect();
+        assert!(names.contains(&"PC"));
+        assert!(changes.pixels_changed > 0);
+        assert!(state_diff::diff(&after, &after).is_empty());
+
+        let mut restored = Chip8::new();
+        restored.load_state(&before);
+        assert_eq!(restored.save_state(), before);
+    }
+}
@@ -0,0 +1,201 @@
+// Compares two save states and reports everything that changed between them
+
+use std::fmt::Write as _;
+use std::ops::Range;
+
+use crate::disassembler;
+use crate::state::SaveState;
+
+// Changed bytes closer together than this are reported as one range
+const MERGE_GAP: usize = 4;
+// Longer ranges are cut off in the listing
+const MAX_LISTED_WORDS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub name: String,
+    pub a: String,
+    pub b: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub fields: Vec<FieldChange>,
+    pub memory: Vec<Range<usize>>,
+    // Only counted when both states use the same resolution
+    pub pixels_changed: usize,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.memory.is_empty() && self.pixels_changed == 0
+    }
+}
+
+pub fn diff(a: &SaveState, b: &SaveState) -> StateDiff {
+    let mut fields = Vec::new();
+    let mut field = |name: String, va: String, vb: String| {
+        if va != vb {
+            fields.push(FieldChange { name, a: va, b: vb });
+        }
+    };
+
+    field(
+        "PC".into(),
+        format!("{:#05x}", a.pc),
+        format!("{:#05x}", b.pc),
+    );
+    field(
+        "I".into(),
+        format!("{:#05x}", a.index),
+        format!("{:#05x}", b.index),
+    );
+    for (i, (ra, rb)) in a.registers.iter().zip(&b.registers).enumerate() {
+        field(
+            format!("V{:X}", i),
+            format!("{:#04x}", ra),
+            format!("{:#04x}", rb),
+        );
+    }
+    field("DT".into(), a.dtimer.to_string(), b.dtimer.to_string());
+    field("ST".into(), a.stimer.to_string(), b.stimer.to_string());
+    field("SP".into(), a.sp.to_string(), b.sp.to_string());
+    field(
+        "Stack".into(),
+        format_stack(a.stack.get(..a.sp as usize).unwrap_or(&a.stack)),
+        format_stack(b.stack.get(..b.sp as usize).unwrap_or(&b.stack)),
+    );
+    field(
+        "Keys".into(),
+        format_keys(&a.keypad),
+        format_keys(&b.keypad),
+    );
+    field(
+        "Mode".into(),
+        format!("{}x{}", a.width(), a.height()),
+        format!("{}x{}", b.width(), b.height()),
+    );
+    field("Planes".into(), a.planes.to_string(), b.planes.to_string());
+    field(
+        "RPL".into(),
+        format!("{:02x?}", a.rpl),
+        format!("{:02x?}", b.rpl),
+    );
+
+    let mut memory: Vec<Range<usize>> = Vec::new();
+    for addr in
+        (0..a.memory.len().min(b.memory.len())).filter(|&addr| a.memory[addr] != b.memory[addr])
+    {
+        match memory.last_mut() {
+            Some(range) if addr - range.end < MERGE_GAP => range.end = addr + 1,
+            _ => memory.push(addr..addr + 1),
+        }
+    }
+
+    let pixels_changed = if a.hires == b.hires {
+        a.video
+            .iter()
+            .zip(&b.video)
+            .filter(|(pa, pb)| pa != pb)
+            .count()
+    } else {
+        0
+    };
+
+    StateDiff {
+        fields,
+        memory,
+        pixels_changed,
+    }
+}
+
+fn format_stack(stack: &[u16]) -> String {
+    let entries: Vec<String> = stack.iter().map(|addr| format!("{:#05x}", addr)).collect();
+    format!("[{}]", entries.join(", "))
+}
+
+fn format_keys(keypad: &[bool]) -> String {
+    let keys: Vec<String> = (0..keypad.len())
+        .filter(|&key| keypad[key])
+        .map(|key| format!("{:X}", key))
+        .collect();
+    if keys.is_empty() {
+        "none".to_string()
+    } else {
+        keys.join(" ")
+    }
+}
+
+fn word(memory: &[u8], addr: usize) -> u16 {
+    u16::from_be_bytes([memory[addr], memory.get(addr + 1).copied().unwrap_or(0)])
+}
+
+pub fn format_diff(a: &SaveState, b: &SaveState, diff: &StateDiff) -> String {
+    let mut out = String::new();
+
+    // Writing into a String can't fail
+    if diff.is_empty() {
+        let _ = writeln!(out, "States are identical");
+        return out;
+    }
+
+    if !diff.fields.is_empty() {
+        let _ = writeln!(out, "[Registers]");
+        for change in &diff.fields {
+            let _ = writeln!(out, "{:<6} {} -> {}", change.name, change.a, change.b);
+        }
+    }
+
+    for range in &diff.memory {
+        let _ = writeln!(
+            out,
+            "\n[Memory {:#05x}..{:#05x}, {} bytes]",
+            range.start,
+            range.end,
+            range.len()
+        );
+
+        // Instructions are word aligned, so start the listing on an even address
+        let words: Vec<usize> = (range.start & !1..range.end).step_by(2).collect();
+        for &addr in words.iter().take(MAX_LISTED_WORDS) {
+            let (wa, wb) = (word(&a.memory, addr), word(&b.memory, addr));
+            let marker = if wa != wb { '*' } else { ' ' };
+            let _ = writeln!(
+                out,
+                "{}{:03x}: {:04x} {:<20} | {:04x} {}",
+                marker,
+                addr,
+                wa,
+                disassembler::mnemonic(wa),
+                wb,
+                disassembler::mnemonic(wb)
+            );
+        }
+        if words.len() > MAX_LISTED_WORDS {
+            let _ = writeln!(out, " ... {} more words", words.len() - MAX_LISTED_WORDS);
+        }
+    }
+
+    if diff.pixels_changed > 0 {
+        let _ = writeln!(
+            out,
+            "\n[Framebuffer, {} pixels changed: + turned on, - turned off]",
+            diff.pixels_changed
+        );
+        for (row_a, row_b) in a.video.chunks(a.width()).zip(b.video.chunks(b.width())) {
+            let line: String = row_a
+                .iter()
+                .zip(row_b)
+                .map(|(&pa, &pb)| match (pa, pb) {
+                    (true, true) => '#',
+                    (false, true) => '+',
+                    (true, false) => '-',
+                    (false, false) => '.',
+                })
+                .collect();
+            let _ = writeln!(out, "{}", line);
+        }
+    }
+
+    out
+}
@@ -0,0 +1,198 @@
+// Runs Timendus' CHIP-8 test suite (https://github.com/Timendus/chip8-test-suite) headlessly and
+// compares the screen each test ends on with a known-good hash. The hashes live next to the ROMs
+// in expected.sha1, one `hash  file` line per test like sha1sum writes them, and `--bless`
+// records the current screens there after they've been checked by eye.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use crate::Chip8;
+use crate::crash_dump;
+use crate::quirks::Quirks;
+
+pub const DEFAULT_FRAMES: u64 = 1200;
+pub const EXPECTED_FILE: &str = "expected.sha1";
+
+// Tests with a platform menu skip it when 0x1FF already holds a choice
+const MENU_CHOICE_ADDRESS: usize = 0x1FF;
+
+pub struct TestRom {
+    pub file: &'static str,
+    pub quirks: &'static str,
+    pub menu_choice: Option<u8>,
+}
+
+// The keypad test waits for keys forever, so it isn't here
+pub const TEST_ROMS: [TestRom; 7] = [
+    TestRom {
+        file: "1-chip8-logo.ch8",
+        quirks: "default",
+        menu_choice: None,
+    },
+    TestRom {
+        file: "2-ibm-logo.ch8",
+        quirks: "default",
+        menu_choice: None,
+    },
+    TestRom {
+        file: "3-corax+.ch8",
+        quirks: "default",
+        menu_choice: None,
+    },
+    TestRom {
+        file: "4-flags.ch8",
+        quirks: "default",
+        menu_choice: None,
+    },
+    // 1 is CHIP-8
+    TestRom {
+        file: "5-quirks.ch8",
+        quirks: "vip",
+        menu_choice: Some(1),
+    },
+    TestRom {
+        file: "7-beep.ch8",
+        quirks: "default",
+        menu_choice: None,
+    },
+    // 1 is SUPER-CHIP in lores
+    TestRom {
+        file: "8-scrolling.ch8",
+        quirks: "schip-modern",
+        menu_choice: Some(1),
+    },
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestOutcome {
+    Pass { hash: String },
+    Fail { hash: String },
+    // Nothing to compare against yet, `--bless` records it
+    Unknown { hash: String },
+    Missing,
+    Error(String),
+}
+
+impl TestOutcome {
+    pub fn is_failure(&self) -> bool {
+        matches!(self, TestOutcome::Fail { .. } | TestOutcome::Error(_))
+    }
+
+    pub fn hash(&self) -> Option<&str> {
+        match self {
+            TestOutcome::Pass { hash }
+            | TestOutcome::Fail { hash }
+            | TestOutcome::Unknown { hash } => Some(hash),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TestOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TestOutcome::Pass { .. } => write!(f, "pass"),
+            TestOutcome::Fail { hash } => write!(f, "FAIL, screen {}", hash),
+            TestOutcome::Unknown { hash } => write!(f, "no expected hash, screen {}", hash),
+            TestOutcome::Missing => write!(f, "skipped, ROM not found"),
+            TestOutcome::Error(e) => write!(f, "FAIL, {}", e),
+        }
+    }
+}
+
+// Runs one test for `frames` frames and returns the hash of the screen it ends on
+pub fn run_test(
+    rom: &Path,
+    test: &TestRom,
+    frames: u64,
+    ticks_per_frame: usize,
+) -> Result<String, String> {
+    let mut emu = Chip8::with_seed(0);
+    emu.rom_database.quirks = false;
+    emu.quirks = Quirks::preset(test.quirks).unwrap();
+    emu.rewind = None;
+
+    emu.load_rom(&rom.to_string_lossy())
+        .map_err(|e| format!("load error: {}", e))?;
+    if let Some(choice) = test.menu_choice {
+        emu.memory[MENU_CHOICE_ADDRESS] = choice;
+    }
+
+    let run = panic::catch_unwind(AssertUnwindSafe(|| {
+        for _ in 0..frames {
+            emu.run_cycles(ticks_per_frame);
+            if let Some(error) = emu.halted.take() {
+                return Err(error.to_string());
+            }
+            emu.tick_timers();
+        }
+        Ok(())
+    }));
+
+    match run {
+        Ok(result) => result.map(|()| emu.frame_hash()),
+        Err(payload) => Err(crash_dump::panic_message(payload.as_ref()).to_string()),
+    }
+}
+
+pub fn read_expected(path: &Path) -> io::Result<HashMap<String, String>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(text
+        .lines()
+        .filter_map(|line| line.split_once("  "))
+        .map(|(hash, file)| (file.trim().to_string(), hash.trim().to_string()))
+        .collect())
+}
+
+pub fn write_expected(path: &Path, hashes: &[(&str, String)]) -> io::Result<()> {
+    let text: String = hashes
+        .iter()
+        .map(|(file, hash)| format!("{}  {}\n", hash, file))
+        .collect();
+    fs::write(path, text)
+}
+
+// Runs every test found in `dir`, in suite order
+pub fn run_dir(
+    dir: &Path,
+    frames: u64,
+    ticks_per_frame: usize,
+) -> io::Result<Vec<(&'static str, TestOutcome)>> {
+    let expected = read_expected(&dir.join(EXPECTED_FILE))?;
+
+    // Panics are reported per test, keep the default hook from spamming stderr
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let results = TEST_ROMS
+        .iter()
+        .map(|test| {
+            let rom = dir.join(test.file);
+            let outcome = if !rom.is_file() {
+                TestOutcome::Missing
+            } else {
+                match run_test(&rom, test, frames, ticks_per_frame) {
+                    Err(e) => TestOutcome::Error(e),
+                    Ok(hash) => match expected.get(test.file) {
+                        Some(expected) if *expected == hash => TestOutcome::Pass { hash },
+                        Some(_) => TestOutcome::Fail { hash },
+                        None => TestOutcome::Unknown { hash },
+                    },
+                }
+            };
+            (test.file, outcome)
+        })
+        .collect();
+
+    panic::set_hook(hook);
+
+    Ok(results)
+}
@@ -0,0 +1,316 @@
+// Cycle tables can be loaded from a TOML file with `--cycle-table`, starting from a preset and
+// changing the cost of opcode classes by their snake_case names:
+//
+//     base = "vip"
+//     frame_budget = 3668
+//     draw_row_cost = 7
+//
+//     [costs]
+//     draw = 30
+//     bcd = 90
+//
+// Everything is optional, `base` defaults to flat. Leaving out `frame_budget` on a flat base
+// keeps using --ticks as the budget.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Names `CycleTable::preset` knows
+pub const PRESETS: [&str; 3] = ["flat", "vip", "hp48"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpcodeClass {
+    ClearScreen,
+    Return,
+    Jump,
+    Call,
+    SkipImmediate,
+    SkipRegister,
+    LoadImmediate,
+    AddImmediate,
+    Arithmetic,
+    LoadIndex,
+    JumpOffset,
+    Random,
+    Draw,
+    SkipKey,
+    ReadDelay,
+    WaitKey,
+    SetTimer,
+    AddIndex,
+    FontChar,
+    Bcd,
+    StoreRegisters,
+    LoadRegisters,
+    Other,
+}
+
+impl OpcodeClass {
+    pub const COUNT: usize = OpcodeClass::Other as usize + 1;
+
+    pub const ALL: [OpcodeClass; OpcodeClass::COUNT] = [
+        OpcodeClass::ClearScreen,
+        OpcodeClass::Return,
+        OpcodeClass::Jump,
+        OpcodeClass::Call,
+        OpcodeClass::SkipImmediate,
+        OpcodeClass::SkipRegister,
+        OpcodeClass::LoadImmediate,
+        OpcodeClass::AddImmediate,
+        OpcodeClass::Arithmetic,
+        OpcodeClass::LoadIndex,
+        OpcodeClass::JumpOffset,
+        OpcodeClass::Random,
+        OpcodeClass::Draw,
+        OpcodeClass::SkipKey,
+        OpcodeClass::ReadDelay,
+        OpcodeClass::WaitKey,
+        OpcodeClass::SetTimer,
+        OpcodeClass::AddIndex,
+        OpcodeClass::FontChar,
+        OpcodeClass::Bcd,
+        OpcodeClass::StoreRegisters,
+        OpcodeClass::LoadRegisters,
+        OpcodeClass::Other,
+    ];
+
+    // The key in a cycle table file
+    pub fn name(self) -> &'static str {
+        match self {
+            OpcodeClass::ClearScreen => "clear_screen",
+            OpcodeClass::Return => "return",
+            OpcodeClass::Jump => "jump",
+            OpcodeClass::Call => "call",
+            OpcodeClass::SkipImmediate => "skip_immediate",
+            OpcodeClass::SkipRegister => "skip_register",
+            OpcodeClass::LoadImmediate => "load_immediate",
+            OpcodeClass::AddImmediate => "add_immediate",
+            OpcodeClass::Arithmetic => "arithmetic",
+            OpcodeClass::LoadIndex => "load_index",
+            OpcodeClass::JumpOffset => "jump_offset",
+            OpcodeClass::Random => "random",
+            OpcodeClass::Draw => "draw",
+            OpcodeClass::SkipKey => "skip_key",
+            OpcodeClass::ReadDelay => "read_delay",
+            OpcodeClass::WaitKey => "wait_key",
+            OpcodeClass::SetTimer => "set_timer",
+            OpcodeClass::AddIndex => "add_index",
+            OpcodeClass::FontChar => "font_char",
+            OpcodeClass::Bcd => "bcd",
+            OpcodeClass::StoreRegisters => "store_registers",
+            OpcodeClass::LoadRegisters => "load_registers",
+            OpcodeClass::Other => "other",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|class| class.name() == name)
+    }
+
+    pub fn of(opcode: u16) -> Self {
+        match (opcode >> 12, opcode & 0x00FF) {
+            (0, 0xE0) => OpcodeClass::ClearScreen,
+            (0, 0xEE) => OpcodeClass::Return,
+            (1, _) => OpcodeClass::Jump,
+            (2, _) => OpcodeClass::Call,
+            (3, _) | (4, _) => OpcodeClass::SkipImmediate,
+            (5, _) | (9, _) => OpcodeClass::SkipRegister,
+            (6, _) => OpcodeClass::LoadImmediate,
+            (7, _) => OpcodeClass::AddImmediate,
+            (8, _) => OpcodeClass::Arithmetic,
+            (0xA, _) => OpcodeClass::LoadIndex,
+            (0xB, _) => OpcodeClass::JumpOffset,
+            (0xC, _) => OpcodeClass::Random,
+            (0xD, _) => OpcodeClass::Draw,
+            (0xE, _) => OpcodeClass::SkipKey,
+            (0xF, 0x07) => OpcodeClass::ReadDelay,
+            (0xF, 0x0A) => OpcodeClass::WaitKey,
+            (0xF, 0x15) | (0xF, 0x18) => OpcodeClass::SetTimer,
+            (0xF, 0x1E) => OpcodeClass::AddIndex,
+            (0xF, 0x29) => OpcodeClass::FontChar,
+            (0xF, 0x33) => OpcodeClass::Bcd,
+            (0xF, 0x55) => OpcodeClass::StoreRegisters,
+            (0xF, 0x65) => OpcodeClass::LoadRegisters,
+            _ => OpcodeClass::Other,
+        }
+    }
+}
+
+// Cost of each opcode class in abstract cycles, consumed by `Chip8::run_cycles`.
+// `frame_budget` is how many cycles fit into one 60 Hz frame; `None` means the caller's
+// ticks-per-frame value is used as the budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleTable {
+    pub costs: [u32; OpcodeClass::COUNT],
+    // Added to the DRW cost for every sprite row, the VIP copies sprites a byte at a time
+    pub draw_row_cost: u32,
+    pub frame_budget: Option<u64>,
+}
+
+impl Default for CycleTable {
+    fn default() -> Self {
+        Self::flat()
+    }
+}
+
+impl CycleTable {
+    // Every instruction costs one cycle, so the budget is simply instructions per frame
+    pub fn flat() -> Self {
+        Self {
+            costs: [1; OpcodeClass::COUNT],
+            draw_row_cost: 0,
+            frame_budget: None,
+        }
+    }
+
+    // Approximate COSMAC VIP interpreter timings in machine cycles (1.76 MHz / 8 per cycle)
+    pub fn vip() -> Self {
+        let mut table = Self {
+            costs: [10; OpcodeClass::COUNT],
+            draw_row_cost: 7,
+            frame_budget: Some(3668),
+        };
+
+        table.set_cost(OpcodeClass::ClearScreen, 24);
+        table.set_cost(OpcodeClass::Return, 10);
+        table.set_cost(OpcodeClass::Jump, 12);
+        table.set_cost(OpcodeClass::Call, 26);
+        table.set_cost(OpcodeClass::SkipImmediate, 10);
+        table.set_cost(OpcodeClass::SkipRegister, 14);
+        table.set_cost(OpcodeClass::LoadImmediate, 6);
+        table.set_cost(OpcodeClass::AddImmediate, 10);
+        table.set_cost(OpcodeClass::Arithmetic, 44);
+        table.set_cost(OpcodeClass::LoadIndex, 12);
+        table.set_cost(OpcodeClass::JumpOffset, 22);
+        table.set_cost(OpcodeClass::Random, 36);
+        table.set_cost(OpcodeClass::Draw, 26);
+        table.set_cost(OpcodeClass::SkipKey, 14);
+        table.set_cost(OpcodeClass::ReadDelay, 10);
+        table.set_cost(OpcodeClass::WaitKey, 19);
+        table.set_cost(OpcodeClass::SetTimer, 10);
+        table.set_cost(OpcodeClass::AddIndex, 16);
+        table.set_cost(OpcodeClass::FontChar, 20);
+        table.set_cost(OpcodeClass::Bcd, 84);
+        table.set_cost(OpcodeClass::StoreRegisters, 64);
+        table.set_cost(OpcodeClass::LoadRegisters, 64);
+
+        table
+    }
+
+    // SUPER-CHIP 1.1 on the HP 48, in quarter instructions. Octo runs SCHIP games at 30
+    // instructions a frame, drawing costs more there since the HP 48 copies sprites into its
+    // screen a row at a time.
+    pub fn hp48() -> Self {
+        let mut table = Self {
+            costs: [4; OpcodeClass::COUNT],
+            draw_row_cost: 1,
+            frame_budget: Some(120),
+        };
+
+        table.set_cost(OpcodeClass::ClearScreen, 16);
+        table.set_cost(OpcodeClass::Draw, 8);
+        table.set_cost(OpcodeClass::Bcd, 6);
+        table.set_cost(OpcodeClass::StoreRegisters, 6);
+        table.set_cost(OpcodeClass::LoadRegisters, 6);
+
+        table
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "flat" => Some(Self::flat()),
+            "vip" => Some(Self::vip()),
+            "hp48" => Some(Self::hp48()),
+            _ => None,
+        }
+    }
+
+    // The TOML format described at the top of this file
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let table: toml::Table = text.parse().map_err(|e| invalid(format!("{}", e)))?;
+        let cycles = |key: &str, value: &toml::Value| {
+            value
+                .as_integer()
+                .and_then(|n| u32::try_from(n).ok())
+                .ok_or_else(|| invalid(format!("'{}' needs a cycle count", key)))
+        };
+
+        let mut cycle_table = match table.get("base") {
+            Some(base) => base
+                .as_str()
+                .and_then(Self::preset)
+                .ok_or_else(|| invalid(format!("'base' must be one of {}", PRESETS.join(", "))))?,
+            None => Self::flat(),
+        };
+        for (key, value) in &table {
+            match key.as_str() {
+                "base" => {}
+                "frame_budget" => cycle_table.frame_budget = Some(cycles(key, value)? as u64),
+                "draw_row_cost" => cycle_table.draw_row_cost = cycles(key, value)?,
+                "costs" => {
+                    let costs = value
+                        .as_table()
+                        .ok_or_else(|| invalid("'costs' must be a table".to_string()))?;
+                    for (name, cost) in costs {
+                        let class = OpcodeClass::from_name(name)
+                            .ok_or_else(|| invalid(format!("unknown opcode class '{}'", name)))?;
+                        cycle_table.set_cost(class, cycles(name, cost)?);
+                    }
+                }
+                _ => return Err(invalid(format!("unknown key '{}'", key))),
+            }
+        }
+
+        Ok(cycle_table)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn set_cost(&mut self, class: OpcodeClass, cycles: u32) {
+        self.costs[class as usize] = cycles;
+    }
+
+    pub fn cost(&self, opcode: u16) -> u32 {
+        let class = OpcodeClass::of(opcode);
+        let cost = self.costs[class as usize];
+        if class == OpcodeClass::Draw {
+            // DXY0 draws a 16x16 SUPER-CHIP sprite
+            let rows = match opcode & 0x000F {
+                0 => 16,
+                n => n as u32,
+            };
+            cost + rows * self.draw_row_cost
+        } else {
+            cost
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycle_table_file_overrides_a_preset() {
+        let table = CycleTable::parse(
+            "base = \"vip\"\nframe_budget = 1000\n\n[costs]\nbcd = 90\nload_index = 3\n",
+        )
+        .unwrap();
+        assert_eq!(table.frame_budget, Some(1000));
+        assert_eq!(table.cost(0xF033), 90);
+        assert_eq!(table.cost(0xA123), 3);
+        // Untouched classes keep the VIP cost, DRW still pays per row
+        assert_eq!(table.cost(0xD125), CycleTable::vip().cost(0xD125));
+        assert_eq!(
+            OpcodeClass::from_name("skip_key"),
+            Some(OpcodeClass::SkipKey)
+        );
+
+        assert!(CycleTable::parse("[costs]\nteleport = 1\n").is_err());
+        assert!(CycleTable::parse("base = \"hp49\"\n").is_err());
+    }
+}
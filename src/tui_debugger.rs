@@ -0,0 +1,410 @@
+// Terminal debugger that runs alongside the SDL window: registers, timers, stack, the
+// instruction at PC and a scrollable memory hexdump, redrawn every frame. It only reads and
+// controls the emulator through `debugger`, like the SDL debugger keys. Keys are handled before
+// the frame runs, so edits land between instructions.
+//
+// Keys in the terminal: Up/Down/PageUp/PageDown scroll memory, F follows I again, P pauses,
+// S steps one instruction and B toggles a breakpoint at PC. For cheats / searches memory for a
+// hex value typed after it, X starts a new search, Tab picks the next address found and Z
+// freezes or thaws it. E edits memory or a register, type `2f0=9`, `va=3`, `i=300` or `pc=200`
+// (all hex) and Enter.
+
+use std::io::{self, Stdout};
+use std::time::Duration;
+
+use ratatui::Frame;
+use ratatui::Terminal;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+
+use crate::Chip8;
+use crate::cheats::Cheats;
+use crate::debugger::{self, CpuState, Target};
+
+const BYTES_PER_ROW: usize = 16;
+const MEMORY_ROWS: usize = 4096 / BYTES_PER_ROW;
+const PAGE_ROWS: usize = 16;
+// Longest edit that can be typed, enough for `vf=ff` or `0xffffff=0xff`
+const EDIT_LENGTH: usize = 16;
+
+// What the line being typed is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    Search,
+    Edit,
+}
+
+pub struct TuiDebugger {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    // First hexdump row on screen
+    scroll: usize,
+    // Keep the row I points at in view until memory is scrolled by hand
+    follow_index: bool,
+    // A cheat search or an edit being typed
+    prompt: Option<(Prompt, String)>,
+    // Why the last edit didn't go through
+    error: Option<String>,
+    // Which of the cheat search's addresses Z freezes
+    selected: usize,
+}
+
+impl TuiDebugger {
+    pub fn new() -> io::Result<Self> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        if let Err(e) = execute!(stdout, EnterAlternateScreen) {
+            let _ = disable_raw_mode();
+            return Err(e);
+        }
+
+        Ok(Self {
+            terminal: Terminal::new(CrosstermBackend::new(stdout))?,
+            scroll: 0,
+            follow_index: true,
+            prompt: None,
+            error: None,
+            selected: 0,
+        })
+    }
+
+    // Handles the keys pressed in the terminal since the last frame and redraws
+    pub fn update(&mut self, emu: &mut Chip8) -> io::Result<()> {
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                self.handle_key(key.code, emu);
+            }
+        }
+
+        let cpu = debugger::cpu_state(emu);
+        let memory = debugger::memory(emu, 0, MEMORY_ROWS * BYTES_PER_ROW);
+        let paused = emu.debug.is_paused();
+        let breakpoints: Vec<u16> = emu.debug.breakpoints().collect();
+        let cheats = CheatsView {
+            cheats: &emu.cheats,
+            memory: &emu.memory,
+            search: match &self.prompt {
+                Some((Prompt::Search, search)) => Some(search),
+                _ => None,
+            },
+            selected: self.selected,
+        };
+
+        let status = match (&self.prompt, &self.error) {
+            (Some((Prompt::Edit, edit)), _) => Line::raw(format!("edit {}_", edit)),
+            (_, Some(error)) => Line::styled(error.as_str(), Style::default().fg(Color::Red)),
+            _ => Line::styled(
+                "E to edit memory or a register",
+                Style::default().fg(Color::DarkGray),
+            ),
+        };
+
+        let mut scroll = self.scroll;
+        let follow_index = self.follow_index;
+        self.terminal.draw(|frame| {
+            let [panels, status_area] =
+                Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+            let [left, right] =
+                Layout::horizontal([Constraint::Length(34), Constraint::Min(0)]).areas(panels);
+            let [cpu_area, stack_area, cheats_area] = Layout::vertical([
+                Constraint::Length(13),
+                Constraint::Min(0),
+                Constraint::Length(10),
+            ])
+            .areas(left);
+
+            draw_cpu(frame, cpu_area, &cpu, paused, &breakpoints);
+            draw_stack(frame, stack_area, &cpu);
+            draw_cheats(frame, cheats_area, &cheats);
+
+            let visible = right.height.saturating_sub(2) as usize;
+            if follow_index {
+                let row = cpu.index as usize / BYTES_PER_ROW;
+                if row < scroll || row >= scroll + visible {
+                    scroll = row.saturating_sub(visible / 2);
+                }
+            }
+            scroll = scroll.min(MEMORY_ROWS.saturating_sub(visible));
+            draw_memory(frame, right, memory, scroll, &cpu);
+            frame.render_widget(Paragraph::new(status), status_area);
+        })?;
+        self.scroll = scroll;
+        Ok(())
+    }
+
+    fn handle_key(&mut self, code: KeyCode, emu: &mut Chip8) {
+        if let Some((prompt, line)) = &mut self.prompt {
+            let length = match prompt {
+                Prompt::Search => 2,
+                Prompt::Edit => EDIT_LENGTH,
+            };
+            match code {
+                KeyCode::Char(c) if line.len() < length && accepts(*prompt, c) => line.push(c),
+                KeyCode::Backspace => {
+                    line.pop();
+                }
+                KeyCode::Enter => {
+                    let (prompt, line) = self.prompt.take().expect("prompt is open");
+                    match prompt {
+                        Prompt::Search => self.search(emu, &line),
+                        Prompt::Edit => self.edit(emu, &line),
+                    }
+                }
+                KeyCode::Esc => self.prompt = None,
+                _ => {}
+            }
+            return;
+        }
+
+        match code {
+            KeyCode::Up => self.scroll_by(-1),
+            KeyCode::Down => self.scroll_by(1),
+            KeyCode::PageUp => self.scroll_by(-(PAGE_ROWS as isize)),
+            KeyCode::PageDown => self.scroll_by(PAGE_ROWS as isize),
+            KeyCode::Char('f') => self.follow_index = true,
+            KeyCode::Char('p') => emu.debug.toggle_pause(),
+            KeyCode::Char('s') => emu.debug.step(1),
+            KeyCode::Char('b') => {
+                emu.debug.toggle_breakpoint(debugger::cpu_state(emu).pc);
+            }
+            KeyCode::Char('/') => self.prompt = Some((Prompt::Search, String::new())),
+            KeyCode::Char('e') => {
+                self.prompt = Some((Prompt::Edit, String::new()));
+                self.error = None;
+            }
+            KeyCode::Char('x') => {
+                emu.cheats.new_search();
+                self.selected = 0;
+            }
+            KeyCode::Tab => {
+                let found = emu.cheats.candidates().len();
+                self.selected = (self.selected + 1) % found.max(1);
+            }
+            KeyCode::Char('z') => {
+                if let Some(&addr) = emu.cheats.candidates().get(self.selected) {
+                    if emu.cheats.is_frozen(addr) {
+                        emu.cheats.unfreeze(addr);
+                    } else {
+                        emu.cheats.freeze(addr, emu.memory[addr]);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn search(&mut self, emu: &mut Chip8, line: &str) {
+        if let Ok(value) = u8::from_str_radix(line, 16) {
+            emu.cheats.search(&emu.memory, value);
+            self.selected = 0;
+        }
+    }
+
+    // `target=value`, an edited byte of memory is scrolled into view
+    fn edit(&mut self, emu: &mut Chip8, line: &str) {
+        let parsed = line.split_once('=').and_then(|(target, value)| {
+            let value = u32::from_str_radix(value.trim_start_matches("0x"), 16).ok()?;
+            Some((Target::parse(target)?, value))
+        });
+        let Some((target, value)) = parsed else {
+            self.error = Some(format!("Can't read `{}`, try 2f0=9 or va=3", line));
+            return;
+        };
+        if !debugger::poke(emu, target, value) {
+            self.error = Some(format!("`{}` is out of range", line));
+            return;
+        }
+        if let Target::Memory(addr) = target {
+            self.follow_index = false;
+            self.scroll = (addr / BYTES_PER_ROW).min(MEMORY_ROWS - 1);
+        }
+    }
+
+    fn scroll_by(&mut self, rows: isize) {
+        self.follow_index = false;
+        self.scroll = self.scroll.saturating_add_signed(rows).min(MEMORY_ROWS - 1);
+    }
+}
+
+impl Drop for TuiDebugger {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+        let _ = self.terminal.show_cursor();
+    }
+}
+
+fn draw_cpu(frame: &mut Frame, area: Rect, cpu: &CpuState, paused: bool, breakpoints: &[u16]) {
+    let label = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("PC ", label),
+            Span::raw(format!("{:#05x}  ", cpu.pc)),
+            Span::styled("I ", label),
+            Span::raw(format!("{:#05x}  ", cpu.index)),
+            Span::styled("SP ", label),
+            Span::raw(format!("{}", cpu.sp)),
+        ]),
+        Line::from(vec![
+            Span::styled("DT ", label),
+            Span::raw(format!("{:02x}     ", cpu.delay_timer)),
+            Span::styled("ST ", label),
+            Span::raw(format!("{:02x}", cpu.sound_timer)),
+        ]),
+        Line::from(""),
+    ];
+
+    for (row, values) in cpu.registers.chunks(4).enumerate() {
+        let spans = values
+            .iter()
+            .enumerate()
+            .flat_map(|(col, value)| {
+                [
+                    Span::styled(format!("V{:X} ", row * 4 + col), label),
+                    Span::raw(format!("{:02x}  ", value)),
+                ]
+            })
+            .collect::<Vec<_>>();
+        lines.push(Line::from(spans));
+    }
+
+    lines.push(Line::from(""));
+    let marker = if breakpoints.contains(&cpu.pc) {
+        "*"
+    } else {
+        " "
+    };
+    lines.push(Line::from(vec![
+        Span::styled(marker, Style::default().fg(Color::Red)),
+        Span::styled(
+            format!("{:04x} {}", cpu.opcode, cpu.instruction()),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+    ]));
+    lines.push(Line::styled(
+        if paused { "paused" } else { "running" },
+        label,
+    ));
+
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("CPU")),
+        area,
+    );
+}
+
+fn draw_stack(frame: &mut Frame, area: Rect, cpu: &CpuState) {
+    // Most recent call on top
+    let lines = cpu
+        .stack
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(depth, addr)| Line::raw(format!("{:2}  {:#05x}", depth, addr)))
+        .collect::<Vec<_>>();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Stack")),
+        area,
+    );
+}
+
+// Keys a prompt takes as part of what is typed
+fn accepts(prompt: Prompt, c: char) -> bool {
+    match prompt {
+        Prompt::Search => c.is_ascii_hexdigit(),
+        Prompt::Edit => c.is_ascii_alphanumeric() || c == '=',
+    }
+}
+
+// What the cheats panel shows, gathered before drawing like the CPU state
+struct CheatsView<'a> {
+    cheats: &'a Cheats,
+    memory: &'a [u8],
+    search: Option<&'a str>,
+    selected: usize,
+}
+
+// The search being typed or how many addresses are left, then the addresses with their values.
+// The selected one is reversed and frozen ones are marked in red.
+fn draw_cheats(frame: &mut Frame, area: Rect, view: &CheatsView) {
+    let label = Style::default().fg(Color::DarkGray);
+    let candidates = view.cheats.candidates();
+    let mut lines = vec![match view.search {
+        Some(search) => Line::from(vec![
+            Span::styled("search ", label),
+            Span::raw(format!("{}_", search)),
+        ]),
+        None if candidates.is_empty() => Line::styled("/ to search", label),
+        None => Line::styled(format!("{} found", candidates.len()), label),
+    }];
+
+    let visible = area.height.saturating_sub(3) as usize;
+    let first = view.selected.saturating_sub(visible.saturating_sub(1));
+    for (i, &addr) in candidates.iter().enumerate().skip(first).take(visible) {
+        let style = if i == view.selected {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        let marker = if view.cheats.is_frozen(addr) {
+            "*"
+        } else {
+            " "
+        };
+        lines.push(Line::from(vec![
+            Span::styled(marker, Style::default().fg(Color::Red)),
+            Span::styled(format!("{:#05x}  {:02x}", addr, view.memory[addr]), style),
+        ]));
+    }
+
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Cheats")),
+        area,
+    );
+}
+
+// The two bytes at PC are shown reversed and the byte at I in yellow
+fn draw_memory(frame: &mut Frame, area: Rect, memory: &[u8], scroll: usize, cpu: &CpuState) {
+    let pc = cpu.pc as usize;
+    let index = cpu.index as usize;
+    let visible = area.height.saturating_sub(2) as usize;
+
+    let lines = memory
+        .chunks(BYTES_PER_ROW)
+        .enumerate()
+        .skip(scroll)
+        .take(visible)
+        .map(|(row, bytes)| {
+            let start = row * BYTES_PER_ROW;
+            let mut spans = vec![Span::styled(
+                format!("{:03x}  ", start),
+                Style::default().fg(Color::DarkGray),
+            )];
+            for (offset, byte) in bytes.iter().enumerate() {
+                let addr = start + offset;
+                let style = if addr == pc || addr == pc + 1 {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else if addr == index {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+                spans.push(Span::styled(format!("{:02x}", byte), style));
+                spans.push(Span::raw(" "));
+            }
+            Line::from(spans)
+        })
+        .collect::<Vec<_>>();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Memory")),
+        area,
+    );
+}
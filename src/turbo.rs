@@ -0,0 +1,70 @@
+// Auto-fire for games that expect a button to be mashed. While a turbo key is held the keypad
+// key it drives is pressed and let go again `turbo_rate` times a second, counted in frames.
+
+use crate::Chip8;
+use crate::keypad::KEY_COUNT;
+use crate::pacing::FRAMES_PER_SECOND;
+
+// Presses a second
+pub const DEFAULT_TURBO_RATE: u32 = 10;
+
+#[derive(Debug, Clone)]
+pub struct Turbo {
+    // Frames from one press to the next, the key is down for the first half
+    period: u32,
+    // How many frames each turbo key has been held for
+    held: [Option<u32>; KEY_COUNT],
+}
+
+impl Turbo {
+    pub fn new(rate: u32) -> Self {
+        // Any faster and there would be no frame left to let go in
+        let period = (FRAMES_PER_SECOND as u32 / rate.max(1)).max(2);
+        Self {
+            period,
+            held: [None; KEY_COUNT],
+        }
+    }
+
+    // Letting go of a turbo key lets go of its keypad key too
+    pub fn hold(&mut self, emu: &mut Chip8, key: usize, held: bool) {
+        self.held[key] = held.then_some(0);
+        if !held {
+            emu.keypress(key, false);
+        }
+    }
+
+    // Call once per frame before running it
+    pub fn frame(&mut self, emu: &mut Chip8) {
+        for (key, frames) in self.held.iter_mut().enumerate() {
+            if let Some(frames) = frames {
+                emu.keypress(key, *frames % self.period < self.period / 2);
+                *frames += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turbo_key_pulses_while_held() {
+        let mut emu = Chip8::new();
+        // 30 presses a second, down one frame and up the next
+        let mut turbo = Turbo::new(30);
+        turbo.hold(&mut emu, 5, true);
+        let pulses: Vec<bool> = (0..4)
+            .map(|_| {
+                turbo.frame(&mut emu);
+                emu.keypad[5]
+            })
+            .collect();
+        assert_eq!(pulses, [true, false, true, false]);
+
+        turbo.hold(&mut emu, 5, false);
+        turbo.frame(&mut emu);
+        assert!(!emu.keypad[5]);
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt;
+
+// What `execute` does with an opcode no supported instruction set defines. ROMs that keep
+// data next to their code sometimes run into it, which doesn't have to be fatal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnUnknownOpcode {
+    #[default]
+    Panic,
+    // Carry on with the next instruction
+    Skip,
+    // Stop executing and report the opcode through `Chip8::halted`
+    Halt,
+    // Like `Skip`, but keep the opcode in `Chip8::skipped_opcodes` and the event log
+    Log,
+}
+
+impl OnUnknownOpcode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "panic" => Some(Self::Panic),
+            "skip" => Some(Self::Skip),
+            "halt" => Some(Self::Halt),
+            "log" => Some(Self::Log),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownOpcode {
+    pub opcode: u16,
+    pub pc: u16,
+}
+
+impl fmt::Display for UnknownOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Unimplemented opcode {:#06x} at {:#05x}",
+            self.opcode, self.pc
+        )
+    }
+}
+
+impl std::error::Error for UnknownOpcode {}
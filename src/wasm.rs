@@ -0,0 +1,82 @@
+// JavaScript bindings for running the emulator in a browser. The page owns the timing and
+// input, it calls `tick` once per animation frame and draws `framebuffer` on a canvas, see
+// examples/web.
+
+use wasm_bindgen::prelude::*;
+
+use crate::Chip8;
+use crate::unknown_opcode::OnUnknownOpcode;
+
+pub const DEFAULT_TICKS_PER_FRAME: usize = 10;
+
+#[wasm_bindgen]
+pub struct Emulator {
+    emu: Chip8,
+    ticks_per_frame: usize,
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        let mut emu = Chip8::new();
+        // A panic can't be caught in the browser, report bad opcodes through `tick` instead
+        emu.on_unknown_opcode = OnUnknownOpcode::Halt;
+        // Rewinding is up to the page, don't pay for the states
+        emu.rewind = None;
+
+        Self {
+            emu,
+            ticks_per_frame: DEFAULT_TICKS_PER_FRAME,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replaces whatever was running with a plain binary ROM
+    pub fn load_rom(&mut self, data: &[u8]) -> Result<(), JsError> {
+        self.emu.reset();
+        self.emu.load_rom_bytes(data)?;
+        Ok(())
+    }
+
+    pub fn set_ticks_per_frame(&mut self, ticks_per_frame: usize) {
+        self.ticks_per_frame = ticks_per_frame.max(1);
+    }
+
+    // Runs one 60 Hz frame, throws once the ROM halts
+    pub fn tick(&mut self) -> Result<(), JsError> {
+        self.emu.run_cycles(self.ticks_per_frame);
+        if let Some(error) = self.emu.halted.take() {
+            return Err(error.into());
+        }
+        self.emu.tick_timers();
+        Ok(())
+    }
+
+    pub fn key_event(&mut self, key: u8, pressed: bool) -> Result<(), JsError> {
+        self.emu.set_key(key, pressed)?;
+        Ok(())
+    }
+
+    pub fn width(&self) -> usize {
+        self.emu.display_width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.emu.display_height()
+    }
+
+    // `width() * height()` bytes row by row, 1 for a lit pixel
+    pub fn framebuffer(&self) -> Vec<u8> {
+        self.emu.get_display().iter().map(|&on| on as u8).collect()
+    }
+
+    pub fn sound_active(&self) -> bool {
+        self.emu.sound_active()
+    }
+}
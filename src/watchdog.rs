@@ -0,0 +1,63 @@
+use std::fmt::Write as _;
+
+use crate::Chip8;
+
+pub const DEFAULT_LIMIT: u64 = 5_000_000;
+
+// Counts instructions executed since the ROM last drew, touched a timer or read the keypad.
+// A ROM that does none of those for millions of instructions has almost certainly crashed.
+#[derive(Debug, Clone, Copy)]
+pub struct Watchdog {
+    pub limit: u64,
+    idle: u64,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new(DEFAULT_LIMIT)
+    }
+}
+
+impl Watchdog {
+    pub fn new(limit: u64) -> Self {
+        Self { limit, idle: 0 }
+    }
+
+    pub fn feed(&mut self) {
+        self.idle = 0;
+    }
+
+    pub fn step(&mut self) {
+        self.idle = self.idle.saturating_add(1);
+    }
+
+    pub fn idle_instructions(&self) -> u64 {
+        self.idle
+    }
+
+    pub fn tripped(&self) -> bool {
+        self.idle >= self.limit
+    }
+}
+
+pub fn diagnostics(emu: &Chip8) -> String {
+    let mut report = String::new();
+    let idle = emu.watchdog.map_or(0, |w| w.idle_instructions());
+
+    let _ = writeln!(
+        report,
+        "Watchdog: {} instructions without drawing, timer activity or key reads, the ROM looks stuck",
+        idle
+    );
+    let _ = writeln!(
+        report,
+        "PC: {:#05x}  I: {:#05x}  SP: {}",
+        emu.pc, emu.index, emu.sp
+    );
+    let _ = write!(report, "Recent instructions:");
+    for (pc, opcode) in &emu.history {
+        let _ = write!(report, " {:03x}:{:04x}", pc, opcode);
+    }
+
+    report
+}
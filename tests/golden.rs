@@ -0,0 +1,116 @@
+// Runs small ROMs written for these tests (public domain, like the rest of the bytes here) for a
+// fixed number of frames and compares the screen with a golden snapshot. A change to the
+// interpreter that alters what any of them draws shows up as a failing snapshot.
+
+use chip8_emu::Chip8;
+use chip8_emu::keypad::KEY_COUNT;
+
+const TICKS_PER_FRAME: usize = 10;
+
+struct Golden {
+    name: &'static str,
+    rom: &'static [u8],
+    frames: usize,
+    // The lit part of the screen from the top left, the rest is dark
+    snapshot: &'static [&'static str],
+}
+
+const GOLDENS: [Golden; 2] = [
+    // The font digits 0 to 3 side by side
+    Golden {
+        name: "digits",
+        rom: &[
+            0x60, 0x00, // V0 = 0
+            0x61, 0x00, // V1 = 0
+            0x62, 0x00, // V2 = 0
+            0xF2, 0x29, // I = digit V2
+            0xD0, 0x15, // draw at V0, V1
+            0x70, 0x05, // V0 += 5
+            0x72, 0x01, // V2 += 1
+            0x32, 0x04, // skip if V2 == 4
+            0x12, 0x06, // loop
+            0x12, 0x12, // halt
+        ],
+        frames: 10,
+        snapshot: &[
+            "####...#..####.####.",
+            "#..#..##.....#....#.",
+            "#..#...#..####.####.",
+            "#..#...#..#.......#.",
+            "####..###.####.####.",
+        ],
+    },
+    // Two zeros drawn over each other two pixels apart, the overlap XORs away
+    Golden {
+        name: "xor",
+        rom: &[
+            0x60, 0x00, // V0 = 0
+            0x61, 0x02, // V1 = 2
+            0x62, 0x00, // V2 = 0
+            0xF2, 0x29, // I = digit V2
+            0xD0, 0x25, // draw at V0, V2
+            0xD1, 0x25, // draw at V1, V2
+            0x12, 0x0C, // halt
+        ],
+        frames: 10,
+        #[rustfmt::skip]
+        snapshot: &[
+            "##..##",
+            "#.##.#",
+            "#.##.#",
+            "#.##.#",
+            "##..##",
+        ],
+    },
+];
+
+fn run(rom: &[u8], frames: usize) -> Chip8 {
+    let mut emu = Chip8::with_seed(0);
+    emu.load_rom_bytes(rom).unwrap();
+    for _ in 0..frames {
+        emu.run_frame(&[false; KEY_COUNT], TICKS_PER_FRAME);
+        assert!(emu.halted.is_none(), "halted: {:?}", emu.halted);
+    }
+    emu
+}
+
+// Pads the golden rows out to the full screen
+fn expected_snapshot(golden: &Golden, width: usize, height: usize) -> String {
+    (0..height)
+        .map(|row| {
+            let lit = golden.snapshot.get(row).copied().unwrap_or("");
+            format!("{:.<width$}\n", lit)
+        })
+        .collect()
+}
+
+#[test]
+fn goldens_match() {
+    for golden in &GOLDENS {
+        let emu = run(golden.rom, golden.frames);
+        let expected = expected_snapshot(golden, emu.display_width(), emu.display_height());
+        assert_eq!(
+            emu.display_snapshot(),
+            expected,
+            "{} drew something else",
+            golden.name
+        );
+    }
+}
+
+#[test]
+fn xor_overlap_sets_vf() {
+    let emu = run(GOLDENS[1].rom, GOLDENS[1].frames);
+    assert_eq!(emu.registers[0xF], 1);
+}
+
+#[test]
+fn frame_hash_follows_the_picture() {
+    let digits = run(GOLDENS[0].rom, GOLDENS[0].frames);
+    let again = run(GOLDENS[0].rom, GOLDENS[0].frames);
+    let xor = run(GOLDENS[1].rom, GOLDENS[1].frames);
+
+    assert_eq!(digits.frame_hash(), again.frame_hash());
+    assert_ne!(digits.frame_hash(), xor.frame_hash());
+    assert_ne!(digits.frame_hash(), Chip8::new().frame_hash());
+}